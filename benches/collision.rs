@@ -0,0 +1,51 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use pikuma_game_engine::components_systems::{
+    BroadPhase, ColliderShape, CollisionComponent, CollisionSystem, RigidBodyComponent,
+};
+use pikuma_game_engine::ecs::Registry;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A tilemap's worth of touching 32x32 colliders.
+fn tilemap(broad_phase: BroadPhase, side: usize) -> Registry {
+    let mut registry = Registry::new();
+    let mut collision_system = CollisionSystem::new();
+    collision_system.set_broad_phase(broad_phase);
+    registry.add_system(Rc::new(RefCell::new(collision_system)));
+    for y in 0..side {
+        for x in 0..side {
+            registry
+                .entity_builder()
+                .with(RigidBodyComponent::at(glam::Vec2::new(
+                    x as f32 * 40.0,
+                    y as f32 * 40.0,
+                )))
+                .with(CollisionComponent {
+                    offset: glam::Vec2::ZERO,
+                    shape: ColliderShape::Rectangle {
+                        width_height: glam::Vec2::new(32.0, 32.0),
+                    },
+                })
+                .build();
+        }
+    }
+    registry
+}
+
+fn bench_broad_phase(c: &mut Criterion) {
+    let mut group = c.benchmark_group("collision");
+    for side in [10, 30] {
+        for broad_phase in [BroadPhase::BruteForce, BroadPhase::SpatialHash] {
+            let mut registry = tilemap(broad_phase, side);
+            group.bench_with_input(
+                BenchmarkId::new(format!("{broad_phase:?}"), side * side),
+                &side,
+                |b, _| b.iter(|| registry.run_system::<CollisionSystem>(()).unwrap()),
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_broad_phase);
+criterion_main!(benches);
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+// Playing sound needs an audio output library, which isn't available everywhere.
+#[cfg(feature = "audio")]
+mod playback;
+#[cfg(feature = "audio")]
+pub use playback::{AudioEngine, AudioError, AudioHandler, Clip, SoundSystem};
+
+/// Loaded clips by path, so each file is only loaded and decoded once.
+pub struct ClipCache<C> {
+    clips: HashMap<PathBuf, Arc<C>>,
+}
+
+impl<C> ClipCache<C> {
+    pub fn new() -> Self {
+        Self {
+            clips: HashMap::new(),
+        }
+    }
+
+    /// The clip at the path, loading it with `load` the first time.
+    /// Failed loads aren't cached, so they're tried again next time.
+    pub fn get<E>(
+        &mut self,
+        path: &Path,
+        load: impl FnOnce(&Path) -> Result<C, E>,
+    ) -> Result<Arc<C>, E> {
+        if let Some(clip) = self.clips.get(path) {
+            return Ok(Arc::clone(clip));
+        }
+        let clip = Arc::new(load(path)?);
+        self.clips.insert(path.to_path_buf(), Arc::clone(&clip));
+        Ok(clip)
+    }
+
+    pub fn len(&self) -> usize {
+        self.clips.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clips.is_empty()
+    }
+}
+
+impl<C> Default for ClipCache<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Limits how many sounds start in one frame,
+/// so many collisions in the same frame don't stack into one deafening burst.
+pub struct PlayLimiter {
+    max_plays: usize,
+    plays: usize,
+}
+
+impl PlayLimiter {
+    pub fn new(max_plays: usize) -> Self {
+        Self {
+            max_plays,
+            plays: 0,
+        }
+    }
+
+    /// Call once per frame, so sounds can play again.
+    pub fn new_frame(&mut self) {
+        self.plays = 0;
+    }
+
+    /// Whether a sound may start this frame. If so, it counts towards the limit.
+    pub fn try_play(&mut self) -> bool {
+        if self.plays >= self.max_plays {
+            return false;
+        }
+        self.plays += 1;
+        true
+    }
+}
+
+/// Which event plays an entity's sound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SoundTrigger {
+    /// The entity collided with another entity.
+    Collision,
+    /// The entity took damage.
+    Damage,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SoundComponent {
+    pub clip: PathBuf,
+    pub trigger: SoundTrigger,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClipCache, PlayLimiter};
+    use std::path::Path;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_play_limiter() {
+        let mut play_limiter = PlayLimiter::new(2);
+        // Five collisions in the same frame only play twice.
+        let plays = (0..5).filter(|_| play_limiter.try_play()).count();
+        assert_eq!(plays, 2);
+        assert!(!play_limiter.try_play());
+        // Sounds can play again next frame.
+        play_limiter.new_frame();
+        assert!(play_limiter.try_play());
+        assert!(play_limiter.try_play());
+        assert!(!play_limiter.try_play());
+    }
+
+    #[test]
+    fn test_clip_cache() {
+        let mut loads = 0;
+        let mut load = |path: &Path| {
+            loads += 1;
+            if path == Path::new("missing.wav") {
+                Err("missing")
+            } else {
+                Ok(path.display().to_string())
+            }
+        };
+        let mut clip_cache = ClipCache::new();
+        let a = clip_cache.get(Path::new("a.wav"), &mut load).unwrap();
+        let b = clip_cache.get(Path::new("a.wav"), &mut load).unwrap();
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(clip_cache.len(), 1);
+        assert!(clip_cache.get(Path::new("missing.wav"), &mut load).is_err());
+        assert!(clip_cache.get(Path::new("missing.wav"), &mut load).is_err());
+        assert_eq!(clip_cache.len(), 1);
+        assert_eq!(loads, 3);
+    }
+}
@@ -0,0 +1,253 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use rodio::Source;
+
+use super::{ClipCache, PlayLimiter, SoundComponent, SoundTrigger};
+use crate::{
+    components_systems::{CollisionEvent, DamageEvent},
+    ecs::{Entity, EntityComponentWrapper},
+    event_bus::Handler,
+    impl_handler_base,
+};
+
+#[derive(Debug)]
+pub enum AudioError {
+    /// A sound file couldn't be opened.
+    Io {
+        path: PathBuf,
+        message: String,
+    },
+    /// A sound file isn't in a supported format.
+    Decode {
+        path: PathBuf,
+        message: String,
+    },
+    /// There's no audio output device.
+    NoOutput(rodio::StreamError),
+    Play(rodio::PlayError),
+}
+
+/// A fully decoded sound. Clips are shared, so playing one again doesn't copy the samples.
+#[derive(Debug)]
+pub struct Clip {
+    channels: u16,
+    sample_rate: u32,
+    samples: Arc<[i16]>,
+}
+
+impl Clip {
+    pub fn load(path: &Path) -> Result<Self, AudioError> {
+        let file = std::fs::File::open(path).map_err(|e| AudioError::Io {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })?;
+        let decoder =
+            rodio::Decoder::new(std::io::BufReader::new(file)).map_err(|e| AudioError::Decode {
+                path: path.to_path_buf(),
+                message: e.to_string(),
+            })?;
+        Ok(Self {
+            channels: decoder.channels(),
+            sample_rate: decoder.sample_rate(),
+            samples: decoder.collect(),
+        })
+    }
+
+    pub fn duration(&self) -> std::time::Duration {
+        let frames = self.samples.len() as f64 / self.channels as f64;
+        std::time::Duration::from_secs_f64(frames / self.sample_rate as f64)
+    }
+
+    fn source(&self) -> ClipSource {
+        ClipSource {
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+            samples: Arc::clone(&self.samples),
+            position: 0,
+        }
+    }
+}
+
+/// Plays a clip's samples from the start.
+#[derive(Clone)]
+struct ClipSource {
+    channels: u16,
+    sample_rate: u32,
+    samples: Arc<[i16]>,
+    position: usize,
+}
+
+impl Iterator for ClipSource {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let sample = self.samples.get(self.position).copied();
+        self.position += 1;
+        sample
+    }
+}
+
+impl Source for ClipSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.samples.len().saturating_sub(self.position))
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<std::time::Duration> {
+        None
+    }
+}
+
+/// Plays sounds on the default output device.
+/// Insert it as a registry resource so handlers such as `SoundSystem` can use it.
+pub struct AudioEngine {
+    // Sound stops when the stream is dropped, so it's kept even though it's never used.
+    _stream: rodio::OutputStream,
+    stream_handle: rodio::OutputStreamHandle,
+    clips: ClipCache<Clip>,
+    music: Option<rodio::Sink>,
+}
+
+impl AudioEngine {
+    pub fn new() -> Result<Self, AudioError> {
+        let (stream, stream_handle) =
+            rodio::OutputStream::try_default().map_err(AudioError::NoOutput)?;
+        Ok(Self {
+            _stream: stream,
+            stream_handle,
+            clips: ClipCache::new(),
+            music: None,
+        })
+    }
+
+    /// Play a clip once. Several clips can play at the same time.
+    pub fn play(&mut self, path: impl AsRef<Path>) -> Result<(), AudioError> {
+        let clip = self.clips.get(path.as_ref(), Clip::load)?;
+        self.stream_handle
+            .play_raw(clip.source().convert_samples())
+            .map_err(AudioError::Play)
+    }
+
+    /// Loop a clip as background music, replacing any music already playing.
+    pub fn play_music(&mut self, path: impl AsRef<Path>) -> Result<(), AudioError> {
+        let clip = self.clips.get(path.as_ref(), Clip::load)?;
+        let sink = rodio::Sink::try_new(&self.stream_handle).map_err(AudioError::Play)?;
+        sink.append(clip.source().repeat_infinite());
+        self.music = Some(sink);
+        Ok(())
+    }
+
+    pub fn stop_music(&mut self) {
+        self.music = None;
+    }
+}
+
+/// Plays the sounds of entities with a `SoundComponent` when their trigger happens.
+/// Does nothing unless there's an `AudioEngine` resource.
+pub struct SoundSystem {}
+
+impl SoundSystem {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn play(ec_manager: &mut EntityComponentWrapper, entity: Entity, trigger: SoundTrigger) {
+        let Ok(Some(sound_component)) = ec_manager.get_component::<SoundComponent>(entity) else {
+            return;
+        };
+        if sound_component.trigger != trigger {
+            return;
+        }
+        let clip = sound_component.clip.clone();
+        if let Some(audio_engine) = ec_manager.get_resource_mut::<AudioEngine>() {
+            if let Err(e) = audio_engine.play(&clip) {
+                log::warn!("Couldn't play {}: {:?}", clip.display(), e);
+            }
+        }
+    }
+}
+
+impl Default for SoundSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl_handler_base!(SoundSystem: CollisionEvent, DamageEvent);
+
+impl Handler<CollisionEvent> for SoundSystem {
+    fn handle(&mut self, ec_manager: &mut EntityComponentWrapper, event: &CollisionEvent) {
+        Self::play(ec_manager, event.entity_a, SoundTrigger::Collision);
+        Self::play(ec_manager, event.entity_b, SoundTrigger::Collision);
+    }
+}
+
+impl Handler<DamageEvent> for SoundSystem {
+    fn handle(&mut self, ec_manager: &mut EntityComponentWrapper, event: &DamageEvent) {
+        Self::play(ec_manager, event.target, SoundTrigger::Damage);
+    }
+}
+
+/// Plays a clip, such as an explosion, whenever two entities collide.
+/// Does nothing unless there's an `AudioEngine` resource.
+pub struct AudioHandler {
+    clip: PathBuf,
+    play_limiter: PlayLimiter,
+}
+
+impl AudioHandler {
+    pub fn new(clip: impl Into<PathBuf>) -> Self {
+        Self {
+            clip: clip.into(),
+            play_limiter: PlayLimiter::new(2),
+        }
+    }
+
+    pub fn set_play_limit(&mut self, max_plays: usize) {
+        self.play_limiter = PlayLimiter::new(max_plays);
+    }
+
+    /// Call once per frame, so the play limit starts over.
+    pub fn new_frame(&mut self) {
+        self.play_limiter.new_frame();
+    }
+}
+
+impl_handler_base!(AudioHandler: CollisionEvent);
+
+impl Handler<CollisionEvent> for AudioHandler {
+    fn handle(&mut self, ec_manager: &mut EntityComponentWrapper, _event: &CollisionEvent) {
+        let Some(audio_engine) = ec_manager.get_resource_mut::<AudioEngine>() else {
+            return;
+        };
+        if !self.play_limiter.try_play() {
+            return;
+        }
+        if let Err(e) = audio_engine.play(&self.clip) {
+            log::warn!("Couldn't play {}: {:?}", self.clip.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Clip;
+    use std::path::Path;
+    use std::time::Duration;
+
+    #[test]
+    fn test_clip_load() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/sounds/helicopter.wav");
+        let clip = Clip::load(&path).unwrap();
+        assert!(clip.duration() > Duration::ZERO);
+        assert!(Clip::load(Path::new("missing.wav")).is_err());
+    }
+}
@@ -1,26 +1,76 @@
-use std::collections::HashSet;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 
+use serde::{Deserialize, Serialize};
 use winit::keyboard::{KeyCode, PhysicalKey};
 
 use crate::{
-    ecs::{Entity, EntityComponentWrapper, System, SystemBase},
-    event_bus::{Handler, HandlerBase},
-    renderer::{Camera, Renderer, SpriteIndex},
+    ecs::{EcsError, Entity, EntityComponentWrapper, Registry, System, SystemBase},
+    event_bus::Handler,
+    gamepad::StickState,
+    geometry::{Circle, Rectangle, Shape},
+    impl_handler_base,
+    renderer::{
+        AlphaMode, Camera, DrawImage, RectangleStyle, Renderer, Rotation, Sprite, SpriteIndex,
+        SpriteOrientation,
+    },
 };
 
 ///////////////////////////////////////////////////////////////////////////////
 // RigidBody / Movement
 ///////////////////////////////////////////////////////////////////////////////
 
-#[derive(Clone)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct RigidBodyComponent {
     pub position: glam::Vec2,
     pub velocity: glam::Vec2,
+    #[serde(default)]
+    pub acceleration: glam::Vec2,
+    /// Whether the `Gravity` resource is added to the acceleration.
+    #[serde(default)]
+    pub affected_by_gravity: bool,
+    /// If set, `MovementSystem` keeps the speed at or below this, however long it accelerates.
+    #[serde(default)]
+    pub max_speed: Option<f32>,
+}
+
+impl RigidBodyComponent {
+    /// A body at rest at the position, unaffected by gravity.
+    pub fn at(position: glam::Vec2) -> Self {
+        Self {
+            position,
+            ..Self::default()
+        }
+    }
+}
+
+/// Acceleration applied to every rigid body that is affected by gravity,
+/// stored as a registry resource. Without the resource, there is no gravity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gravity(pub glam::Vec2);
+
+/// The edges of the world, stored as a registry resource.
+/// See `MovementSystem::set_wraparound`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldBounds(pub Rectangle);
+
+/// How MovementSystem integrates acceleration and velocity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Integrator {
+    /// Update position from the old velocity, then update velocity.
+    #[default]
+    ExplicitEuler,
+    /// Update velocity first, then update position from the new velocity.
+    /// This is more stable for springy or orbital motion.
+    SemiImplicitEuler,
 }
 
 pub struct MovementSystem {
     required_components: HashSet<std::any::TypeId>,
     entities: HashSet<Entity>,
+    integrator: Integrator,
+    wraparound: bool,
 }
 
 impl MovementSystem {
@@ -30,8 +80,26 @@ impl MovementSystem {
         Self {
             required_components,
             entities: HashSet::new(),
+            integrator: Integrator::default(),
+            wraparound: false,
         }
     }
+
+    pub fn set_integrator(&mut self, integrator: Integrator) {
+        self.integrator = integrator;
+    }
+
+    /// Wrap entities that leave the `WorldBounds` resource around to the opposite edge,
+    /// as in Asteroids. Does nothing if there is no `WorldBounds` resource.
+    pub fn set_wraparound(&mut self, wraparound: bool) {
+        self.wraparound = wraparound;
+    }
+}
+
+impl Default for MovementSystem {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SystemBase for MovementSystem {
@@ -50,16 +118,252 @@ impl SystemBase for MovementSystem {
     fn remove_entity(&mut self, entity: Entity) {
         self.entities.remove(&entity);
     }
+
+    fn entities(&self) -> Vec<Entity> {
+        self.entities.iter().copied().collect()
+    }
 }
 
 impl System for MovementSystem {
     type Input<'i> = f32;
 
     fn run(&self, ec_manager: &mut EntityComponentWrapper, delta_time: Self::Input<'_>) {
+        let world_bounds: Option<WorldBounds> = if self.wraparound {
+            ec_manager.get_resource::<WorldBounds>().copied()
+        } else {
+            None
+        };
+        let gravity: glam::Vec2 = ec_manager
+            .get_resource::<Gravity>()
+            .map_or(glam::Vec2::ZERO, |Gravity(gravity)| *gravity);
         for entity in self.entities.iter() {
             let rigid_body_component: &mut RigidBodyComponent =
                 ec_manager.get_component_mut(*entity).unwrap().unwrap();
-            rigid_body_component.position += rigid_body_component.velocity * delta_time;
+            let acceleration = if rigid_body_component.affected_by_gravity {
+                rigid_body_component.acceleration + gravity
+            } else {
+                rigid_body_component.acceleration
+            };
+            let max_speed = rigid_body_component.max_speed.unwrap_or(f32::INFINITY);
+            match self.integrator {
+                Integrator::ExplicitEuler => {
+                    rigid_body_component.position += rigid_body_component.velocity * delta_time;
+                    rigid_body_component.velocity = (rigid_body_component.velocity
+                        + acceleration * delta_time)
+                        .clamp_length_max(max_speed);
+                }
+                Integrator::SemiImplicitEuler => {
+                    rigid_body_component.velocity = (rigid_body_component.velocity
+                        + acceleration * delta_time)
+                        .clamp_length_max(max_speed);
+                    rigid_body_component.position += rigid_body_component.velocity * delta_time;
+                }
+            }
+            if let Some(WorldBounds(bounds)) = world_bounds {
+                rigid_body_component.position = bounds.wrap(rigid_body_component.position);
+            }
+        }
+    }
+}
+
+/// Move an entity to a new position without it looking like it moved there.
+/// The motion animation forgets which way the entity was facing and restarts,
+/// so a respawned entity doesn't keep animating in its old direction.
+pub fn teleport(
+    registry: &mut Registry,
+    entity: Entity,
+    position: glam::Vec2,
+) -> Result<(), EcsError> {
+    let rigid_body_component: &mut RigidBodyComponent = registry
+        .get_component_mut(entity)?
+        .ok_or(EcsError::NoSuchComponent)?;
+    rigid_body_component.position = position;
+    if let Some(motion_animation_component) =
+        registry.get_component_mut::<MotionAnimationComponent>(entity)?
+    {
+        motion_animation_component.last_velocity = glam::Vec2::ZERO;
+        motion_animation_component.current_frame = 0;
+        motion_animation_component.current_frame_time = 0.0;
+    }
+    Ok(())
+}
+
+/// The entity with a `T` component whose rigid body is nearest to `from`, such as the player
+/// for an enemy to chase. Entities farther than `max_range` are ignored.
+pub fn nearest_with<T: Clone + 'static>(
+    registry: &Registry,
+    from: glam::Vec2,
+    max_range: Option<f32>,
+) -> Option<Entity> {
+    let required_components = [
+        std::any::TypeId::of::<RigidBodyComponent>(),
+        std::any::TypeId::of::<T>(),
+    ];
+    registry
+        .entities_and_components()
+        .filter(|(_, components)| {
+            required_components
+                .iter()
+                .all(|component| components.contains(component))
+        })
+        .map(|(entity, _)| {
+            let rigid_body_component: &RigidBodyComponent =
+                registry.get_component(*entity).unwrap().unwrap();
+            (
+                *entity,
+                rigid_body_component.position.distance_squared(from),
+            )
+        })
+        .filter(|(_, distance_squared)| {
+            max_range.is_none_or(|max_range| *distance_squared <= max_range.powi(2))
+        })
+        // Break ties by entity, so the result doesn't depend on hash order.
+        .min_by(
+            |(entity0, distance_squared0), (entity1, distance_squared1)| {
+                distance_squared0
+                    .total_cmp(distance_squared1)
+                    .then(entity0.cmp(entity1))
+            },
+        )
+        .map(|(entity, _)| entity)
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Hierarchy
+///////////////////////////////////////////////////////////////////////////////
+
+/// Removes the entity and all its descendants, as described by ParentComponents.
+pub fn remove_entity_recursive(registry: &mut Registry, entity: Entity) -> Result<(), EcsError> {
+    if registry.is_dead(entity) {
+        return Err(EcsError::DeadEntity);
+    }
+    let mut children: HashMap<Entity, Vec<Entity>> = HashMap::new();
+    for child in registry.entities() {
+        if let Ok(Some(parent_component)) = registry.get_component::<ParentComponent>(*child) {
+            children
+                .entry(parent_component.parent)
+                .or_default()
+                .push(*child);
+        }
+    }
+    // Hierarchies shouldn't have cycles, but track visited entities to be safe.
+    let mut visited: HashSet<Entity> = HashSet::new();
+    let mut to_remove: Vec<Entity> = vec![entity];
+    while let Some(entity) = to_remove.pop() {
+        if !visited.insert(entity) {
+            continue;
+        }
+        if let Some(entity_children) = children.get(&entity) {
+            to_remove.extend(entity_children);
+        }
+        registry.remove_entity(entity)?;
+    }
+    Ok(())
+}
+
+/// Attaches an entity to a parent entity, such as a turret to a tank.
+/// See `HierarchySystem` and `remove_entity_recursive`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ParentComponent {
+    pub parent: Entity,
+    pub local_offset: glam::Vec2,
+}
+
+/// Moves children to their parent's position plus their local offset.
+/// Run it after the `MovementSystem`, so children follow where their parents moved to.
+/// Children of dead parents, or parents without a rigid body, are detached and stay where they are.
+pub struct HierarchySystem {
+    required_components: HashSet<std::any::TypeId>,
+    entities: HashSet<Entity>,
+}
+
+impl HierarchySystem {
+    pub fn new() -> Self {
+        let mut required_components = HashSet::new();
+        required_components.insert(std::any::TypeId::of::<RigidBodyComponent>());
+        required_components.insert(std::any::TypeId::of::<ParentComponent>());
+        Self {
+            required_components,
+            entities: HashSet::new(),
+        }
+    }
+
+    /// How many ancestors the entity has. Parents must be positioned before their children.
+    fn depth(ec_manager: &EntityComponentWrapper, entity: Entity) -> usize {
+        let mut depth = 0;
+        let mut entity = entity;
+        while let Ok(Some(parent_component)) = ec_manager.get_component::<ParentComponent>(entity) {
+            depth += 1;
+            entity = parent_component.parent;
+            // Hierarchies shouldn't have cycles, but don't loop forever if one does.
+            if depth > ec_manager.entity_count() {
+                break;
+            }
+        }
+        depth
+    }
+}
+
+impl Default for HierarchySystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemBase for HierarchySystem {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn required_components(&self) -> &HashSet<std::any::TypeId> {
+        &self.required_components
+    }
+
+    fn add_entity(&mut self, entity: Entity) {
+        self.entities.insert(entity);
+    }
+
+    fn remove_entity(&mut self, entity: Entity) {
+        self.entities.remove(&entity);
+    }
+
+    fn entities(&self) -> Vec<Entity> {
+        self.entities.iter().copied().collect()
+    }
+}
+
+impl System for HierarchySystem {
+    type Input<'i> = ();
+
+    fn run(&self, ec_manager: &mut EntityComponentWrapper, _input: Self::Input<'_>) {
+        let mut entities: Vec<(usize, Entity)> = self
+            .entities
+            .iter()
+            .map(|entity| (Self::depth(ec_manager, *entity), *entity))
+            .collect();
+        entities.sort();
+        for (_, entity) in entities {
+            let parent_component: &ParentComponent =
+                ec_manager.get_component(entity).unwrap().unwrap();
+            let local_offset = parent_component.local_offset;
+            let parent_position: Option<glam::Vec2> = match ec_manager
+                .get_component::<RigidBodyComponent>(parent_component.parent)
+            {
+                Ok(Some(parent_rigid_body_component)) => Some(parent_rigid_body_component.position),
+                _ => None,
+            };
+            match parent_position {
+                Some(parent_position) => {
+                    let rigid_body_component: &mut RigidBodyComponent =
+                        ec_manager.get_component_mut(entity).unwrap().unwrap();
+                    rigid_body_component.position = parent_position + local_offset;
+                }
+                None => {
+                    ec_manager
+                        .remove_component::<ParentComponent>(entity)
+                        .unwrap();
+                }
+            }
         }
     }
 }
@@ -68,7 +372,7 @@ impl System for MovementSystem {
 // Sprite / Render
 ///////////////////////////////////////////////////////////////////////////////
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Layer {
     Background,
     Ground,
@@ -85,11 +389,41 @@ impl Layer {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SpriteComponent {
     pub sprite_index: SpriteIndex,
     pub sprite_layer: Layer,
     pub size: glam::Vec2,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    /// Rotation is applied before flipping.
+    pub rotation: Rotation,
+    pub alpha_mode: AlphaMode,
+    /// Multiplies the sprite's color, for hit flashes or team colors. White leaves it unchanged.
+    pub tint: glam::Vec4,
+}
+
+impl SpriteComponent {
+    pub fn new(sprite_index: SpriteIndex, sprite_layer: Layer, size: glam::Vec2) -> Self {
+        Self {
+            sprite_index,
+            sprite_layer,
+            size,
+            flip_x: false,
+            flip_y: false,
+            rotation: Rotation::None,
+            alpha_mode: AlphaMode::Blend,
+            tint: glam::Vec4::ONE,
+        }
+    }
+
+    fn orientation(&self) -> SpriteOrientation {
+        SpriteOrientation {
+            flip_x: self.flip_x,
+            flip_y: self.flip_y,
+            rotation: self.rotation,
+        }
+    }
 }
 
 pub struct RenderSystem {
@@ -109,6 +443,12 @@ impl RenderSystem {
     }
 }
 
+impl Default for RenderSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SystemBase for RenderSystem {
     fn as_any(&self) -> &dyn std::any::Any {
         self
@@ -125,13 +465,34 @@ impl SystemBase for RenderSystem {
     fn remove_entity(&mut self, entity: Entity) {
         self.entities.remove(&entity);
     }
+
+    fn entities(&self) -> Vec<Entity> {
+        self.entities.iter().copied().collect()
+    }
+}
+
+/// Orders sprites for drawing.
+///
+/// The depth buffer puts opaque and cutout sprites in the right layer, whatever order they're
+/// drawn in, so they aren't sorted. Blended sprites are drawn after them, back to front, so their
+/// partly transparent pixels are blended with everything below them.
+pub fn draw_order<'a>(
+    components: Vec<(&'a RigidBodyComponent, &'a SpriteComponent)>,
+) -> Vec<(&'a RigidBodyComponent, &'a SpriteComponent)> {
+    let (mut blended, mut ordered): (Vec<_>, Vec<_>) = components
+        .into_iter()
+        .partition(|(_, sprite_component)| sprite_component.alpha_mode == AlphaMode::Blend);
+    blended.sort_by(|(_, a), (_, b)| a.sprite_layer.as_z().total_cmp(&b.sprite_layer.as_z()));
+    ordered.append(&mut blended);
+    ordered
 }
 
 impl System for RenderSystem {
     type Input<'i> = &'i mut Renderer;
 
     fn run(&self, ec_manager: &mut EntityComponentWrapper, renderer: Self::Input<'_>) {
-        let mut components: Vec<(&RigidBodyComponent, &SpriteComponent)> = self
+        let visible_bounds = renderer.visible_bounds();
+        let components: Vec<(&RigidBodyComponent, &SpriteComponent)> = self
             .entities
             .iter()
             .map(|entity| {
@@ -141,20 +502,120 @@ impl System for RenderSystem {
                     ec_manager.get_component(*entity).unwrap().unwrap();
                 (rigid_body_component, sprite_component)
             })
+            // Don't draw sprites that are entirely off screen.
+            .filter(|(rigid_body_component, sprite_component)| {
+                Rectangle::from_top_left(rigid_body_component.position, sprite_component.size)
+                    .intersects(&visible_bounds)
+            })
             .collect();
-        components.sort_by(|a, b| {
-            a.1.sprite_layer
-                .as_z()
-                .partial_cmp(&b.1.sprite_layer.as_z())
-                .unwrap()
-        });
-        for (rigid_body_component, sprite_component) in components {
-            renderer.draw_image(
-                sprite_component.sprite_index,
-                sprite_component.sprite_layer.as_z(),
-                rigid_body_component.position,
-                sprite_component.size,
-            );
+        for (rigid_body_component, sprite_component) in draw_order(components) {
+            renderer.draw_image(DrawImage {
+                sprite_index: sprite_component.sprite_index,
+                z: sprite_component.sprite_layer.as_z(),
+                location: rigid_body_component.position,
+                size: sprite_component.size,
+                orientation: sprite_component.orientation(),
+                alpha_mode: sprite_component.alpha_mode,
+                tint: sprite_component.tint,
+            });
+        }
+    }
+}
+
+/// A sprite positioned on the screen rather than in the world, for HUD elements.
+/// The position is in canvas pixels, so it doesn't move with the camera.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UiSpriteComponent {
+    pub sprite_index: SpriteIndex,
+    pub position: glam::Vec2,
+    pub size: glam::Vec2,
+    /// Multiplies the sprite's color, such as to gray out a disabled button.
+    #[serde(default = "white")]
+    pub tint: glam::Vec4,
+}
+
+impl UiSpriteComponent {
+    pub fn new(sprite_index: SpriteIndex, position: glam::Vec2, size: glam::Vec2) -> Self {
+        Self {
+            sprite_index,
+            position,
+            size,
+            tint: glam::Vec4::ONE,
+        }
+    }
+}
+
+/// A tint that leaves colors unchanged.
+fn white() -> glam::Vec4 {
+    glam::Vec4::ONE
+}
+
+/// Draws UI sprites on top of the world. Run it after the `RenderSystem`.
+/// This calls `Renderer::begin_ui`, so anything drawn afterward this frame is also UI.
+pub struct UiRenderSystem {
+    required_components: HashSet<std::any::TypeId>,
+    entities: HashSet<Entity>,
+}
+
+impl UiRenderSystem {
+    pub fn new() -> Self {
+        let mut required_components = HashSet::new();
+        required_components.insert(std::any::TypeId::of::<UiSpriteComponent>());
+        Self {
+            required_components,
+            entities: HashSet::new(),
+        }
+    }
+}
+
+impl Default for UiRenderSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemBase for UiRenderSystem {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn required_components(&self) -> &HashSet<std::any::TypeId> {
+        &self.required_components
+    }
+
+    fn add_entity(&mut self, entity: Entity) {
+        self.entities.insert(entity);
+    }
+
+    fn remove_entity(&mut self, entity: Entity) {
+        self.entities.remove(&entity);
+    }
+
+    fn entities(&self) -> Vec<Entity> {
+        self.entities.iter().copied().collect()
+    }
+}
+
+impl System for UiRenderSystem {
+    type Input<'i> = &'i mut Renderer;
+
+    fn run(&self, ec_manager: &mut EntityComponentWrapper, renderer: Self::Input<'_>) {
+        // Sprites are drawn in the order they're submitted, so later entities are drawn on top.
+        let mut entities: Vec<Entity> = self.entities.iter().copied().collect();
+        entities.sort();
+        renderer.begin_ui();
+        for entity in entities {
+            let ui_sprite_component: &UiSpriteComponent =
+                ec_manager.get_component(entity).unwrap().unwrap();
+            renderer.draw_image(DrawImage {
+                z: Layer::Air.as_z(),
+                tint: ui_sprite_component.tint,
+                ..DrawImage::new(
+                    ui_sprite_component.sprite_index,
+                    ui_sprite_component.position,
+                    ui_sprite_component.size,
+                )
+            });
         }
     }
 }
@@ -163,12 +624,26 @@ impl System for RenderSystem {
 // Animation
 ///////////////////////////////////////////////////////////////////////////////
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct AnimationComponent {
     pub frames: Vec<SpriteIndex>,
     pub frame_time: f32,
     pub current_frame: u32,
     pub current_frame_time: f32,
+    /// Start over after the last frame. Otherwise, stop on the last frame.
+    #[serde(default = "default_looping")]
+    pub looping: bool,
+    /// Set once a non-looping animation has shown its last frame for a full frame time.
+    #[serde(default)]
+    pub finished: bool,
+    /// Despawn the entity when the animation finishes.
+    #[serde(default)]
+    pub despawn_when_finished: bool,
+}
+
+/// Animations saved before they could stop kept looping.
+fn default_looping() -> bool {
+    true
 }
 
 impl AnimationComponent {
@@ -178,10 +653,69 @@ impl AnimationComponent {
             frame_time,
             current_frame: 0,
             current_frame_time: 0.0,
+            looping: true,
+            finished: false,
+            despawn_when_finished: false,
+        }
+    }
+
+    /// An animation that plays once, such as an explosion.
+    pub fn new_one_shot(frame_time: f32, frames: Vec<SpriteIndex>) -> Self {
+        Self {
+            looping: false,
+            ..Self::new(frame_time, frames)
         }
     }
 }
 
+/// Spawn an effect, such as an explosion, centered on position.
+/// It plays its frames once and then despawns. Panics if there are no frames.
+pub fn spawn_explosion(
+    registry: &mut Registry,
+    renderer: &mut Renderer,
+    position: glam::Vec2,
+    frames: &[Sprite],
+    frame_time: f32,
+) -> Entity {
+    assert!(!frames.is_empty(), "an explosion needs at least one frame");
+    let size = frames[0].width_height().as_vec2();
+    spawn_one_shot_animation(
+        registry,
+        position,
+        size,
+        renderer.preload(frames),
+        frame_time,
+    )
+}
+
+/// Like `spawn_explosion`, but with frames that are already loaded.
+pub fn spawn_one_shot_animation(
+    registry: &mut Registry,
+    position: glam::Vec2,
+    size: glam::Vec2,
+    frames: Vec<SpriteIndex>,
+    frame_time: f32,
+) -> Entity {
+    assert!(
+        !frames.is_empty(),
+        "a one shot animation needs at least one frame"
+    );
+    let sprite_component = SpriteComponent::new(frames[0], Layer::Air, size);
+    let mut animation_component = AnimationComponent::new_one_shot(frame_time, frames);
+    animation_component.despawn_when_finished = true;
+    registry
+        .entity_builder()
+        .with(RigidBodyComponent::at(position - size / 2.0))
+        .with(sprite_component)
+        .with(animation_component)
+        .build()
+}
+
+/// Dispatched once when a non-looping animation finishes.
+pub struct AnimationFinishedEvent {
+    pub entity: Entity,
+}
+
 pub struct AnimationSystem {
     required_components: HashSet<std::any::TypeId>,
     entities: HashSet<Entity>,
@@ -199,6 +733,12 @@ impl AnimationSystem {
     }
 }
 
+impl Default for AnimationSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SystemBase for AnimationSystem {
     fn as_any(&self) -> &dyn std::any::Any {
         self
@@ -215,6 +755,10 @@ impl SystemBase for AnimationSystem {
     fn remove_entity(&mut self, entity: Entity) {
         self.entities.remove(&entity);
     }
+
+    fn entities(&self) -> Vec<Entity> {
+        self.entities.iter().copied().collect()
+    }
 }
 
 impl System for AnimationSystem {
@@ -224,15 +768,32 @@ impl System for AnimationSystem {
         for entity in self.entities.iter() {
             let animation_component: &mut AnimationComponent =
                 ec_manager.get_component_mut(*entity).unwrap().unwrap();
+            if animation_component.finished {
+                continue;
+            }
             animation_component.current_frame_time += delta_time;
             let mut update_sprite_frame: Option<SpriteIndex> = None;
             if animation_component.current_frame_time > animation_component.frame_time {
                 animation_component.current_frame_time -= animation_component.frame_time;
-                animation_component.current_frame = (animation_component.current_frame + 1)
-                    % animation_component.frames.len() as u32;
+                let next_frame = animation_component.current_frame + 1;
+                if next_frame < animation_component.frames.len() as u32 {
+                    animation_component.current_frame = next_frame;
+                } else if animation_component.looping {
+                    animation_component.current_frame = 0;
+                } else {
+                    animation_component.finished = true;
+                }
                 update_sprite_frame =
                     Some(animation_component.frames[animation_component.current_frame as usize]);
             }
+            if animation_component.finished {
+                let despawn = animation_component.despawn_when_finished;
+                ec_manager.dispatch_event(AnimationFinishedEvent { entity: *entity });
+                if despawn {
+                    ec_manager.commands().despawn(*entity);
+                }
+                continue;
+            }
             if let Some(update_sprite_frame) = update_sprite_frame {
                 let sprite_component: &mut SpriteComponent =
                     ec_manager.get_component_mut(*entity).unwrap().unwrap();
@@ -242,7 +803,10 @@ impl System for AnimationSystem {
     }
 }
 
-#[derive(Clone)]
+/// Animation frames for each direction of movement.
+/// If one direction has no frames, the frames for the opposite direction are flipped instead,
+/// so a single right facing animation can be used for moving left too.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct MotionAnimationComponent {
     pub left_frames: Vec<SpriteIndex>,
     pub down_frames: Vec<SpriteIndex>,
@@ -252,6 +816,12 @@ pub struct MotionAnimationComponent {
     pub frame_time: f32,
     pub current_frame: u32,
     pub current_frame_time: f32,
+    /// The flips last applied to the sprite for the opposite direction's frames.
+    /// They're undone when the direction changes, leaving any flips set on the sprite itself.
+    #[serde(default)]
+    pub mirrored_x: bool,
+    #[serde(default)]
+    pub mirrored_y: bool,
 }
 
 impl MotionAnimationComponent {
@@ -271,6 +841,8 @@ impl MotionAnimationComponent {
             current_frame: 0,
             current_frame_time: 0.0,
             last_velocity: glam::Vec2::ZERO,
+            mirrored_x: false,
+            mirrored_y: false,
         }
     }
 }
@@ -293,6 +865,12 @@ impl MotionAnimationSystem {
     }
 }
 
+impl Default for MotionAnimationSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SystemBase for MotionAnimationSystem {
     fn as_any(&self) -> &dyn std::any::Any {
         self
@@ -309,6 +887,10 @@ impl SystemBase for MotionAnimationSystem {
     fn remove_entity(&mut self, entity: Entity) {
         self.entities.remove(&entity);
     }
+
+    fn entities(&self) -> Vec<Entity> {
+        self.entities.iter().copied().collect()
+    }
 }
 
 impl System for MotionAnimationSystem {
@@ -325,32 +907,45 @@ impl System for MotionAnimationSystem {
                 velocity = motion_animation_component.last_velocity;
             }
             motion_animation_component.last_velocity = velocity;
+            // Each direction, its frames, and the opposite frames to flip if it has none.
             let cardinal_frames = [
                 (
                     glam::Vec2::new(0.0, 1.0),
                     &motion_animation_component.down_frames,
+                    &motion_animation_component.up_frames,
                 ),
                 (
                     glam::Vec2::new(1.0, 0.0),
                     &motion_animation_component.right_frames,
+                    &motion_animation_component.left_frames,
                 ),
                 (
                     glam::Vec2::new(-1.0, 0.0),
                     &motion_animation_component.left_frames,
+                    &motion_animation_component.right_frames,
                 ),
                 (
                     glam::Vec2::new(0.0, -1.0),
                     &motion_animation_component.up_frames,
+                    &motion_animation_component.down_frames,
                 ),
             ];
-            let (_, frames) = cardinal_frames
+            let (direction, frames, opposite_frames) = cardinal_frames
                 .iter()
-                .max_by(|(dir0, _), (dir1, _)| {
+                .max_by(|(dir0, _, _), (dir1, _, _)| {
                     let dot0 = velocity.dot(*dir0);
                     let dot1 = velocity.dot(*dir1);
                     dot0.partial_cmp(&dot1).unwrap()
                 })
                 .unwrap();
+            let (frames, mirror_x, mirror_y) = if frames.is_empty() {
+                (*opposite_frames, direction.x != 0.0, direction.y != 0.0)
+            } else {
+                (*frames, false, false)
+            };
+            if frames.is_empty() {
+                continue;
+            }
             motion_animation_component.current_frame_time += delta_time;
             let mut update_sprite_frame: Option<SpriteIndex> = None;
             if motion_animation_component.current_frame_time > motion_animation_component.frame_time
@@ -363,9 +958,16 @@ impl System for MotionAnimationSystem {
                     Some(frames[motion_animation_component.current_frame as usize]);
             }
             if let Some(update_sprite_frame) = update_sprite_frame {
+                let undo_x = motion_animation_component.mirrored_x != mirror_x;
+                let undo_y = motion_animation_component.mirrored_y != mirror_y;
+                motion_animation_component.mirrored_x = mirror_x;
+                motion_animation_component.mirrored_y = mirror_y;
                 let sprite_component: &mut SpriteComponent =
                     ec_manager.get_component_mut(*entity).unwrap().unwrap();
                 sprite_component.sprite_index = update_sprite_frame;
+                // Toggled rather than set, so the sprite's own flips are kept.
+                sprite_component.flip_x ^= undo_x;
+                sprite_component.flip_y ^= undo_y;
             }
         }
     }
@@ -375,48 +977,89 @@ impl System for MotionAnimationSystem {
 // Collision
 ///////////////////////////////////////////////////////////////////////////////
 
+/// Dispatched for every pair of entities that overlap when `CollisionSystem` runs.
+///
+/// Handlers run after the whole pass, so an entity despawned while handling one event may still
+/// be in later events from the same pass. Check `is_dead` before acting on it.
 pub struct CollisionEvent {
     pub entity_a: Entity,
     pub entity_b: Entity,
+    /// The minimum translation vector: moving `entity_a` by this much separates it from `entity_b`.
+    pub overlap: glam::Vec2,
+    /// A point in the overlapping region.
+    pub contact_point: glam::Vec2,
 }
 
-pub struct Rectangle {
-    top_left: glam::Vec2,
-    bottom_right: glam::Vec2,
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ColliderShape {
+    /// An axis aligned box, with the collider's offset at its top left.
+    Rectangle { width_height: glam::Vec2 },
+    /// A circle, with the collider's offset at its center.
+    Circle { radius: f32 },
 }
 
-impl Rectangle {
-    fn range_intersects(a0: f32, a1: f32, b0: f32, b1: f32) -> bool {
-        (a0 <= b0 && b0 <= a1) || (a0 <= b1 && b1 <= a1) || (b0 <= a0 && a0 <= b1)
-    }
-
-    fn collides_with(&self, other: &Rectangle) -> bool {
-        let x_axis_intersects = Self::range_intersects(
-            self.top_left.x,
-            self.bottom_right.x,
-            other.top_left.x,
-            other.bottom_right.x,
-        );
-        let y_axis_intersects = Self::range_intersects(
-            self.top_left.y,
-            self.bottom_right.y,
-            other.top_left.y,
-            other.bottom_right.y,
-        );
-        x_axis_intersects && y_axis_intersects
-    }
+/// Entities whose bounding box covers more spatial hash cells than this aren't put in the grid.
+/// They're tested against every other entity instead.
+const MAX_SPATIAL_HASH_CELLS: i64 = 256;
+
+/// How CollisionSystem finds the pairs of entities that might collide.
+/// Both find the same collisions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BroadPhase {
+    /// Test every pair of entities.
+    BruteForce,
+    /// Only test entities whose bounding boxes share a cell of a uniform grid.
+    #[default]
+    SpatialHash,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct CollisionComponent {
     pub offset: glam::Vec2,
-    pub width_height: glam::Vec2,
+    pub shape: ColliderShape,
+}
+
+impl CollisionComponent {
+    /// The collider in world space, for an entity at the given position.
+    pub fn world_shape(&self, position: glam::Vec2) -> Shape {
+        let position = position + self.offset;
+        match self.shape {
+            ColliderShape::Rectangle { width_height } => {
+                Shape::Rectangle(Rectangle::from_top_left(position, width_height))
+            }
+            ColliderShape::Circle { radius } => Shape::Circle(Circle::new(position, radius)),
+        }
+    }
+}
+
+/// The entity's collision shapes in world space.
+/// An entity may have several, added with `Registry::add_component_keyed`.
+fn collision_shapes(ec_manager: &EntityComponentWrapper, entity: Entity) -> Vec<Shape> {
+    let rigid_body_component: &RigidBodyComponent =
+        ec_manager.get_component(entity).unwrap().unwrap();
+    ec_manager
+        .get_components::<CollisionComponent>(entity)
+        .unwrap()
+        .map(|collision_component| collision_component.world_shape(rigid_body_component.position))
+        .collect()
+}
+
+/// Whether the projectile was fired by the owner.
+fn fired_by(ec_manager: &EntityComponentWrapper, projectile: Entity, owner: Entity) -> bool {
+    matches!(
+        ec_manager.get_component::<ProjectileComponent>(projectile),
+        Ok(Some(projectile_component)) if projectile_component.owner == Some(owner)
+    )
 }
 
 pub struct CollisionSystem {
     required_components: HashSet<std::any::TypeId>,
     entities: HashSet<Entity>,
-    render_collision_boxes: bool,
+    /// Pairs of entities that collided the last time the system ran.
+    collisions: RefCell<Vec<(Entity, Entity)>>,
+    broad_phase: BroadPhase,
+    /// Width and height of a spatial hash cell, in world units.
+    spatial_hash_cell_size: f32,
 }
 
 impl CollisionSystem {
@@ -427,11 +1070,102 @@ impl CollisionSystem {
         Self {
             required_components,
             entities: HashSet::new(),
-            render_collision_boxes: false,
+            collisions: RefCell::new(Vec::new()),
+            broad_phase: BroadPhase::default(),
+            spatial_hash_cell_size: 64.0,
+        }
+    }
+
+    pub fn set_broad_phase(&mut self, broad_phase: BroadPhase) {
+        self.broad_phase = broad_phase;
+    }
+
+    pub fn set_spatial_hash_cell_size(&mut self, cell_size: f32) {
+        assert!(cell_size > 0.0, "spatial hash cell size must be positive");
+        self.spatial_hash_cell_size = cell_size;
+    }
+
+    /// Whether the entity collided with anything the last time the system ran.
+    pub fn is_colliding(&self, entity: Entity) -> bool {
+        self.collisions
+            .borrow()
+            .iter()
+            .any(|(a, b)| *a == entity || *b == entity)
+    }
+
+    /// The entities this entity collided with the last time the system ran.
+    pub fn collisions_for(&self, entity: Entity) -> impl Iterator<Item = Entity> {
+        let collisions: Vec<Entity> = self
+            .collisions
+            .borrow()
+            .iter()
+            .filter_map(|(a, b)| {
+                if *a == entity {
+                    Some(*b)
+                } else if *b == entity {
+                    Some(*a)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        collisions.into_iter()
+    }
+
+    /// Pairs of indexes into `bounds` that might collide, with the lower index first, in order.
+    fn candidate_pairs(&self, bounds: &[Rectangle]) -> Vec<(usize, usize)> {
+        match self.broad_phase {
+            BroadPhase::BruteForce => (0..bounds.len())
+                .flat_map(|a| (a + 1..bounds.len()).map(move |b| (a, b)))
+                .collect(),
+            BroadPhase::SpatialHash => {
+                let cell =
+                    |point: glam::Vec2| (point / self.spatial_hash_cell_size).floor().as_ivec2();
+                let mut grid: HashMap<glam::IVec2, Vec<usize>> = HashMap::new();
+                let mut oversized = Vec::new();
+                for (index, rectangle) in bounds.iter().enumerate() {
+                    let top_left = cell(rectangle.top_left);
+                    let bottom_right = cell(rectangle.bottom_right);
+                    let cell_count = (i64::from(bottom_right.x) - i64::from(top_left.x) + 1)
+                        * (i64::from(bottom_right.y) - i64::from(top_left.y) + 1);
+                    if cell_count > MAX_SPATIAL_HASH_CELLS {
+                        oversized.push(index);
+                        continue;
+                    }
+                    for y in top_left.y..=bottom_right.y {
+                        for x in top_left.x..=bottom_right.x {
+                            grid.entry(glam::IVec2::new(x, y)).or_default().push(index);
+                        }
+                    }
+                }
+                // Entities spanning several cells would otherwise be paired more than once.
+                let mut pairs = HashSet::new();
+                for indexes in grid.values() {
+                    for (i, &a) in indexes.iter().enumerate() {
+                        for &b in &indexes[i + 1..] {
+                            pairs.insert((a, b));
+                        }
+                    }
+                }
+                for &a in &oversized {
+                    for b in (0..bounds.len()).filter(|b| *b != a) {
+                        pairs.insert((a.min(b), a.max(b)));
+                    }
+                }
+                let mut pairs: Vec<(usize, usize)> = pairs.into_iter().collect();
+                pairs.sort_unstable();
+                pairs
+            }
         }
     }
 }
 
+impl Default for CollisionSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SystemBase for CollisionSystem {
     fn as_any(&self) -> &dyn std::any::Any {
         self
@@ -448,70 +1182,76 @@ impl SystemBase for CollisionSystem {
     fn remove_entity(&mut self, entity: Entity) {
         self.entities.remove(&entity);
     }
+
+    fn entities(&self) -> Vec<Entity> {
+        self.entities.iter().copied().collect()
+    }
 }
 
 impl System for CollisionSystem {
-    type Input<'i> = &'i mut Renderer;
+    type Input<'i> = ();
 
-    fn run(&self, ec_manager: &mut EntityComponentWrapper, renderer: Self::Input<'_>) {
-        let entities: Vec<&Entity> = self.entities.iter().collect();
-        for a_index in 0..entities.len() {
-            let entity_a = entities[a_index];
-            if ec_manager.is_dead(*entity_a) {
+    fn run(&self, ec_manager: &mut EntityComponentWrapper, _input: Self::Input<'_>) {
+        let mut collisions = self.collisions.borrow_mut();
+        collisions.clear();
+        let entities: Vec<Entity> = self
+            .entities
+            .iter()
+            .copied()
+            .filter(|entity| !ec_manager.is_dead(*entity))
+            .collect();
+        let shapes: Vec<Vec<Shape>> = entities
+            .iter()
+            .map(|entity| collision_shapes(ec_manager, *entity))
+            .collect();
+        let bounds: Vec<Rectangle> = shapes
+            .iter()
+            .map(|shapes| {
+                shapes
+                    .iter()
+                    .map(Shape::bounding_rectangle)
+                    .reduce(|a, b| {
+                        Rectangle::new(
+                            a.top_left.min(b.top_left),
+                            a.bottom_right.max(b.bottom_right),
+                        )
+                    })
+                    .unwrap()
+            })
+            .collect();
+        for (a_index, b_index) in self.candidate_pairs(&bounds) {
+            let (entity_a, entity_b) = (entities[a_index], entities[b_index]);
+            if fired_by(ec_manager, entity_a, entity_b) || fired_by(ec_manager, entity_b, entity_a)
+            {
                 continue;
             }
-            let rigid_body_a: &RigidBodyComponent =
-                ec_manager.get_component(*entity_a).unwrap().unwrap();
-            let collision_a: &CollisionComponent =
-                ec_manager.get_component(*entity_a).unwrap().unwrap();
-            if self.render_collision_boxes {
-                renderer.draw_rectangle(
-                    rigid_body_a.position + collision_a.offset,
-                    collision_a.width_height,
-                );
-            }
-            let world_space_collision_rectangle_a = Rectangle {
-                top_left: rigid_body_a.position + collision_a.offset,
-                bottom_right: rigid_body_a.position + collision_a.offset + collision_a.width_height,
-            };
-            for b_index in (a_index + 1)..entities.len() {
-                let entity_b = entities[b_index];
-                if ec_manager.is_dead(*entity_b) {
-                    continue;
-                }
-                let rigid_body_b: &RigidBodyComponent =
-                    ec_manager.get_component(*entity_b).unwrap().unwrap();
-                let collision_b: &CollisionComponent =
-                    ec_manager.get_component(*entity_b).unwrap().unwrap();
-                let world_space_collision_rectangle_b = Rectangle {
-                    top_left: rigid_body_b.position + collision_b.offset,
-                    bottom_right: rigid_body_b.position
-                        + collision_b.offset
-                        + collision_b.width_height,
-                };
-                if world_space_collision_rectangle_a
-                    .collides_with(&world_space_collision_rectangle_b)
-                {
-                    ec_manager.dispatch_event(CollisionEvent {
-                        entity_a: *entity_a,
-                        entity_b: *entity_b,
-                    });
-                }
+            // With several collision shapes, report the deepest contact.
+            let contact = shapes[a_index]
+                .iter()
+                .flat_map(|shape_a| {
+                    shapes[b_index]
+                        .iter()
+                        .filter_map(|shape_b| shape_a.contact(shape_b))
+                })
+                .max_by(|a, b| {
+                    a.overlap
+                        .length_squared()
+                        .total_cmp(&b.overlap.length_squared())
+                });
+            if let Some(contact) = contact {
+                collisions.push((entity_a, entity_b));
+                ec_manager.dispatch_event(CollisionEvent {
+                    entity_a,
+                    entity_b,
+                    overlap: contact.overlap,
+                    contact_point: contact.contact_point,
+                });
             }
         }
     }
 }
 
-impl HandlerBase for CollisionSystem {
-    fn handle_any(&mut self, ec_manager: &mut EntityComponentWrapper, event: &dyn std::any::Any) {
-        if let Some(event) = event.downcast_ref::<CollisionEvent>() {
-            self.handle(ec_manager, event);
-        }
-        if let Some(event) = event.downcast_ref::<PhysicalKey>() {
-            self.handle(ec_manager, event);
-        }
-    }
-}
+impl_handler_base!(CollisionSystem: CollisionEvent);
 
 impl Handler<CollisionEvent> for CollisionSystem {
     fn handle(
@@ -519,41 +1259,447 @@ impl Handler<CollisionEvent> for CollisionSystem {
         ec_manager: &mut EntityComponentWrapper,
         collision_event: &CollisionEvent,
     ) {
-        ec_manager.remove_entity(collision_event.entity_a).unwrap();
-        ec_manager.remove_entity(collision_event.entity_b).unwrap();
+        // An entity may be in several collisions this frame, so it may already be queued.
+        let commands = ec_manager.commands();
+        commands.despawn(collision_event.entity_a);
+        commands.despawn(collision_event.entity_b);
+    }
+}
+
+/// Draws the outlines of collision shapes, when toggled on with a key.
+pub struct CollisionRenderSystem {
+    required_components: HashSet<std::any::TypeId>,
+    entities: HashSet<Entity>,
+    render_collision_boxes: bool,
+    /// Outline thickness of rendered collision boxes, in canvas pixels.
+    collision_box_thickness: f32,
+    collision_box_color: glam::Vec4,
+    /// Pressing this key toggles rendering of collision boxes.
+    render_collision_boxes_key: PhysicalKey,
+}
+
+impl CollisionRenderSystem {
+    pub fn new() -> Self {
+        let mut required_components = HashSet::new();
+        required_components.insert(std::any::TypeId::of::<RigidBodyComponent>());
+        required_components.insert(std::any::TypeId::of::<CollisionComponent>());
+        Self {
+            required_components,
+            entities: HashSet::new(),
+            render_collision_boxes: false,
+            collision_box_thickness: 1.0,
+            collision_box_color: glam::Vec4::new(1.0, 1.0, 0.0, 1.0),
+            render_collision_boxes_key: PhysicalKey::Code(KeyCode::KeyB),
+        }
+    }
+
+    pub fn set_collision_box_thickness(&mut self, thickness: f32) {
+        self.collision_box_thickness = thickness;
+    }
+
+    pub fn set_collision_box_color(&mut self, color: glam::Vec4) {
+        self.collision_box_color = color;
+    }
+
+    pub fn set_render_collision_boxes_key(&mut self, key: PhysicalKey) {
+        self.render_collision_boxes_key = key;
+    }
+
+    fn draw_collision_shape(&self, renderer: &mut Renderer, shape: &Shape) {
+        match shape {
+            Shape::Rectangle(rectangle) => renderer.draw_rectangle(
+                rectangle.top_left,
+                rectangle.width_height(),
+                self.collision_box_color,
+                RectangleStyle::Outline(self.collision_box_thickness),
+            ),
+            Shape::Circle(circle) => {
+                const SEGMENTS: usize = 24;
+                let points: Vec<glam::Vec2> = (0..=SEGMENTS)
+                    .map(|i| {
+                        let angle = i as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+                        circle.center + glam::Vec2::from_angle(angle) * circle.radius
+                    })
+                    .collect();
+                renderer.draw_polyline(
+                    &points,
+                    self.collision_box_thickness,
+                    self.collision_box_color,
+                );
+            }
+        }
+    }
+}
+
+impl Default for CollisionRenderSystem {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-impl Handler<PhysicalKey> for CollisionSystem {
+impl SystemBase for CollisionRenderSystem {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn required_components(&self) -> &HashSet<std::any::TypeId> {
+        &self.required_components
+    }
+
+    fn add_entity(&mut self, entity: Entity) {
+        self.entities.insert(entity);
+    }
+
+    fn remove_entity(&mut self, entity: Entity) {
+        self.entities.remove(&entity);
+    }
+
+    fn entities(&self) -> Vec<Entity> {
+        self.entities.iter().copied().collect()
+    }
+}
+
+impl System for CollisionRenderSystem {
+    type Input<'i> = &'i mut Renderer;
+
+    fn run(&self, ec_manager: &mut EntityComponentWrapper, renderer: Self::Input<'_>) {
+        if !self.render_collision_boxes {
+            return;
+        }
+        for entity in self.entities.iter() {
+            for shape in collision_shapes(ec_manager, *entity) {
+                self.draw_collision_shape(renderer, &shape);
+            }
+        }
+    }
+}
+
+impl_handler_base!(CollisionRenderSystem: PhysicalKey);
+
+impl Handler<PhysicalKey> for CollisionRenderSystem {
     fn handle(&mut self, _ec_manager: &mut EntityComponentWrapper, event: &PhysicalKey) {
-        if let PhysicalKey::Code(KeyCode::KeyB) = event {
+        if *event == self.render_collision_boxes_key {
             self.render_collision_boxes = !self.render_collision_boxes;
         }
     }
 }
 
+///////////////////////////////////////////////////////////////////////////////
+// Health
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HealthComponent {
+    pub current: f32,
+    pub max: f32,
+}
+
+/// Damage an entity. A negative amount heals, up to the entity's max health.
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: f32,
+}
+
+/// Dispatched when damage takes an entity's health to zero or below.
+/// The entity has already been despawned by the time this is handled.
+pub struct DeathEvent {
+    pub entity: Entity,
+}
+
+/// Handles `DamageEvent`s for entities with a `HealthComponent`.
+pub struct HealthSystem {}
+
+impl HealthSystem {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Default for HealthSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl_handler_base!(HealthSystem: DamageEvent);
+
+impl Handler<DamageEvent> for HealthSystem {
+    fn handle(&mut self, ec_manager: &mut EntityComponentWrapper, damage_event: &DamageEvent) {
+        let Ok(Some(health_component)) =
+            ec_manager.get_component_mut::<HealthComponent>(damage_event.target)
+        else {
+            return;
+        };
+        health_component.current =
+            (health_component.current - damage_event.amount).min(health_component.max);
+        if health_component.current <= 0.0 {
+            ec_manager.commands().despawn(damage_event.target);
+            ec_manager.dispatch_event(DeathEvent {
+                entity: damage_event.target,
+            });
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Lifetime
+///////////////////////////////////////////////////////////////////////////////
+
+/// Despawns the entity once `remaining` seconds have passed, such as for bullets and particles.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LifetimeComponent {
+    pub remaining: f32,
+}
+
+pub struct LifetimeSystem {
+    required_components: HashSet<std::any::TypeId>,
+    entities: HashSet<Entity>,
+}
+
+impl LifetimeSystem {
+    pub fn new() -> Self {
+        let mut required_components = HashSet::new();
+        required_components.insert(std::any::TypeId::of::<LifetimeComponent>());
+        Self {
+            required_components,
+            entities: HashSet::new(),
+        }
+    }
+}
+
+impl Default for LifetimeSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemBase for LifetimeSystem {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn required_components(&self) -> &HashSet<std::any::TypeId> {
+        &self.required_components
+    }
+
+    fn add_entity(&mut self, entity: Entity) {
+        self.entities.insert(entity);
+    }
+
+    fn remove_entity(&mut self, entity: Entity) {
+        self.entities.remove(&entity);
+    }
+
+    fn entities(&self) -> Vec<Entity> {
+        self.entities.iter().copied().collect()
+    }
+}
+
+impl System for LifetimeSystem {
+    type Input<'i> = f32;
+
+    fn run(&self, ec_manager: &mut EntityComponentWrapper, delta_time: Self::Input<'_>) {
+        for entity in self.entities.iter() {
+            let lifetime_component: &mut LifetimeComponent =
+                ec_manager.get_component_mut(*entity).unwrap().unwrap();
+            lifetime_component.remaining -= delta_time;
+            if lifetime_component.remaining <= 0.0 {
+                ec_manager.commands().despawn(*entity);
+            }
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Projectile
+///////////////////////////////////////////////////////////////////////////////
+
+/// Fires projectiles from the entity's position at a fixed rate.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProjectileEmitterComponent {
+    pub velocity: glam::Vec2,
+    /// Seconds between projectiles.
+    pub repeat_frequency: f32,
+    /// Seconds each projectile lives before it despawns.
+    pub projectile_duration: f32,
+    /// Damage done by each projectile, as a percent of the target's max health.
+    pub percent_damage: f32,
+    /// Friendly projectiles come from the player, and only hurt enemies.
+    pub is_friendly: bool,
+    /// Each projectile is drawn with this sprite, and collides with a box the same size.
+    pub projectile_sprite: SpriteComponent,
+    /// Seconds since the last projectile was fired.
+    pub time_since_last_emission: f32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProjectileComponent {
+    pub percent_damage: f32,
+    pub is_friendly: bool,
+    /// The entity that fired the projectile. It's fired from inside the owner,
+    /// so `CollisionSystem` doesn't report collisions between them.
+    #[serde(default)]
+    pub owner: Option<Entity>,
+}
+
+pub struct ProjectileEmitterSystem {
+    required_components: HashSet<std::any::TypeId>,
+    entities: HashSet<Entity>,
+}
+
+impl ProjectileEmitterSystem {
+    pub fn new() -> Self {
+        let mut required_components = HashSet::new();
+        required_components.insert(std::any::TypeId::of::<RigidBodyComponent>());
+        required_components.insert(std::any::TypeId::of::<ProjectileEmitterComponent>());
+        Self {
+            required_components,
+            entities: HashSet::new(),
+        }
+    }
+}
+
+impl Default for ProjectileEmitterSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemBase for ProjectileEmitterSystem {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn required_components(&self) -> &HashSet<std::any::TypeId> {
+        &self.required_components
+    }
+
+    fn add_entity(&mut self, entity: Entity) {
+        self.entities.insert(entity);
+    }
+
+    fn remove_entity(&mut self, entity: Entity) {
+        self.entities.remove(&entity);
+    }
+
+    fn entities(&self) -> Vec<Entity> {
+        self.entities.iter().copied().collect()
+    }
+}
+
+impl System for ProjectileEmitterSystem {
+    type Input<'i> = f32;
+
+    fn run(&self, ec_manager: &mut EntityComponentWrapper, delta_time: Self::Input<'_>) {
+        for entity in self.entities.iter() {
+            let position = ec_manager
+                .get_component::<RigidBodyComponent>(*entity)
+                .unwrap()
+                .unwrap()
+                .position;
+            let emitter: &mut ProjectileEmitterComponent =
+                ec_manager.get_component_mut(*entity).unwrap().unwrap();
+            if emitter.repeat_frequency <= 0.0 {
+                continue;
+            }
+            emitter.time_since_last_emission += delta_time;
+            let mut emitted = 0;
+            while emitter.time_since_last_emission >= emitter.repeat_frequency {
+                emitter.time_since_last_emission -= emitter.repeat_frequency;
+                emitted += 1;
+            }
+            let emitter = emitter.clone();
+            for _ in 0..emitted {
+                ec_manager
+                    .commands()
+                    .spawn()
+                    .with(RigidBodyComponent {
+                        velocity: emitter.velocity,
+                        ..RigidBodyComponent::at(position)
+                    })
+                    .with(emitter.projectile_sprite.clone())
+                    .with(CollisionComponent {
+                        offset: glam::Vec2::ZERO,
+                        shape: ColliderShape::Rectangle {
+                            width_height: emitter.projectile_sprite.size,
+                        },
+                    })
+                    .with(LifetimeComponent {
+                        remaining: emitter.projectile_duration,
+                    })
+                    .with(ProjectileComponent {
+                        percent_damage: emitter.percent_damage,
+                        is_friendly: emitter.is_friendly,
+                        owner: Some(*entity),
+                    });
+            }
+        }
+    }
+}
+
 ///////////////////////////////////////////////////////////////////////////////
 // Keyboard Control
 ///////////////////////////////////////////////////////////////////////////////
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct KeyboardControlComponent;
 
+/// The keys that move keyboard controlled entities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBindings {
+    pub up: PhysicalKey,
+    pub down: PhysicalKey,
+    pub left: PhysicalKey,
+    pub right: PhysicalKey,
+}
+
+impl Default for KeyBindings {
+    /// WASD
+    fn default() -> Self {
+        Self {
+            up: PhysicalKey::Code(KeyCode::KeyW),
+            down: PhysicalKey::Code(KeyCode::KeyS),
+            left: PhysicalKey::Code(KeyCode::KeyA),
+            right: PhysicalKey::Code(KeyCode::KeyD),
+        }
+    }
+}
+
 pub struct KeyboardControlSystem {
     required_components: HashSet<std::any::TypeId>,
     entities: HashSet<Entity>,
+    key_bindings: KeyBindings,
 }
 
 impl KeyboardControlSystem {
     pub fn new() -> Self {
+        Self::with_bindings(KeyBindings::default())
+    }
+
+    pub fn with_bindings(key_bindings: KeyBindings) -> Self {
         let mut required_components = HashSet::new();
         required_components.insert(std::any::TypeId::of::<RigidBodyComponent>());
         required_components.insert(std::any::TypeId::of::<KeyboardControlComponent>());
         Self {
             required_components,
             entities: HashSet::new(),
+            key_bindings,
         }
     }
+
+    pub fn set_key_bindings(&mut self, key_bindings: KeyBindings) {
+        self.key_bindings = key_bindings;
+    }
+
+    pub fn key_bindings(&self) -> KeyBindings {
+        self.key_bindings
+    }
+}
+
+impl Default for KeyboardControlSystem {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SystemBase for KeyboardControlSystem {
@@ -572,6 +1718,10 @@ impl SystemBase for KeyboardControlSystem {
     fn remove_entity(&mut self, entity: Entity) {
         self.entities.remove(&entity);
     }
+
+    fn entities(&self) -> Vec<Entity> {
+        self.entities.iter().copied().collect()
+    }
 }
 
 impl System for KeyboardControlSystem {
@@ -579,19 +1729,20 @@ impl System for KeyboardControlSystem {
 
     fn run(&self, ec_manager: &mut EntityComponentWrapper, pressed_keys: Self::Input<'_>) {
         let mut unit_velocity = glam::Vec2::ZERO;
-        if pressed_keys.contains(&PhysicalKey::Code(KeyCode::KeyA)) {
+        if pressed_keys.contains(&self.key_bindings.left) {
             unit_velocity += glam::Vec2::new(-1.0, 0.0);
         }
-        if pressed_keys.contains(&PhysicalKey::Code(KeyCode::KeyS)) {
+        if pressed_keys.contains(&self.key_bindings.down) {
             unit_velocity += glam::Vec2::new(0.0, 1.0);
         }
-        if pressed_keys.contains(&PhysicalKey::Code(KeyCode::KeyD)) {
+        if pressed_keys.contains(&self.key_bindings.right) {
             unit_velocity += glam::Vec2::new(1.0, 0.0);
         }
-        if pressed_keys.contains(&PhysicalKey::Code(KeyCode::KeyW)) {
+        if pressed_keys.contains(&self.key_bindings.up) {
             unit_velocity += glam::Vec2::new(0.0, -1.0);
         }
-        let velocity = unit_velocity * 80.0;
+        // Normalized, so moving diagonally isn't faster than moving straight.
+        let velocity = unit_velocity.normalize_or_zero() * 80.0;
         for entity in self.entities.iter() {
             let rigid_body_component: &mut RigidBodyComponent =
                 ec_manager.get_component_mut(*entity).unwrap().unwrap();
@@ -600,21 +1751,244 @@ impl System for KeyboardControlSystem {
     }
 }
 
+///////////////////////////////////////////////////////////////////////////////
+// GamepadControl
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GamepadControlComponent;
+
+/// Like `KeyboardControlSystem`, but steered by a gamepad's left stick,
+/// so partly tilting the stick moves slower.
+pub struct GamepadControlSystem {
+    required_components: HashSet<std::any::TypeId>,
+    entities: HashSet<Entity>,
+    deadzone: f32,
+}
+
+impl GamepadControlSystem {
+    pub fn new() -> Self {
+        let mut required_components = HashSet::new();
+        required_components.insert(std::any::TypeId::of::<RigidBodyComponent>());
+        required_components.insert(std::any::TypeId::of::<GamepadControlComponent>());
+        Self {
+            required_components,
+            entities: HashSet::new(),
+            deadzone: 0.15,
+        }
+    }
+
+    /// Stick positions closer to the center than this are treated as centered.
+    pub fn set_deadzone(&mut self, deadzone: f32) {
+        self.deadzone = deadzone.clamp(0.0, 0.99);
+    }
+}
+
+impl Default for GamepadControlSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemBase for GamepadControlSystem {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn required_components(&self) -> &HashSet<std::any::TypeId> {
+        &self.required_components
+    }
+
+    fn add_entity(&mut self, entity: Entity) {
+        self.entities.insert(entity);
+    }
+
+    fn remove_entity(&mut self, entity: Entity) {
+        self.entities.remove(&entity);
+    }
+
+    fn entities(&self) -> Vec<Entity> {
+        self.entities.iter().copied().collect()
+    }
+}
+
+impl System for GamepadControlSystem {
+    type Input<'i> = StickState;
+
+    fn run(&self, ec_manager: &mut EntityComponentWrapper, left_stick: Self::Input<'_>) {
+        // Full tilt is as fast as the keyboard.
+        let velocity = left_stick.with_deadzone(self.deadzone) * 80.0;
+        for entity in self.entities.iter() {
+            let rigid_body_component: &mut RigidBodyComponent =
+                ec_manager.get_component_mut(*entity).unwrap().unwrap();
+            rigid_body_component.velocity = velocity;
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Seek
+///////////////////////////////////////////////////////////////////////////////
+
+/// Moves an entity toward a target entity, such as an enemy chasing the player.
+/// See `nearest_with` for finding a target.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SeekComponent {
+    pub target: Entity,
+    pub speed: f32,
+    /// If set, the entity turns smoothly by accelerating toward the target,
+    /// rather than immediately changing its velocity.
+    pub max_acceleration: Option<f32>,
+}
+
+impl SeekComponent {
+    pub fn new(target: Entity, speed: f32) -> Self {
+        Self {
+            target,
+            speed,
+            max_acceleration: None,
+        }
+    }
+}
+
+/// Steers seeking entities toward their targets. Run it before the `MovementSystem`.
+/// When a target dies, the seeking entity stops and its `SeekComponent` is removed.
+pub struct SeekSystem {
+    required_components: HashSet<std::any::TypeId>,
+    entities: HashSet<Entity>,
+}
+
+impl SeekSystem {
+    pub fn new() -> Self {
+        let mut required_components = HashSet::new();
+        required_components.insert(std::any::TypeId::of::<RigidBodyComponent>());
+        required_components.insert(std::any::TypeId::of::<SeekComponent>());
+        Self {
+            required_components,
+            entities: HashSet::new(),
+        }
+    }
+}
+
+impl Default for SeekSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemBase for SeekSystem {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn required_components(&self) -> &HashSet<std::any::TypeId> {
+        &self.required_components
+    }
+
+    fn add_entity(&mut self, entity: Entity) {
+        self.entities.insert(entity);
+    }
+
+    fn remove_entity(&mut self, entity: Entity) {
+        self.entities.remove(&entity);
+    }
+
+    fn entities(&self) -> Vec<Entity> {
+        self.entities.iter().copied().collect()
+    }
+}
+
+impl System for SeekSystem {
+    type Input<'i> = ();
+
+    fn run(&self, ec_manager: &mut EntityComponentWrapper, _input: Self::Input<'_>) {
+        for entity in self.entities.iter() {
+            let seek_component: SeekComponent = ec_manager
+                .get_component::<SeekComponent>(*entity)
+                .unwrap()
+                .unwrap()
+                .clone();
+            let target_position: Option<glam::Vec2> = match ec_manager
+                .get_component::<RigidBodyComponent>(seek_component.target)
+            {
+                Ok(Some(target_rigid_body_component)) => Some(target_rigid_body_component.position),
+                _ => None,
+            };
+            let rigid_body_component: &mut RigidBodyComponent =
+                ec_manager.get_component_mut(*entity).unwrap().unwrap();
+            let Some(target_position) = target_position else {
+                rigid_body_component.velocity = glam::Vec2::ZERO;
+                rigid_body_component.acceleration = glam::Vec2::ZERO;
+                ec_manager
+                    .remove_component::<SeekComponent>(*entity)
+                    .unwrap();
+                continue;
+            };
+            let desired_velocity = (target_position - rigid_body_component.position)
+                .normalize_or_zero()
+                * seek_component.speed;
+            match seek_component.max_acceleration {
+                Some(max_acceleration) => {
+                    rigid_body_component.acceleration = (desired_velocity
+                        - rigid_body_component.velocity)
+                        .clamp_length_max(max_acceleration);
+                }
+                None => {
+                    rigid_body_component.velocity = desired_velocity;
+                }
+            }
+        }
+    }
+}
+
 ///////////////////////////////////////////////////////////////////////////////
 // Camera
 ///////////////////////////////////////////////////////////////////////////////
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct CameraFocusComponent {
     pub focus_offset: glam::Vec2,
-    pub viewport_size: glam::Vec2,
     pub map_top_left: glam::Vec2,
     pub map_bottom_right: glam::Vec2,
+    /// Passed on to the camera. 1 shows the whole viewport, 2 shows half as much of the world.
+    pub zoom: f32,
+    /// Which camera follows this entity. With several focused entities, the canvas is split,
+    /// and cameras are ordered by slot.
+    pub slot: usize,
+}
+
+impl CameraFocusComponent {
+    /// A camera centered on the focus, but kept within the map so nothing past its edges shows.
+    /// Along an axis where the map is smaller than the visible area, the map is centered instead.
+    pub fn camera(&self, position: glam::Vec2, viewport_size: glam::Vec2) -> Camera {
+        let focus = position + self.focus_offset;
+        // Zoom scales around the center of the camera, so only this much of the viewport shows.
+        let half_visible_size = viewport_size / self.zoom / 2.0;
+        let min_center = self.map_top_left + half_visible_size;
+        let max_center = self.map_bottom_right - half_visible_size;
+        let map_center = (self.map_top_left + self.map_bottom_right) / 2.0;
+        let constrain = |focus: f32, min: f32, max: f32, map_center: f32| {
+            if min <= max {
+                focus.clamp(min, max)
+            } else {
+                map_center
+            }
+        };
+        let center = glam::Vec2::new(
+            constrain(focus.x, min_center.x, max_center.x, map_center.x),
+            constrain(focus.y, min_center.y, max_center.y, map_center.y),
+        );
+        Camera {
+            top_left: center - viewport_size / 2.0,
+            width_height: viewport_size,
+            zoom: self.zoom,
+        }
+    }
 }
 
 pub struct CameraFocusSystem {
     required_components: HashSet<std::any::TypeId>,
-    entity: Option<Entity>,
+    entities: HashSet<Entity>,
 }
 
 impl CameraFocusSystem {
@@ -624,11 +1998,17 @@ impl CameraFocusSystem {
         required_components.insert(std::any::TypeId::of::<CameraFocusComponent>());
         Self {
             required_components,
-            entity: None,
+            entities: HashSet::new(),
         }
     }
 }
 
+impl Default for CameraFocusSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SystemBase for CameraFocusSystem {
     fn as_any(&self) -> &dyn std::any::Any {
         self
@@ -639,13 +2019,15 @@ impl SystemBase for CameraFocusSystem {
     }
 
     fn add_entity(&mut self, entity: Entity) {
-        self.entity = Some(entity);
+        self.entities.insert(entity);
     }
 
     fn remove_entity(&mut self, entity: Entity) {
-        if self.entity == Some(entity) {
-            self.entity = None;
-        }
+        self.entities.remove(&entity);
+    }
+
+    fn entities(&self) -> Vec<Entity> {
+        self.entities.iter().copied().collect()
     }
 }
 
@@ -653,28 +2035,218 @@ impl System for CameraFocusSystem {
     type Input<'i> = &'i mut Renderer;
 
     fn run(&self, ec_manager: &mut EntityComponentWrapper, renderer: Self::Input<'_>) {
-        if self.entity.is_none() {
+        if self.entities.is_empty() {
             return;
         }
-        let entity = self.entity.unwrap();
-        let rigid_body_component: &RigidBodyComponent =
-            ec_manager.get_component(entity).unwrap().unwrap();
-        let camera_focus_component: &CameraFocusComponent =
-            ec_manager.get_component(entity).unwrap().unwrap();
-        // TODO: Constrain viewport at edges of map
-        let focus = rigid_body_component.position + camera_focus_component.focus_offset;
-        let focus_top_left = focus - (camera_focus_component.viewport_size / 2.0);
-        let focus_top_left_out_of_bounds =
-            (camera_focus_component.map_top_left - focus_top_left).max(glam::Vec2::ZERO);
-        let focus_bottom_right = focus + (camera_focus_component.viewport_size / 2.0);
-        let focus_bottom_right_out_of_bounds =
-            (camera_focus_component.map_bottom_right - focus_bottom_right).min(glam::Vec2::ZERO);
-        let camera = Camera {
-            top_left: focus_top_left
-                + focus_top_left_out_of_bounds
-                + focus_bottom_right_out_of_bounds,
-            width_height: camera_focus_component.viewport_size,
-        };
-        renderer.set_camera(camera);
+        let mut focuses: Vec<(&CameraFocusComponent, glam::Vec2)> = self
+            .entities
+            .iter()
+            .map(|&entity| {
+                let rigid_body_component: &RigidBodyComponent =
+                    ec_manager.get_component(entity).unwrap().unwrap();
+                let camera_focus_component: &CameraFocusComponent =
+                    ec_manager.get_component(entity).unwrap().unwrap();
+                (camera_focus_component, rigid_body_component.position)
+            })
+            .collect();
+        focuses.sort_by_key(|(camera_focus_component, _)| camera_focus_component.slot);
+        // Each camera matches its viewport, so the camera shows exactly what is rendered.
+        let viewports = renderer.viewports(focuses.len());
+        let cameras: Vec<Camera> = focuses
+            .iter()
+            .zip(viewports)
+            .map(|((camera_focus_component, position), viewport)| {
+                camera_focus_component.camera(*position, viewport.size.as_vec2())
+            })
+            .collect();
+        renderer.set_cameras(&cameras);
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// NoOp
+///////////////////////////////////////////////////////////////////////////////
+
+/// A system that does nothing when run, other than count how many times it was run.
+/// Useful in tests, and as a minimal example of implementing `System` and `SystemBase`.
+pub struct NoOpSystem {
+    required_components: HashSet<std::any::TypeId>,
+    entities: HashSet<Entity>,
+    run_count: std::cell::Cell<usize>,
+}
+
+impl NoOpSystem {
+    pub fn new() -> Self {
+        Self {
+            required_components: HashSet::new(),
+            entities: HashSet::new(),
+            run_count: std::cell::Cell::new(0),
+        }
+    }
+
+    /// Require entities to have a component of type T.
+    /// This must be called before the system is added to a Registry.
+    pub fn require_component<T: 'static>(&mut self) {
+        self.required_components.insert(std::any::TypeId::of::<T>());
+    }
+
+    /// How many times this system has been run.
+    pub fn run_count(&self) -> usize {
+        self.run_count.get()
     }
 }
+
+impl Default for NoOpSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemBase for NoOpSystem {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn required_components(&self) -> &HashSet<std::any::TypeId> {
+        &self.required_components
+    }
+
+    fn add_entity(&mut self, entity: Entity) {
+        self.entities.insert(entity);
+    }
+
+    fn remove_entity(&mut self, entity: Entity) {
+        self.entities.remove(&entity);
+    }
+
+    fn entities(&self) -> Vec<Entity> {
+        self.entities.iter().copied().collect()
+    }
+}
+
+impl System for NoOpSystem {
+    type Input<'i> = ();
+
+    fn run(&self, _ec_manager: &mut EntityComponentWrapper, _input: Self::Input<'_>) {
+        self.run_count.set(self.run_count.get() + 1);
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Update
+///////////////////////////////////////////////////////////////////////////////
+
+/// Add the systems that update the game state each frame.
+/// None of these systems need a window, so they can be stepped in tests.
+pub fn add_update_systems(registry: &mut Registry) {
+    registry.add_system(Rc::new(RefCell::new(KeyboardControlSystem::new())));
+    registry.add_system(Rc::new(RefCell::new(GamepadControlSystem::new())));
+    registry.add_system(Rc::new(RefCell::new(SeekSystem::new())));
+    registry.add_system(Rc::new(RefCell::new(MovementSystem::new())));
+    registry.add_system(Rc::new(RefCell::new(HierarchySystem::new())));
+    registry.add_system(Rc::new(RefCell::new(AnimationSystem::new())));
+    registry.add_system(Rc::new(RefCell::new(MotionAnimationSystem::new())));
+    registry.add_system(Rc::new(RefCell::new(ProjectileEmitterSystem::new())));
+    registry.add_system(Rc::new(RefCell::new(LifetimeSystem::new())));
+    let collision_system = Rc::new(RefCell::new(CollisionSystem::new()));
+    registry.add_handler::<CollisionEvent, _>(Rc::clone(&collision_system));
+    registry.add_system(collision_system);
+    let collision_render_system = Rc::new(RefCell::new(CollisionRenderSystem::new()));
+    registry.add_handler::<PhysicalKey, _>(Rc::clone(&collision_render_system));
+    registry.add_system(collision_render_system);
+    registry.add_handler::<DamageEvent, _>(Rc::new(RefCell::new(HealthSystem::new())));
+}
+
+/// Run the systems added by `add_update_systems`, advancing the game by delta_t seconds.
+/// If a renderer is given, the systems may also draw debug shapes, such as collision boxes.
+pub fn run_update_systems(
+    registry: &mut Registry,
+    pressed_keys: &HashSet<PhysicalKey>,
+    delta_t: f32,
+    renderer: Option<&mut Renderer>,
+) -> Result<(), EcsError> {
+    run_fixed_update_systems(registry, pressed_keys, delta_t)?;
+    run_frame_update_systems(registry, delta_t, renderer)
+}
+
+/// Run the simulation systems added by `add_update_systems`, such as movement and collision.
+/// These should be run with a fixed delta_t, so the simulation doesn't depend on the frame rate.
+/// Gamepads are steered by the `StickState` resource, if there is one.
+pub fn run_fixed_update_systems(
+    registry: &mut Registry,
+    pressed_keys: &HashSet<PhysicalKey>,
+    delta_t: f32,
+) -> Result<(), EcsError> {
+    registry.run_system::<KeyboardControlSystem>(pressed_keys)?;
+    let left_stick = registry
+        .get_resource::<StickState>()
+        .copied()
+        .unwrap_or_default();
+    registry.run_system::<GamepadControlSystem>(left_stick)?;
+    registry.run_system::<SeekSystem>(())?;
+    registry.run_system::<ProjectileEmitterSystem>(delta_t)?;
+    registry.run_system::<MovementSystem>(delta_t)?;
+    registry.run_system::<HierarchySystem>(())?;
+    registry.run_system::<CollisionSystem>(())?;
+    registry.run_system::<LifetimeSystem>(delta_t)?;
+    Ok(())
+}
+
+/// Run the presentation systems added by `add_update_systems`, such as animation,
+/// once per rendered frame.
+/// If a renderer is given, the systems may also draw debug shapes, such as collision boxes.
+pub fn run_frame_update_systems(
+    registry: &mut Registry,
+    delta_t: f32,
+    renderer: Option<&mut Renderer>,
+) -> Result<(), EcsError> {
+    registry.run_system::<AnimationSystem>(delta_t)?;
+    registry.run_system::<MotionAnimationSystem>(delta_t)?;
+    if let Some(renderer) = renderer {
+        registry.run_system::<CollisionRenderSystem>(renderer)?;
+    }
+    Ok(())
+}
+
+/// The systems `set_simulation_paused` disabled, stored as a registry resource.
+/// Resuming only enables these, so systems the game disabled itself stay disabled.
+struct PausedSystems(HashSet<std::any::TypeId>);
+
+/// Pause or resume the simulation systems added by `add_update_systems`, such as movement,
+/// collision, and animation, so the scene freezes while it's still drawn.
+/// Debug drawing, such as collision boxes, keeps running.
+pub fn set_simulation_paused(registry: &mut Registry, paused: bool) -> Result<(), EcsError> {
+    let mut paused_systems = registry
+        .get_resource_mut::<PausedSystems>()
+        .map(|paused_systems| std::mem::take(&mut paused_systems.0))
+        .unwrap_or_default();
+    set_system_paused::<KeyboardControlSystem>(registry, &mut paused_systems, paused)?;
+    set_system_paused::<GamepadControlSystem>(registry, &mut paused_systems, paused)?;
+    set_system_paused::<SeekSystem>(registry, &mut paused_systems, paused)?;
+    set_system_paused::<ProjectileEmitterSystem>(registry, &mut paused_systems, paused)?;
+    set_system_paused::<MovementSystem>(registry, &mut paused_systems, paused)?;
+    set_system_paused::<HierarchySystem>(registry, &mut paused_systems, paused)?;
+    set_system_paused::<CollisionSystem>(registry, &mut paused_systems, paused)?;
+    set_system_paused::<LifetimeSystem>(registry, &mut paused_systems, paused)?;
+    set_system_paused::<AnimationSystem>(registry, &mut paused_systems, paused)?;
+    set_system_paused::<MotionAnimationSystem>(registry, &mut paused_systems, paused)?;
+    registry.insert_resource(PausedSystems(paused_systems));
+    Ok(())
+}
+
+fn set_system_paused<S: System + 'static>(
+    registry: &mut Registry,
+    paused_systems: &mut HashSet<std::any::TypeId>,
+    paused: bool,
+) -> Result<(), EcsError> {
+    let type_id = std::any::TypeId::of::<S>();
+    let enabled = if paused {
+        if registry.is_system_enabled::<S>() {
+            paused_systems.insert(type_id);
+        }
+        false
+    } else {
+        paused_systems.remove(&type_id) || registry.is_system_enabled::<S>()
+    };
+    registry.set_system_enabled::<S>(enabled)
+}
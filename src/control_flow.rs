@@ -0,0 +1,53 @@
+/// Something gameplay code wants the main loop to do once the current frame is finished.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlRequest {
+    Exit,
+    SceneChange(String),
+}
+
+/// A resource that lets systems talk to the main loop.
+/// Systems make requests, and the main loop takes them after running the systems.
+/// If several requests are made in one frame, the last one wins.
+#[derive(Default)]
+pub struct ControlFlow {
+    request: Option<ControlRequest>,
+}
+
+impl ControlFlow {
+    pub fn new() -> Self {
+        Self { request: None }
+    }
+
+    pub fn request_exit(&mut self) {
+        self.request = Some(ControlRequest::Exit);
+    }
+
+    pub fn request_scene_change<S: Into<String>>(&mut self, scene: S) {
+        self.request = Some(ControlRequest::SceneChange(scene.into()));
+    }
+
+    /// Take the pending request, leaving none behind.
+    pub fn take_request(&mut self) -> Option<ControlRequest> {
+        self.request.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ControlFlow, ControlRequest};
+
+    #[test]
+    fn test_take_request() {
+        let mut control_flow = ControlFlow::new();
+        assert_eq!(control_flow.take_request(), None);
+        control_flow.request_scene_change("level 2");
+        control_flow.request_exit();
+        assert_eq!(control_flow.take_request(), Some(ControlRequest::Exit));
+        assert_eq!(control_flow.take_request(), None);
+        control_flow.request_scene_change("level 2");
+        assert_eq!(
+            control_flow.take_request(),
+            Some(ControlRequest::SceneChange("level 2".to_string()))
+        );
+    }
+}
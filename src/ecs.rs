@@ -1,28 +1,54 @@
 use std::any::{Any, TypeId};
 use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::rc::Rc;
 
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
 use crate::event_bus::{EventBus, Handler};
+use crate::profiling::SystemTimings;
 
 type IndexT = u32;
 type GenerationT = u32;
 
 const VEC_RESIZE_MARGIN: usize = 10;
 
+/// A name for finding an entity with `Registry::find_by_name`, and for debugging.
+/// Names should be unique; if several entities share a name, the last one named is found.
+/// Rename an entity by adding a new `NameComponent`;
+/// `find_by_name` doesn't see names changed in place with `get_component_mut`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NameComponent(pub String);
+
 #[derive(Debug)]
 pub enum EcsError {
     DeadEntity,
     NoSuchComponent,
     NoSuchSystem,
+    /// Creating the entity would exceed the registry's maximum entity count.
+    TooManyEntities,
+    /// A system panicked while running.
+    /// This usually means the system accessed a component missing from its `required_components`.
+    SystemPanicked {
+        system: &'static str,
+        message: String,
+    },
+    /// Saving or loading the registry failed.
+    Serialization(String),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Entity {
     id: IndexT,
     generation: GenerationT,
 }
 
+impl PartialOrd for Entity {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl Ord for Entity {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         self.id
@@ -31,6 +57,8 @@ impl Ord for Entity {
     }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "SavedEntityManager", into = "SavedEntityManager")]
 struct EntityManager {
     /// Entity ids that are free to issue again.
     free_entity_ids: Vec<IndexT>,
@@ -40,6 +68,49 @@ struct EntityManager {
     /// If a given Entity has a generation less than this,
     /// that Entity is no longer valid.
     generations: Vec<IndexT>,
+    /// Alive entities in the order they were created.
+    /// Removed entities leave a hole, so removing doesn't shift the entities after them.
+    creation_order: Vec<Option<Entity>>,
+    /// Where each id's alive entity is in `creation_order`, indexed by id.
+    creation_slots: Vec<usize>,
+    /// How many entities are alive, which is `creation_order` without its holes.
+    entity_count: usize,
+}
+
+/// The format `EntityManager` is saved in, with the holes left out of the creation order.
+#[derive(Serialize, Deserialize)]
+struct SavedEntityManager {
+    free_entity_ids: Vec<IndexT>,
+    next_entity_id: IndexT,
+    generations: Vec<IndexT>,
+    creation_order: Vec<Entity>,
+}
+
+impl From<EntityManager> for SavedEntityManager {
+    fn from(entity_manager: EntityManager) -> Self {
+        Self {
+            creation_order: entity_manager.entities_ordered().copied().collect(),
+            free_entity_ids: entity_manager.free_entity_ids,
+            next_entity_id: entity_manager.next_entity_id,
+            generations: entity_manager.generations,
+        }
+    }
+}
+
+/// A saved entity manager may be inconsistent; `EntityManager::validate` checks it.
+impl From<SavedEntityManager> for EntityManager {
+    fn from(saved: SavedEntityManager) -> Self {
+        let mut entity_manager = Self {
+            free_entity_ids: saved.free_entity_ids,
+            next_entity_id: saved.next_entity_id,
+            creation_slots: vec![0; saved.generations.len()],
+            generations: saved.generations,
+            entity_count: saved.creation_order.len(),
+            creation_order: saved.creation_order.into_iter().map(Some).collect(),
+        };
+        entity_manager.index_creation_slots();
+        entity_manager
+    }
 }
 
 impl EntityManager {
@@ -48,23 +119,37 @@ impl EntityManager {
             free_entity_ids: Vec::new(),
             next_entity_id: 0,
             generations: Vec::new(),
+            creation_order: Vec::new(),
+            creation_slots: Vec::new(),
+            entity_count: 0,
         }
     }
 
     /// Returns a free entity id if available, otherwise returns the next new entity id.
     /// Will not alter generations, which should only be altered when removing entities.
     fn create_entity(&mut self) -> Entity {
-        if let Some(entity_id) = self.free_entity_ids.pop() {
-            return Entity {
+        let result = if let Some(entity_id) = self.free_entity_ids.pop() {
+            Entity {
                 id: entity_id,
                 generation: self.alive_generation(entity_id),
+            }
+        } else {
+            let entity_id = self.next_entity_id as usize;
+            // Every issued id has a generation, so there is never doubt about which are alive.
+            if entity_id >= self.generations.len() {
+                self.generations.resize(entity_id + VEC_RESIZE_MARGIN, 0);
+                self.creation_slots.resize(self.generations.len(), 0);
+            }
+            let result = Entity {
+                id: self.next_entity_id,
+                generation: self.generations[entity_id],
             };
-        }
-        let result = Entity {
-            id: self.next_entity_id,
-            generation: self.alive_generation(self.next_entity_id),
+            self.next_entity_id += 1;
+            result
         };
-        self.next_entity_id += 1;
+        self.creation_slots[result.id as usize] = self.creation_order.len();
+        self.creation_order.push(Some(result));
+        self.entity_count += 1;
         result
     }
 
@@ -75,91 +160,211 @@ impl EntityManager {
         if self.is_dead(entity) {
             return Err(EcsError::DeadEntity);
         }
-        let entity_id = entity.id as usize;
-        if entity_id >= self.generations.len() {
-            self.generations.resize(entity_id + VEC_RESIZE_MARGIN, 0);
-        }
         self.free_entity_ids.push(entity.id);
-        self.generations[entity_id] += 1;
+        self.generations[entity.id as usize] += 1;
+        self.creation_order[self.creation_slots[entity.id as usize]] = None;
+        self.entity_count -= 1;
+        // Compacting once there are more holes than entities keeps removal amortized O(1).
+        if self.creation_order.len() - self.entity_count > self.entity_count {
+            self.creation_order.retain(Option::is_some);
+            self.index_creation_slots();
+        }
         Ok(())
     }
 
+    /// Point each alive entity's id at its place in `creation_order`.
+    /// Ids outside the generations are left for `validate` to reject.
+    fn index_creation_slots(&mut self) {
+        for (slot, entity) in self.creation_order.iter().enumerate() {
+            if let Some(creation_slot) =
+                entity.and_then(|entity| self.creation_slots.get_mut(entity.id as usize))
+            {
+                *creation_slot = slot;
+            }
+        }
+    }
+
+    /// Ids that have never been issued are not alive, whatever their generation.
     fn is_alive(&self, entity: Entity) -> bool {
-        entity.generation == self.alive_generation(entity.id)
+        self.is_issued(entity.id) && entity.generation == self.alive_generation(entity.id)
     }
 
     fn is_dead(&self, entity: Entity) -> bool {
-        entity.generation != self.alive_generation(entity.id)
+        !self.is_alive(entity)
+    }
+
+    fn is_issued(&self, entity_id: IndexT) -> bool {
+        entity_id < self.next_entity_id
+    }
+
+    fn entities_ordered(&self) -> impl Iterator<Item = &Entity> {
+        self.creation_order.iter().flatten()
     }
 
+    fn entity_count(&self) -> usize {
+        self.entity_count
+    }
+
+    /// Only valid for issued ids, which always have a generation.
     fn alive_generation(&self, entity_id: IndexT) -> GenerationT {
-        let entity_id = entity_id as usize;
-        if entity_id >= self.generations.len() {
-            return 0;
+        self.generations[entity_id as usize]
+    }
+
+    /// Check a loaded entity manager is consistent, so it can't index past its generations.
+    fn validate(&self) -> Result<(), EcsError> {
+        let error = |message: &str| Err(EcsError::Serialization(message.to_string()));
+        if self.next_entity_id as usize > self.generations.len() {
+            return error("more entity ids issued than there are generations");
         }
-        self.generations[entity_id]
+        if !self.free_entity_ids.iter().all(|id| self.is_issued(*id)) {
+            return error("free entity id was never issued");
+        }
+        if !self.entities_ordered().all(|entity| self.is_alive(*entity)) {
+            return error("entity in creation order is not alive");
+        }
+        Ok(())
     }
 }
 
 struct ComponentPool<T: Clone> {
-    components: Vec<(IndexT, Option<T>)>,
+    /// Each entity's components of this type, sorted by key.
+    /// Most entities have a single component, at key 0.
+    components: Vec<(IndexT, Vec<(u32, T)>)>,
 }
 
 impl<T: Clone> ComponentPool<T> {
-    fn new_one(entity: Entity, component: T) -> Self {
+    fn new_one(entity: Entity, key: u32, component: T) -> Self {
         // We make room for several extra components to avoid
         // increasing the capacity by 1 over and over
         // and thus causing lots of copying.
-        let mut components = vec![(0, None); VEC_RESIZE_MARGIN];
-        components[entity.id as usize] = (entity.generation, Some(component));
+        let mut components = vec![(0, Vec::new()); VEC_RESIZE_MARGIN];
+        components[entity.id as usize] = (entity.generation, vec![(key, component)]);
         Self { components }
     }
 
-    fn get(&self, entity: Entity) -> Option<&T> {
+    fn get_all(&self, entity: Entity) -> &[(u32, T)] {
         if entity.id as usize >= self.components.len() {
-            return None;
+            return &[];
         }
-        let generation_component = &self.components[entity.id as usize];
-        if generation_component.0 < entity.generation {
-            return None;
+        let generation_components = &self.components[entity.id as usize];
+        if generation_components.0 < entity.generation {
+            return &[];
         }
-        generation_component.1.as_ref()
+        &generation_components.1
+    }
+
+    /// The component with the lowest key.
+    fn get(&self, entity: Entity) -> Option<&T> {
+        self.get_all(entity).first().map(|(_, component)| component)
     }
 
+    /// The component with the lowest key.
     fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
         if entity.id as usize >= self.components.len() {
             return None;
         }
-        let generation_component = &mut self.components[entity.id as usize];
-        if generation_component.0 < entity.generation {
+        let generation_components = &mut self.components[entity.id as usize];
+        if generation_components.0 < entity.generation {
             return None;
         }
-        generation_component.1.as_mut()
+        generation_components
+            .1
+            .first_mut()
+            .map(|(_, component)| component)
     }
 
-    fn set(&mut self, entity: Entity, component: T) {
+    fn set(&mut self, entity: Entity, key: u32, component: T) {
         if entity.id as usize >= self.components.len() {
             // We make room for several extra components to avoid
             // increasing the capacity by 1 over and over
             // and thus causing lots of copying.
             self.components
-                .resize(entity.id as usize + VEC_RESIZE_MARGIN, (0, None));
+                .resize(entity.id as usize + VEC_RESIZE_MARGIN, (0, Vec::new()));
+        }
+        let generation_components = &mut self.components[entity.id as usize];
+        if generation_components.0 != entity.generation {
+            // Left over from a removed entity that had the same id.
+            *generation_components = (entity.generation, Vec::new());
+        }
+        let keyed_components = &mut generation_components.1;
+        match keyed_components.binary_search_by_key(&key, |(key, _)| *key) {
+            Ok(index) => keyed_components[index].1 = component,
+            Err(index) => keyed_components.insert(index, (key, component)),
         }
-        self.components[entity.id as usize] = (entity.generation, Some(component));
     }
 
+    /// Removes the entity's components of this type, whatever their keys.
     fn remove(&mut self, entity: Entity) {
         if entity.id as usize >= self.components.len() {
             return;
         }
-        self.components[entity.id as usize] = (entity.generation, None);
+        self.components[entity.id as usize] = (entity.generation, Vec::new());
+    }
+}
+
+/// Components saved by `Registry::save`, as `(key, component)` pairs.
+type SavedComponents = Vec<(u32, serde_json::Value)>;
+
+/// Converts one component type to and from JSON. See `Registry::register_serializable`.
+struct ComponentSerializer {
+    /// Identifies the component type in saved files.
+    name: &'static str,
+    save: fn(&EntityComponentManager, Entity) -> Result<SavedComponents, EcsError>,
+    load: fn(&mut EntityComponentManager, Entity, SavedComponents) -> Result<(), EcsError>,
+}
+
+impl ComponentSerializer {
+    fn new<T: Clone + Serialize + DeserializeOwned + 'static>(name: &'static str) -> Self {
+        Self {
+            name,
+            save: |ec_manager, entity| {
+                ec_manager
+                    .get_components_keyed::<T>(entity)?
+                    .iter()
+                    .map(|(key, component)| Ok((*key, serde_json::to_value(component)?)))
+                    .collect::<Result<_, serde_json::Error>>()
+                    .map_err(|e| EcsError::Serialization(e.to_string()))
+            },
+            load: |ec_manager, entity, saved_components| {
+                for (key, value) in saved_components {
+                    let component: T = serde_json::from_value(value)
+                        .map_err(|e| EcsError::Serialization(e.to_string()))?;
+                    ec_manager.add_component_keyed(entity, key, component)?;
+                }
+                Ok(())
+            },
+        }
     }
 }
 
+#[derive(Serialize, Deserialize)]
+struct SavedEntity {
+    entity: Entity,
+    /// Components by their serializer's name.
+    components: BTreeMap<String, SavedComponents>,
+}
+
+/// The format written by `Registry::save`.
+#[derive(Serialize, Deserialize)]
+struct SavedRegistry {
+    entity_manager: EntityManager,
+    entities: Vec<SavedEntity>,
+    tags: BTreeMap<String, Vec<Entity>>,
+}
+
 struct EntityComponentManager {
     entity_manager: EntityManager,
     entity_components: HashMap<Entity, HashSet<TypeId>>,
     component_pools: HashMap<TypeId, Box<dyn Any>>,
+    /// Global values that don't belong to any entity, at most one of each type.
+    resources: HashMap<TypeId, Box<dyn Any>>,
+    /// Creating more alive entities than this is probably a spawn leak.
+    max_entities: Option<usize>,
+    /// Entities by their `NameComponent`.
+    names: HashMap<String, Entity>,
+    tags: HashMap<String, HashSet<Entity>>,
+    /// Component types included by `save` and `load`.
+    serializers: HashMap<TypeId, ComponentSerializer>,
 }
 
 impl EntityComponentManager {
@@ -168,20 +373,200 @@ impl EntityComponentManager {
             entity_manager: EntityManager::new(),
             entity_components: HashMap::new(),
             component_pools: HashMap::new(),
+            resources: HashMap::new(),
+            max_entities: None,
+            names: HashMap::new(),
+            tags: HashMap::new(),
+            serializers: HashMap::new(),
         }
     }
 
     fn create_entity(&mut self) -> Entity {
+        if self.at_max_entities() {
+            log::warn!(
+                "Creating more than the maximum of {} entities",
+                self.max_entities.unwrap()
+            );
+        }
         let new_entity = self.entity_manager.create_entity();
         self.entity_components.insert(new_entity, HashSet::new());
         new_entity
     }
 
+    fn try_create_entity(&mut self) -> Result<Entity, EcsError> {
+        if self.at_max_entities() {
+            return Err(EcsError::TooManyEntities);
+        }
+        Ok(self.create_entity())
+    }
+
+    fn at_max_entities(&self) -> bool {
+        self.max_entities
+            .is_some_and(|max_entities| self.entity_count() >= max_entities)
+    }
+
+    fn entity_count(&self) -> usize {
+        self.entity_manager.entity_count()
+    }
+
     fn remove_entity(&mut self, entity: Entity) -> Result<(), EcsError> {
+        if self.is_alive(entity) {
+            self.unindex_name(entity);
+            self.tags.retain(|_, tagged_entities| {
+                tagged_entities.remove(&entity);
+                !tagged_entities.is_empty()
+            });
+        }
         self.entity_components.remove(&entity);
         self.entity_manager.remove_entity(entity)
     }
 
+    /// Forget the entity's name, if it has one.
+    /// This goes by entity, not by its current name, in case the name was changed in place.
+    fn unindex_name(&mut self, entity: Entity) {
+        self.names.retain(|_, named_entity| *named_entity != entity);
+    }
+
+    fn find_by_name(&self, name: &str) -> Option<Entity> {
+        let entity = *self.names.get(name)?;
+        if self.is_dead(entity) {
+            return None;
+        }
+        match self.get_component::<NameComponent>(entity) {
+            Ok(Some(NameComponent(current_name))) if current_name == name => Some(entity),
+            _ => None,
+        }
+    }
+
+    fn tag(&mut self, entity: Entity, tag: &str) -> Result<(), EcsError> {
+        if self.is_dead(entity) {
+            return Err(EcsError::DeadEntity);
+        }
+        self.tags.entry(tag.to_string()).or_default().insert(entity);
+        Ok(())
+    }
+
+    fn entities_with_tag(&self, tag: &str) -> impl Iterator<Item = Entity> + '_ {
+        self.tags
+            .get(tag)
+            .into_iter()
+            .flat_map(|tagged_entities| tagged_entities.iter().copied())
+    }
+
+    fn register_serializable<T: Clone + Serialize + DeserializeOwned + 'static>(
+        &mut self,
+        name: &'static str,
+    ) {
+        let type_id = TypeId::of::<T>();
+        assert!(
+            !self
+                .serializers
+                .iter()
+                .any(|(other_type_id, serializer)| serializer.name == name
+                    && *other_type_id != type_id),
+            "another component type is already saved as {name}"
+        );
+        self.serializers
+            .insert(type_id, ComponentSerializer::new::<T>(name));
+    }
+
+    fn save<W: std::io::Write>(&self, writer: W) -> Result<(), EcsError> {
+        let entities = self
+            .entities_ordered()
+            .map(|entity| {
+                let mut components = BTreeMap::new();
+                for type_id in &self.entity_components[entity] {
+                    if let Some(serializer) = self.serializers.get(type_id) {
+                        components.insert(
+                            serializer.name.to_string(),
+                            (serializer.save)(self, *entity)?,
+                        );
+                    }
+                }
+                Ok(SavedEntity {
+                    entity: *entity,
+                    components,
+                })
+            })
+            .collect::<Result<_, EcsError>>()?;
+        let tags = self
+            .tags
+            .iter()
+            .map(|(tag, tagged_entities)| {
+                let mut tagged_entities: Vec<Entity> = tagged_entities.iter().copied().collect();
+                tagged_entities.sort();
+                (tag.clone(), tagged_entities)
+            })
+            .collect();
+        let saved = SavedRegistry {
+            entity_manager: self.entity_manager.clone(),
+            entities,
+            tags,
+        };
+        serde_json::to_writer(writer, &saved).map_err(|e| EcsError::Serialization(e.to_string()))
+    }
+
+    /// Replaces all entities and components with the saved ones.
+    /// Resources, serializers, and the maximum entity count are kept.
+    /// If loading fails, nothing is changed.
+    fn load<R: std::io::Read>(&mut self, reader: R) -> Result<(), EcsError> {
+        let saved: SavedRegistry =
+            serde_json::from_reader(reader).map_err(|e| EcsError::Serialization(e.to_string()))?;
+        let loaders: HashMap<&str, _> = self
+            .serializers
+            .values()
+            .map(|serializer| (serializer.name, serializer.load))
+            .collect();
+        // Check every component type is known before changing anything.
+        for saved_entity in &saved.entities {
+            for name in saved_entity.components.keys() {
+                if !loaders.contains_key(name.as_str()) {
+                    return Err(EcsError::Serialization(format!(
+                        "unregistered component type: {name}"
+                    )));
+                }
+            }
+        }
+        saved.entity_manager.validate()?;
+        // Every alive entity is saved exactly once, even without components.
+        let alive: HashSet<Entity> = saved.entity_manager.entities_ordered().copied().collect();
+        let saved_entities: HashSet<Entity> = saved
+            .entities
+            .iter()
+            .map(|saved_entity| saved_entity.entity)
+            .collect();
+        if alive.len() != saved.entity_manager.entity_count()
+            || saved_entities.len() != saved.entities.len()
+            || saved_entities != alive
+        {
+            return Err(EcsError::Serialization(
+                "saved entities don't match the alive entities".to_string(),
+            ));
+        }
+        // Load into a scratch manager, so a component that fails to load changes nothing.
+        let mut loaded = EntityComponentManager::new();
+        loaded.entity_manager = saved.entity_manager;
+        loaded.tags = saved
+            .tags
+            .into_iter()
+            .map(|(tag, tagged_entities)| (tag, tagged_entities.into_iter().collect()))
+            .collect();
+        for saved_entity in saved.entities {
+            loaded
+                .entity_components
+                .insert(saved_entity.entity, HashSet::new());
+            for (name, saved_components) in saved_entity.components {
+                loaders[name.as_str()](&mut loaded, saved_entity.entity, saved_components)?;
+            }
+        }
+        self.entity_manager = loaded.entity_manager;
+        self.entity_components = loaded.entity_components;
+        self.component_pools = loaded.component_pools;
+        self.names = loaded.names;
+        self.tags = loaded.tags;
+        Ok(())
+    }
+
     fn is_alive(&self, entity: Entity) -> bool {
         self.entity_manager.is_alive(entity)
     }
@@ -190,14 +575,42 @@ impl EntityComponentManager {
         self.entity_manager.is_dead(entity)
     }
 
+    /// Whether the entity's id has been issued again, to a newer entity that is still alive.
+    /// Every alive entity has an entry in `entity_components`, even with no components,
+    /// so this doesn't need to search the free entity ids.
+    fn is_recycled(&self, entity: Entity) -> bool {
+        if !self.entity_manager.is_issued(entity.id) {
+            return false;
+        }
+        let newest = Entity {
+            id: entity.id,
+            generation: self.entity_manager.alive_generation(entity.id),
+        };
+        entity.generation < newest.generation && self.entity_components.contains_key(&newest)
+    }
+
     fn add_component<T: Clone + 'static>(
         &mut self,
         entity: Entity,
         component: T,
+    ) -> Result<(), EcsError> {
+        self.add_component_keyed(entity, 0, component)
+    }
+
+    fn add_component_keyed<T: Clone + 'static>(
+        &mut self,
+        entity: Entity,
+        key: u32,
+        component: T,
     ) -> Result<(), EcsError> {
         if self.is_dead(entity) {
             return Err(EcsError::DeadEntity);
         }
+        if let Some(NameComponent(name)) = (&component as &dyn Any).downcast_ref::<NameComponent>()
+        {
+            self.unindex_name(entity);
+            self.names.insert(name.clone(), entity);
+        }
         let type_id: TypeId = TypeId::of::<T>();
         self.entity_components
             .get_mut(&entity)
@@ -205,13 +618,13 @@ impl EntityComponentManager {
             .insert(type_id);
         match self.component_pools.get_mut(&type_id) {
             None => {
-                let new_component_pool = Box::new(ComponentPool::new_one(entity, component));
+                let new_component_pool = Box::new(ComponentPool::new_one(entity, key, component));
                 self.component_pools.insert(type_id, new_component_pool);
             }
             Some(component_pool) => {
                 let component_pool: &mut ComponentPool<T> =
-                    (&mut **component_pool).downcast_mut().unwrap();
-                component_pool.set(entity, component);
+                    (**component_pool).downcast_mut().unwrap();
+                component_pool.set(entity, key, component);
             }
         }
         Ok(())
@@ -222,6 +635,9 @@ impl EntityComponentManager {
             return Err(EcsError::DeadEntity);
         }
         let type_id: TypeId = TypeId::of::<T>();
+        if type_id == TypeId::of::<NameComponent>() {
+            self.unindex_name(entity);
+        }
         self.entity_components
             .get_mut(&entity)
             .unwrap()
@@ -232,13 +648,30 @@ impl EntityComponentManager {
             }
             Some(component_pool) => {
                 let component_pool: &mut ComponentPool<T> =
-                    (&mut **component_pool).downcast_mut().unwrap();
+                    (**component_pool).downcast_mut().unwrap();
                 component_pool.remove(entity);
             }
         }
         Ok(())
     }
 
+    /// Removes the component of type T from every entity.
+    /// Returns the entities that had the component.
+    fn remove_all<T: Clone + 'static>(&mut self) -> Vec<Entity> {
+        let type_id: TypeId = TypeId::of::<T>();
+        if type_id == TypeId::of::<NameComponent>() {
+            self.names.clear();
+        }
+        let mut affected_entities = Vec::new();
+        for (entity, components) in self.entity_components.iter_mut() {
+            if components.remove(&type_id) {
+                affected_entities.push(*entity);
+            }
+        }
+        self.component_pools.remove(&type_id);
+        affected_entities
+    }
+
     fn get_component<T: Clone + 'static>(&self, entity: Entity) -> Result<Option<&T>, EcsError> {
         if self.is_dead(entity) {
             return Err(EcsError::DeadEntity);
@@ -247,12 +680,37 @@ impl EntityComponentManager {
         match self.component_pools.get(&type_id) {
             None => Err(EcsError::NoSuchComponent),
             Some(component_pool) => {
-                let component_pool: &ComponentPool<T> = (&**component_pool).downcast_ref().unwrap();
+                let component_pool: &ComponentPool<T> = (**component_pool).downcast_ref().unwrap();
                 Ok(component_pool.get(entity))
             }
         }
     }
 
+    fn get_components<T: Clone + 'static>(
+        &self,
+        entity: Entity,
+    ) -> Result<impl Iterator<Item = &T>, EcsError> {
+        let keyed_components = self.get_components_keyed::<T>(entity)?;
+        Ok(keyed_components.iter().map(|(_, component)| component))
+    }
+
+    /// The entity's components of this type, with their keys, sorted by key.
+    fn get_components_keyed<T: Clone + 'static>(
+        &self,
+        entity: Entity,
+    ) -> Result<&[(u32, T)], EcsError> {
+        if self.is_dead(entity) {
+            return Err(EcsError::DeadEntity);
+        }
+        match self.component_pools.get(&TypeId::of::<T>()) {
+            None => Ok(&[]),
+            Some(component_pool) => {
+                let component_pool: &ComponentPool<T> = (**component_pool).downcast_ref().unwrap();
+                Ok(component_pool.get_all(entity))
+            }
+        }
+    }
+
     fn get_component_mut<T: Clone + 'static>(
         &mut self,
         entity: Entity,
@@ -265,12 +723,64 @@ impl EntityComponentManager {
             None => Err(EcsError::NoSuchComponent),
             Some(component_pool) => {
                 let component_pool: &mut ComponentPool<T> =
-                    (&mut **component_pool).downcast_mut().unwrap();
+                    (**component_pool).downcast_mut().unwrap();
                 Ok(component_pool.get_mut(entity))
             }
         }
     }
 
+    fn query_mut<A: Clone + 'static, B: Clone + 'static>(
+        &mut self,
+    ) -> impl Iterator<Item = (Entity, &mut A, &mut B)> {
+        let type_id_a: TypeId = TypeId::of::<A>();
+        let type_id_b: TypeId = TypeId::of::<B>();
+        assert_ne!(
+            type_id_a, type_id_b,
+            "query_mut needs two different component types"
+        );
+        let entity_manager = &self.entity_manager;
+        let entity_components = &self.entity_components;
+        let component_pools = match self
+            .component_pools
+            .get_disjoint_mut([&type_id_a, &type_id_b])
+        {
+            [Some(component_pool_a), Some(component_pool_b)] => {
+                Some((component_pool_a, component_pool_b))
+            }
+            _ => None,
+        };
+        component_pools
+            .into_iter()
+            .flat_map(move |(component_pool_a, component_pool_b)| {
+                let component_pool_a: &mut ComponentPool<A> =
+                    (**component_pool_a).downcast_mut().unwrap();
+                let component_pool_b: &mut ComponentPool<B> =
+                    (**component_pool_b).downcast_mut().unwrap();
+                component_pool_a
+                    .components
+                    .iter_mut()
+                    .zip(component_pool_b.components.iter_mut())
+                    .enumerate()
+                    .filter_map(move |(entity_id, ((_, a), (_, b)))| {
+                        // Pools have spare slots past the last issued id.
+                        if !entity_manager.is_issued(entity_id as IndexT) {
+                            return None;
+                        }
+                        let entity = Entity {
+                            id: entity_id as IndexT,
+                            generation: entity_manager.alive_generation(entity_id as IndexT),
+                        };
+                        // Pools keep stale components of removed entities,
+                        // so only trust the components the entity is known to have.
+                        let components = entity_components.get(&entity)?;
+                        if !(components.contains(&type_id_a) && components.contains(&type_id_b)) {
+                            return None;
+                        }
+                        Some((entity, &mut a.first_mut()?.1, &mut b.first_mut()?.1))
+                    })
+            })
+    }
+
     fn has_components(&self, entity: Entity) -> Result<&HashSet<TypeId>, EcsError> {
         if self.is_dead(entity) {
             return Err(EcsError::DeadEntity);
@@ -281,12 +791,114 @@ impl EntityComponentManager {
     fn entities_and_components(&self) -> impl Iterator<Item = (&Entity, &HashSet<TypeId>)> {
         self.entity_components.iter()
     }
+
+    fn entities_ordered(&self) -> impl Iterator<Item = &Entity> {
+        self.entity_manager.entities_ordered()
+    }
+
+    fn insert_resource<R: 'static>(&mut self, resource: R) {
+        self.resources.insert(TypeId::of::<R>(), Box::new(resource));
+    }
+
+    fn get_resource<R: 'static>(&self) -> Option<&R> {
+        self.resources
+            .get(&TypeId::of::<R>())
+            .map(|resource| resource.downcast_ref().unwrap())
+    }
+
+    fn get_resource_mut<R: 'static>(&mut self) -> Option<&mut R> {
+        self.resources
+            .get_mut(&TypeId::of::<R>())
+            .map(|resource| resource.downcast_mut().unwrap())
+    }
+}
+
+type ComponentAdder = Box<dyn FnOnce(&mut EntityComponentWrapper, Entity) -> Result<(), EcsError>>;
+
+enum Command {
+    Spawn(Vec<ComponentAdder>),
+    Despawn(Entity),
+    AddComponent(Entity, ComponentAdder),
+}
+
+/// Changes to entities that are applied after the current system or event handler returns,
+/// so the rest of the system sees a consistent world.
+/// Get one from `EntityComponentWrapper::commands`.
+/// Commands for entities that are dead by the time the commands are applied are ignored,
+/// so an entity can safely be despawned more than once.
+#[derive(Default)]
+pub struct CommandBuffer {
+    commands: Vec<Command>,
+}
+
+impl CommandBuffer {
+    /// Queue a new entity. Add components to it with `SpawnCommand::with`.
+    pub fn spawn(&mut self) -> SpawnCommand<'_> {
+        self.commands.push(Command::Spawn(Vec::new()));
+        let Some(Command::Spawn(component_adders)) = self.commands.last_mut() else {
+            unreachable!();
+        };
+        SpawnCommand { component_adders }
+    }
+
+    pub fn despawn(&mut self, entity: Entity) {
+        self.commands.push(Command::Despawn(entity));
+    }
+
+    pub fn add_component<T: Clone + 'static>(&mut self, entity: Entity, component: T) {
+        self.commands.push(Command::AddComponent(
+            entity,
+            Self::component_adder(component),
+        ));
+    }
+
+    fn component_adder<T: Clone + 'static>(component: T) -> ComponentAdder {
+        Box::new(move |ec_manager, entity| ec_manager.add_component(entity, component))
+    }
+
+    /// Apply the commands in the order they were queued.
+    fn apply(self, ec_manager: &mut EntityComponentWrapper) {
+        for command in self.commands {
+            match command {
+                Command::Spawn(component_adders) => {
+                    let entity = ec_manager.create_entity();
+                    for component_adder in component_adders {
+                        component_adder(ec_manager, entity).unwrap();
+                    }
+                }
+                Command::Despawn(entity) => {
+                    // Another command may already have despawned it.
+                    let _ = ec_manager.remove_entity(entity);
+                }
+                Command::AddComponent(entity, component_adder) => {
+                    let _ = component_adder(ec_manager, entity);
+                }
+            }
+        }
+    }
+}
+
+/// Adds components to an entity queued by `CommandBuffer::spawn`.
+pub struct SpawnCommand<'c> {
+    component_adders: &'c mut Vec<ComponentAdder>,
+}
+
+impl SpawnCommand<'_> {
+    pub fn with<T: Clone + 'static>(self, component: T) -> Self {
+        self.component_adders
+            .push(CommandBuffer::component_adder(component));
+        self
+    }
 }
 
 pub struct EntityComponentWrapper<'ec> {
     ec_manager: &'ec mut EntityComponentManager,
     changed_entities: HashSet<Entity>,
+    created_entities: HashSet<Entity>,
+    removed_entities: HashSet<Entity>,
     dispatched_events: Vec<(TypeId, Box<dyn Any>)>,
+    enqueued_events: Vec<(TypeId, Box<dyn Any>)>,
+    commands: CommandBuffer,
 }
 
 impl<'ec> EntityComponentWrapper<'ec> {
@@ -294,19 +906,40 @@ impl<'ec> EntityComponentWrapper<'ec> {
         Self {
             ec_manager,
             changed_entities: HashSet::new(),
+            created_entities: HashSet::new(),
+            removed_entities: HashSet::new(),
             dispatched_events: Vec::new(),
+            enqueued_events: Vec::new(),
+            commands: CommandBuffer::default(),
         }
     }
 
     pub fn create_entity(&mut self) -> Entity {
         let new_entity = self.ec_manager.create_entity();
         self.changed_entities.insert(new_entity);
+        self.created_entities.insert(new_entity);
         new_entity
     }
 
+    /// Like `create_entity`, but fails instead of exceeding the registry's maximum entity count.
+    pub fn try_create_entity(&mut self) -> Result<Entity, EcsError> {
+        let new_entity = self.ec_manager.try_create_entity()?;
+        self.changed_entities.insert(new_entity);
+        self.created_entities.insert(new_entity);
+        Ok(new_entity)
+    }
+
+    pub fn entity_count(&self) -> usize {
+        self.ec_manager.entity_count()
+    }
+
     pub fn remove_entity(&mut self, entity: Entity) -> Result<(), EcsError> {
         self.changed_entities.insert(entity);
-        self.ec_manager.remove_entity(entity)
+        let result = self.ec_manager.remove_entity(entity);
+        if result.is_ok() {
+            self.removed_entities.insert(entity);
+        }
+        result
     }
 
     pub fn is_alive(&self, entity: Entity) -> bool {
@@ -326,11 +959,27 @@ impl<'ec> EntityComponentWrapper<'ec> {
         self.ec_manager.add_component(entity, component)
     }
 
+    /// See `Registry::add_component_keyed`.
+    pub fn add_component_keyed<T: Clone + 'static>(
+        &mut self,
+        entity: Entity,
+        key: u32,
+        component: T,
+    ) -> Result<(), EcsError> {
+        self.changed_entities.insert(entity);
+        self.ec_manager.add_component_keyed(entity, key, component)
+    }
+
     pub fn remove_component<T: Clone + 'static>(&mut self, entity: Entity) -> Result<(), EcsError> {
         self.changed_entities.insert(entity);
         self.ec_manager.remove_component::<T>(entity)
     }
 
+    pub fn remove_all<T: Clone + 'static>(&mut self) {
+        let affected_entities = self.ec_manager.remove_all::<T>();
+        self.changed_entities.extend(affected_entities);
+    }
+
     pub fn get_component<T: Clone + 'static>(
         &self,
         entity: Entity,
@@ -345,6 +994,22 @@ impl<'ec> EntityComponentWrapper<'ec> {
         self.ec_manager.get_component_mut(entity)
     }
 
+    /// See `Registry::get_components`.
+    pub fn get_components<T: Clone + 'static>(
+        &self,
+        entity: Entity,
+    ) -> Result<impl Iterator<Item = &T>, EcsError> {
+        self.ec_manager.get_components(entity)
+    }
+
+    /// Every entity with both an `A` and a `B` component, with mutable access to both at once.
+    /// Panics if `A` and `B` are the same type.
+    pub fn query_mut<A: Clone + 'static, B: Clone + 'static>(
+        &mut self,
+    ) -> impl Iterator<Item = (Entity, &mut A, &mut B)> {
+        self.ec_manager.query_mut::<A, B>()
+    }
+
     pub fn has_components(&self, entity: Entity) -> Result<&HashSet<TypeId>, EcsError> {
         self.ec_manager.has_components(entity)
     }
@@ -357,14 +1022,44 @@ impl<'ec> EntityComponentWrapper<'ec> {
         self.ec_manager.entities_and_components()
     }
 
+    /// Alive entities in the order they were created.
+    pub fn entities_ordered(&self) -> impl Iterator<Item = &Entity> {
+        self.ec_manager.entities_ordered()
+    }
+
     pub fn changed_entities(&self) -> impl Iterator<Item = &Entity> {
         self.changed_entities.iter()
     }
 
+    pub fn get_resource<R: 'static>(&self) -> Option<&R> {
+        self.ec_manager.get_resource()
+    }
+
+    pub fn get_resource_mut<R: 'static>(&mut self) -> Option<&mut R> {
+        self.ec_manager.get_resource_mut()
+    }
+
+    /// Queue an event; handlers run once the current system or `run_once` closure returns.
     pub fn dispatch_event<E: 'static>(&mut self, event: E) {
         self.dispatched_events
             .push((TypeId::of::<E>(), Box::new(event)));
     }
+
+    /// Queue an event for the next `Registry::drain_events`, usually next frame.
+    pub fn enqueue_event<E: 'static>(&mut self, event: E) {
+        self.enqueued_events
+            .push((TypeId::of::<E>(), Box::new(event)));
+    }
+
+    /// Queue changes to apply once the current system, `run_once` closure, or handler returns.
+    pub fn commands(&mut self) -> &mut CommandBuffer {
+        &mut self.commands
+    }
+
+    fn apply_commands(&mut self) {
+        let commands = std::mem::take(&mut self.commands);
+        commands.apply(self);
+    }
 }
 
 pub trait SystemBase {
@@ -372,6 +1067,8 @@ pub trait SystemBase {
     fn required_components(&self) -> &HashSet<TypeId>;
     fn add_entity(&mut self, entity: Entity);
     fn remove_entity(&mut self, entity: Entity);
+    /// The entities this system is currently interested in.
+    fn entities(&self) -> Vec<Entity>;
 }
 
 pub trait System: SystemBase {
@@ -380,18 +1077,85 @@ pub trait System: SystemBase {
     fn run(&self, ec_manager: &mut EntityComponentWrapper, input: Self::Input<'_>);
 }
 
+/// Systems in the order they were added.
+/// Systems are told about entities in this order, so that runs are reproducible.
+#[derive(Default)]
+struct Systems {
+    systems: Vec<(TypeId, Rc<RefCell<dyn SystemBase>>)>,
+}
+
+impl Systems {
+    fn get(&self, type_id: &TypeId) -> Option<&Rc<RefCell<dyn SystemBase>>> {
+        self.systems
+            .iter()
+            .find(|(system_type_id, _)| system_type_id == type_id)
+            .map(|(_, system)| system)
+    }
+
+    fn contains_key(&self, type_id: &TypeId) -> bool {
+        self.get(type_id).is_some()
+    }
+
+    /// A replaced system keeps its place in the order.
+    fn insert(
+        &mut self,
+        type_id: TypeId,
+        system: Rc<RefCell<dyn SystemBase>>,
+    ) -> Option<Rc<RefCell<dyn SystemBase>>> {
+        match self
+            .systems
+            .iter_mut()
+            .find(|(system_type_id, _)| *system_type_id == type_id)
+        {
+            Some((_, existing_system)) => Some(std::mem::replace(existing_system, system)),
+            None => {
+                self.systems.push((type_id, system));
+                None
+            }
+        }
+    }
+
+    fn remove(&mut self, type_id: &TypeId) -> Option<Rc<RefCell<dyn SystemBase>>> {
+        let index = self
+            .systems
+            .iter()
+            .position(|(system_type_id, _)| system_type_id == type_id)?;
+        Some(self.systems.remove(index).1)
+    }
+
+    fn values_mut(&mut self) -> impl Iterator<Item = &mut Rc<RefCell<dyn SystemBase>>> {
+        self.systems.iter_mut().map(|(_, system)| system)
+    }
+}
+
 pub struct Registry {
     ec_manager: EntityComponentManager,
-    systems: HashMap<TypeId, Rc<RefCell<dyn SystemBase>>>,
+    systems: Systems,
+    /// Systems that are skipped by `run_system`.
+    /// Disabled systems still track which entities they are interested in.
+    disabled_systems: HashSet<TypeId>,
     event_bus: EventBus,
+    /// Entities created by the last `run_system` or `dispatch_event`.
+    created_entities: HashSet<Entity>,
+    /// Entities removed by the last `run_system` or `dispatch_event`.
+    removed_entities: HashSet<Entity>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Registry {
     pub fn new() -> Self {
         Self {
             ec_manager: EntityComponentManager::new(),
-            systems: HashMap::new(),
+            systems: Systems::default(),
+            disabled_systems: HashSet::new(),
             event_bus: EventBus::new(),
+            created_entities: HashSet::new(),
+            removed_entities: HashSet::new(),
         }
     }
 
@@ -400,6 +1164,22 @@ impl Registry {
         self.ec_manager.create_entity()
     }
 
+    /// Like `create_entity`, but fails instead of exceeding the maximum entity count.
+    pub fn try_create_entity(&mut self) -> Result<Entity, EcsError> {
+        self.ec_manager.try_create_entity()
+    }
+
+    /// The number of alive entities.
+    pub fn entity_count(&self) -> usize {
+        self.ec_manager.entity_count()
+    }
+
+    /// Limit the number of alive entities, to catch spawn leaks during development.
+    /// Beyond the limit, `create_entity` logs a warning and `try_create_entity` returns an error.
+    pub fn set_max_entities(&mut self, max_entities: Option<usize>) {
+        self.ec_manager.max_entities = max_entities;
+    }
+
     pub fn remove_entity(&mut self, entity: Entity) -> Result<(), EcsError> {
         for system in self.systems.values_mut() {
             system.borrow_mut().remove_entity(entity);
@@ -407,6 +1187,9 @@ impl Registry {
         self.ec_manager.remove_entity(entity)
     }
 
+    /// Whether this exact entity is alive.
+    /// An `Entity` handle includes a generation, so once the entity is removed this stays false,
+    /// even after its id is reused by a new entity. See `same_entity` and `is_recycled`.
     pub fn is_alive(&self, entity: Entity) -> bool {
         self.ec_manager.is_alive(entity)
     }
@@ -415,6 +1198,19 @@ impl Registry {
         self.ec_manager.is_dead(entity)
     }
 
+    /// Whether a handle held across frames still refers to the entity it was taken from,
+    /// rather than to nothing or to a newer entity that reused its id.
+    /// This is the same check as `is_alive`, named for when the handle is old.
+    pub fn same_entity(&self, old: Entity) -> bool {
+        self.ec_manager.is_alive(old)
+    }
+
+    /// Whether the entity was removed and its id now belongs to a different, alive entity.
+    /// Unlike `is_dead`, this is false while the id is still waiting to be reused.
+    pub fn is_recycled(&self, old: Entity) -> bool {
+        self.ec_manager.is_recycled(old)
+    }
+
     pub fn add_component<T: Clone + 'static>(
         &mut self,
         entity: Entity,
@@ -422,20 +1218,48 @@ impl Registry {
     ) -> Result<(), EcsError> {
         let result = self.ec_manager.add_component(entity, component);
         if result.is_ok() {
-            for system in self.systems.values_mut() {
-                if self
-                    .ec_manager
-                    .has_components(entity)
-                    .unwrap()
-                    .is_superset(system.borrow().required_components())
-                {
-                    system.borrow_mut().add_entity(entity);
-                }
-            }
+            self.add_entity_to_systems(entity);
         }
         result
     }
 
+    /// Add one of several components of the same type, such as separate hit and hurt boxes.
+    /// Adding a component with a key the entity already has replaces that component.
+    /// `add_component` uses key 0, and `get_component` returns the component with the lowest key.
+    /// Removing the component type removes the components with every key.
+    pub fn add_component_keyed<T: Clone + 'static>(
+        &mut self,
+        entity: Entity,
+        key: u32,
+        component: T,
+    ) -> Result<(), EcsError> {
+        let result = self.ec_manager.add_component_keyed(entity, key, component);
+        if result.is_ok() {
+            self.add_entity_to_systems(entity);
+        }
+        result
+    }
+
+    /// Start building a new entity.
+    /// Components added through the builder are only matched against systems once, when `build` is called.
+    pub fn entity_builder(&mut self) -> EntityBuilder<'_> {
+        let entity = self.create_entity();
+        EntityBuilder {
+            registry: self,
+            entity,
+        }
+    }
+
+    /// Add the entity to every system interested in it.
+    fn add_entity_to_systems(&mut self, entity: Entity) {
+        let components = self.ec_manager.has_components(entity).unwrap();
+        for system in self.systems.values_mut() {
+            if components.is_superset(system.borrow().required_components()) {
+                system.borrow_mut().add_entity(entity);
+            }
+        }
+    }
+
     pub fn remove_component<T: Clone + 'static>(&mut self, entity: Entity) -> Result<(), EcsError> {
         let result = self.ec_manager.remove_component::<T>(entity);
         if result.is_ok() {
@@ -453,6 +1277,20 @@ impl Registry {
         result
     }
 
+    /// Removes the component of type T from every entity that has it,
+    /// and removes those entities from systems that required it.
+    pub fn remove_all<T: Clone + 'static>(&mut self) {
+        let affected_entities = self.ec_manager.remove_all::<T>();
+        let type_id: TypeId = TypeId::of::<T>();
+        for system in self.systems.values_mut() {
+            if system.borrow().required_components().contains(&type_id) {
+                for entity in affected_entities.iter() {
+                    system.borrow_mut().remove_entity(*entity);
+                }
+            }
+        }
+    }
+
     pub fn get_component<T: Clone + 'static>(
         &self,
         entity: Entity,
@@ -467,6 +1305,29 @@ impl Registry {
         self.ec_manager.get_component_mut(entity)
     }
 
+    /// All of the entity's components of type T, in key order.
+    pub fn get_components<T: Clone + 'static>(
+        &self,
+        entity: Entity,
+    ) -> Result<impl Iterator<Item = &T>, EcsError> {
+        self.ec_manager.get_components(entity)
+    }
+
+    /// Get the component, adding the given default first if the entity doesn't have one.
+    pub fn get_or_insert_component<T: Clone + 'static>(
+        &mut self,
+        entity: Entity,
+        default: T,
+    ) -> Result<&mut T, EcsError> {
+        if !matches!(self.get_component::<T>(entity), Ok(Some(_))) {
+            self.add_component(entity, default)?;
+        }
+        Ok(self.get_component_mut(entity)?.unwrap())
+    }
+
+    /// Add a system, which will be told about every entity it's interested in.
+    /// There can only be one system of each type; adding another replaces the first.
+    /// A newly added system is always enabled, even if the one it replaces was disabled.
     pub fn add_system<S: System + 'static>(&mut self, system: Rc<RefCell<S>>) {
         for (entity, components) in self.ec_manager.entities_and_components() {
             if components.is_superset(system.borrow().required_components()) {
@@ -474,17 +1335,57 @@ impl Registry {
             }
         }
         let type_id: TypeId = TypeId::of::<S>();
-        self.systems.insert(type_id, system);
+        if self.systems.insert(type_id, system).is_some() {
+            log::warn!(
+                "Replaced existing system of the same type: {}",
+                std::any::type_name::<S>()
+            );
+        }
+        self.disabled_systems.remove(&type_id);
     }
 
+    /// Remove a system. If the system was also added as a handler, it stops receiving events.
     pub fn remove_system<S: System + 'static>(&mut self) {
         let type_id: TypeId = TypeId::of::<S>();
-        self.systems.remove(&type_id);
+        if let Some(system) = self.systems.remove(&type_id) {
+            self.event_bus
+                .remove_handler_at(Rc::as_ptr(&system) as *const ());
+        }
+        self.disabled_systems.remove(&type_id);
+    }
+
+    /// Enable or disable a system without removing it.
+    /// A disabled system keeps its entities up to date, but `run_system` will skip it.
+    pub fn set_system_enabled<S: System + 'static>(
+        &mut self,
+        enabled: bool,
+    ) -> Result<(), EcsError> {
+        let type_id: TypeId = TypeId::of::<S>();
+        if !self.systems.contains_key(&type_id) {
+            return Err(EcsError::NoSuchSystem);
+        }
+        if enabled {
+            self.disabled_systems.remove(&type_id);
+        } else {
+            self.disabled_systems.insert(type_id);
+        }
+        Ok(())
+    }
+
+    pub fn is_system_enabled<S: System + 'static>(&self) -> bool {
+        let type_id: TypeId = TypeId::of::<S>();
+        self.systems.contains_key(&type_id) && !self.disabled_systems.contains(&type_id)
+    }
+
+    /// The entities a system is currently interested in, or None if the system isn't registered.
+    pub fn system_entities<S: System + 'static>(&self) -> Option<impl Iterator<Item = Entity>> {
+        let type_id: TypeId = TypeId::of::<S>();
+        self.systems
+            .get(&type_id)
+            .map(|system| system.borrow().entities().into_iter())
     }
 
-    fn get_system<S: System + 'static>(
-        systems: &HashMap<TypeId, Rc<RefCell<dyn SystemBase>>>,
-    ) -> Option<Rc<RefCell<S>>> {
+    fn get_system<S: System + 'static>(systems: &Systems) -> Option<Rc<RefCell<S>>> {
         let type_id = TypeId::of::<S>();
         if let Some(system_base) = systems.get(&type_id) {
             let system_base = Rc::clone(system_base);
@@ -497,10 +1398,7 @@ impl Registry {
         None
     }
 
-    fn update_system_entities(
-        systems: &mut HashMap<TypeId, Rc<RefCell<dyn SystemBase>>>,
-        ec_wrapper: &mut EntityComponentWrapper,
-    ) {
+    fn update_system_entities(systems: &mut Systems, ec_wrapper: &mut EntityComponentWrapper) {
         for entity in ec_wrapper.changed_entities() {
             for system in systems.values_mut() {
                 if let Ok(has_components) = ec_wrapper.has_components(*entity) {
@@ -522,46 +1420,131 @@ impl Registry {
         if system.is_none() {
             return Err(EcsError::NoSuchSystem);
         }
-        system.unwrap().borrow().run(&mut ec_wrapper, input);
+        if self.disabled_systems.contains(&TypeId::of::<S>()) {
+            // Nothing ran, so nothing was created or removed during this run.
+            self.created_entities.clear();
+            self.removed_entities.clear();
+            return Ok(());
+        }
+        let system = system.unwrap();
+        // Only time systems if someone wants the timings.
+        let start_time = ec_wrapper
+            .get_resource::<SystemTimings>()
+            .map(|_| std::time::Instant::now());
+        let run_result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            system.borrow().run(&mut ec_wrapper, input);
+        }));
+        if let Some(start_time) = start_time {
+            if let Some(system_timings) = ec_wrapper.get_resource_mut::<SystemTimings>() {
+                system_timings.record(
+                    std::any::type_name::<S>(),
+                    start_time.elapsed().as_secs_f32(),
+                );
+            }
+        }
+        // Commands from a system that panicked are dropped, rather than partly applied.
+        if run_result.is_ok() {
+            ec_wrapper.apply_commands();
+        }
+        // Even if the system panicked, keep system membership in sync with
+        // whatever changes the system made before it panicked.
         Self::update_system_entities(&mut self.systems, &mut ec_wrapper);
+        if let Err(panic) = run_result {
+            self.created_entities = std::mem::take(&mut ec_wrapper.created_entities);
+            self.removed_entities = std::mem::take(&mut ec_wrapper.removed_entities);
+            let message = if let Some(message) = panic.downcast_ref::<&str>() {
+                message.to_string()
+            } else if let Some(message) = panic.downcast_ref::<String>() {
+                message.clone()
+            } else {
+                "unknown panic".to_string()
+            };
+            return Err(EcsError::SystemPanicked {
+                system: std::any::type_name::<S>(),
+                message,
+            });
+        }
+        Self::process_events(&mut self.systems, &mut self.event_bus, &mut ec_wrapper);
+        self.created_entities = std::mem::take(&mut ec_wrapper.created_entities);
+        self.removed_entities = std::mem::take(&mut ec_wrapper.removed_entities);
+        Ok(())
+    }
+
+    /// Dispatch events until handlers stop dispatching new events.
+    /// Enqueued events are kept for the next `drain_events`.
+    fn process_events(
+        systems: &mut Systems,
+        event_bus: &mut EventBus,
+        ec_wrapper: &mut EntityComponentWrapper,
+    ) {
         loop {
-            let dispatched_events =
-                std::mem::replace(&mut ec_wrapper.dispatched_events, Vec::new());
-            if dispatched_events.len() == 0 {
+            let dispatched_events = std::mem::take(&mut ec_wrapper.dispatched_events);
+            if dispatched_events.is_empty() {
                 break;
             }
             for event in dispatched_events {
                 let e0: TypeId = event.0;
                 let e1: Box<dyn Any> = event.1;
-                self.event_bus.dispatch(&mut ec_wrapper, e0, &*e1);
-                Self::update_system_entities(&mut self.systems, &mut ec_wrapper);
+                event_bus.dispatch(ec_wrapper, e0, &*e1);
+                ec_wrapper.apply_commands();
+                Self::update_system_entities(systems, ec_wrapper);
             }
         }
-        Ok(())
+        for (type_id, event) in std::mem::take(&mut ec_wrapper.enqueued_events) {
+            event_bus.enqueue_any(type_id, event);
+        }
+    }
+
+    /// Run a closure once, with the same access to entities, components, and resources as a system.
+    /// Useful for one-off setup, such as spawning the initial scene.
+    pub fn run_once<F: FnOnce(&mut EntityComponentWrapper)>(&mut self, f: F) {
+        let mut ec_wrapper = EntityComponentWrapper::new(&mut self.ec_manager);
+        f(&mut ec_wrapper);
+        ec_wrapper.apply_commands();
+        Self::update_system_entities(&mut self.systems, &mut ec_wrapper);
+        Self::process_events(&mut self.systems, &mut self.event_bus, &mut ec_wrapper);
+        self.created_entities = std::mem::take(&mut ec_wrapper.created_entities);
+        self.removed_entities = std::mem::take(&mut ec_wrapper.removed_entities);
     }
 
+    /// Dispatch an event from outside of a system, such as input from the window.
+    /// Handlers run immediately, along with any events they dispatch in turn, and system
+    /// membership is updated afterward just like after `run_system`.
     pub fn dispatch_event<E: 'static>(&mut self, event: E) {
         let mut ec_wrapper = EntityComponentWrapper::new(&mut self.ec_manager);
         ec_wrapper.dispatch_event(event);
-        loop {
-            let dispatched_events =
-                std::mem::replace(&mut ec_wrapper.dispatched_events, Vec::new());
-            if dispatched_events.len() == 0 {
-                break;
-            }
-            for event in dispatched_events {
-                let e0: TypeId = event.0;
-                let e1: Box<dyn Any> = event.1;
-                self.event_bus.dispatch(&mut ec_wrapper, e0, &*e1);
-                Self::update_system_entities(&mut self.systems, &mut ec_wrapper);
-            }
-        }
+        Self::process_events(&mut self.systems, &mut self.event_bus, &mut ec_wrapper);
+        self.created_entities = std::mem::take(&mut ec_wrapper.created_entities);
+        self.removed_entities = std::mem::take(&mut ec_wrapper.removed_entities);
+    }
+
+    /// Queue an event for the next `drain_events`, rather than dispatching it immediately.
+    pub fn enqueue_event<E: 'static>(&mut self, event: E) {
+        self.event_bus.enqueue(event);
+    }
+
+    /// Dispatch the events queued so far. Call this once per frame.
+    /// Events that handlers queue while draining wait for the next drain, so handlers can safely
+    /// queue follow-up events, even for themselves.
+    pub fn drain_events(&mut self) {
+        let mut ec_wrapper = EntityComponentWrapper::new(&mut self.ec_manager);
+        ec_wrapper.dispatched_events = self.event_bus.take_queue();
+        Self::process_events(&mut self.systems, &mut self.event_bus, &mut ec_wrapper);
+        self.created_entities = std::mem::take(&mut ec_wrapper.created_entities);
+        self.removed_entities = std::mem::take(&mut ec_wrapper.removed_entities);
     }
 
     pub fn add_handler<E: 'static, H: Handler<E> + 'static>(&mut self, handler: Rc<RefCell<H>>) {
         self.event_bus.add_handler(handler)
     }
 
+    pub fn remove_handler<E: 'static, H: Handler<E> + 'static>(
+        &mut self,
+        handler: &Rc<RefCell<H>>,
+    ) {
+        self.event_bus.remove_handler::<E, H>(handler)
+    }
+
     pub fn entities(&self) -> impl Iterator<Item = &Entity> {
         self.ec_manager.entities_and_components().map(|(e, _c)| e)
     }
@@ -569,13 +1552,132 @@ impl Registry {
     pub fn entities_and_components(&self) -> impl Iterator<Item = (&Entity, &HashSet<TypeId>)> {
         self.ec_manager.entities_and_components()
     }
+
+    /// Alive entities in the order they were created.
+    pub fn entities_ordered(&self) -> impl Iterator<Item = &Entity> {
+        self.ec_manager.entities_ordered()
+    }
+
+    /// The entity with the given `NameComponent`, if it's alive.
+    pub fn find_by_name(&self, name: &str) -> Option<Entity> {
+        self.ec_manager.find_by_name(name)
+    }
+
+    /// Tag an entity. Unlike names, a tag may be shared by many entities, such as "enemy".
+    /// Tags are forgotten when the entity is removed.
+    pub fn tag(&mut self, entity: Entity, tag: &str) -> Result<(), EcsError> {
+        self.ec_manager.tag(entity, tag)
+    }
+
+    pub fn entities_with_tag(&self, tag: &str) -> impl Iterator<Item = Entity> + '_ {
+        self.ec_manager.entities_with_tag(tag)
+    }
+
+    /// Include components of this type in `save` and `load`.
+    /// Components of unregistered types are left out of saves.
+    /// Saves identify the type by `name`, so keep it the same even if the type is renamed or moved.
+    /// Panics if another type is already registered with the same name.
+    pub fn register_serializable<T: Clone + Serialize + DeserializeOwned + 'static>(
+        &mut self,
+        name: &'static str,
+    ) {
+        self.ec_manager.register_serializable::<T>(name)
+    }
+
+    /// Write all entities, their registered components, and tags as JSON.
+    /// Resources, systems, and handlers are not saved.
+    /// Sprite indices are only valid when loaded into a registry that uses the same renderer.
+    pub fn save<W: std::io::Write>(&self, writer: W) -> Result<(), EcsError> {
+        self.ec_manager.save(writer)
+    }
+
+    /// Replace all entities with those written by `save`, keeping the same entity handles.
+    /// Every saved component type must be registered with `register_serializable`.
+    /// Systems are told about the loaded entities; resources and systems are left as they are.
+    /// If loading fails, the registry is left unchanged.
+    pub fn load<R: std::io::Read>(&mut self, reader: R) -> Result<(), EcsError> {
+        let old_entities: Vec<Entity> = self.entities_ordered().copied().collect();
+        self.ec_manager.load(reader)?;
+        for entity in old_entities {
+            for system in self.systems.values_mut() {
+                system.borrow_mut().remove_entity(entity);
+            }
+        }
+        let entities: Vec<Entity> = self.entities_ordered().copied().collect();
+        for entity in entities {
+            self.add_entity_to_systems(entity);
+        }
+        Ok(())
+    }
+
+    /// Store a global value that doesn't belong to any entity, such as gravity or the map scale.
+    /// There is at most one resource of each type; inserting another replaces the first.
+    /// Systems can reach resources through the `EntityComponentWrapper`.
+    pub fn insert_resource<R: 'static>(&mut self, resource: R) {
+        self.ec_manager.insert_resource(resource)
+    }
+
+    pub fn get_resource<R: 'static>(&self) -> Option<&R> {
+        self.ec_manager.get_resource()
+    }
+
+    pub fn get_resource_mut<R: 'static>(&mut self) -> Option<&mut R> {
+        self.ec_manager.get_resource_mut()
+    }
+
+    /// Entities created by systems and event handlers during the last `run_system` or `dispatch_event`.
+    pub fn created_entities(&self) -> impl Iterator<Item = &Entity> {
+        self.created_entities.iter()
+    }
+
+    /// Entities removed by systems and event handlers during the last `run_system` or `dispatch_event`.
+    pub fn removed_entities(&self) -> impl Iterator<Item = &Entity> {
+        self.removed_entities.iter()
+    }
+}
+
+/// Builds an entity with several components.
+/// Systems are not told about the entity until `build` is called.
+#[must_use = "systems won't see the entity until build is called"]
+pub struct EntityBuilder<'r> {
+    registry: &'r mut Registry,
+    entity: Entity,
+}
+
+impl<'r> EntityBuilder<'r> {
+    pub fn with<T: Clone + 'static>(self, component: T) -> Self {
+        // The entity was just created, so it can't be dead.
+        self.registry
+            .ec_manager
+            .add_component(self.entity, component)
+            .unwrap();
+        self
+    }
+
+    pub fn build(self) -> Entity {
+        self.registry.add_entity_to_systems(self.entity);
+        self.entity
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Entity, EntityComponentWrapper, EntityManager, Registry, System, SystemBase};
+    use super::{
+        EcsError, Entity, EntityComponentWrapper, EntityManager, NameComponent, Registry, System,
+        SystemBase,
+    };
+    use crate::components_systems::{
+        CollisionEvent, CollisionSystem, Layer, NoOpSystem, ParentComponent, RigidBodyComponent,
+        SpriteComponent,
+    };
+    use crate::event_bus::Handler;
+    use crate::impl_handler_base;
+    use crate::profiling::SystemTimings;
+    use crate::renderer::SpriteIndex;
     use std::any::{Any, TypeId};
+    use std::cell::RefCell;
     use std::collections::HashSet;
+    use std::rc::Rc;
 
     #[test]
     fn test_entity_manager_happy_path() {
@@ -609,6 +1711,44 @@ mod tests {
         assert!(em.remove_entity(e1).is_err());
     }
 
+    #[test]
+    fn test_entity_manager_high_ids() {
+        let mut em = EntityManager::new();
+        let entities: Vec<Entity> = (0..100).map(|_| em.create_entity()).collect();
+        assert!(entities
+            .iter()
+            .all(|e| (e.id as usize) < em.generations.len()));
+
+        let high = entities[97];
+        em.remove_entity(high).unwrap();
+        assert!(em.is_dead(high));
+        for e in &entities[..97] {
+            assert!(em.is_alive(*e));
+        }
+        assert!(em.is_alive(entities[98]));
+        assert!(em.is_alive(entities[99]));
+
+        // Ids that were never issued are not alive, even with a matching generation.
+        for id in 100..120 {
+            assert!(em.is_dead(Entity { id, generation: 0 }));
+        }
+        assert!(em
+            .remove_entity(Entity {
+                id: 150,
+                generation: 0
+            })
+            .is_err());
+
+        let reused = em.create_entity();
+        assert_eq!(reused.id, high.id);
+        assert_eq!(reused.generation, 1);
+        assert!(em.is_alive(reused));
+        assert!(em.is_dead(high));
+        let next = em.create_entity();
+        assert_eq!(next.id, 100);
+        assert!(em.is_alive(next));
+    }
+
     #[test]
     fn test_registry_happy_path() {
         let mut registry: Registry = Registry::new();
@@ -677,6 +1817,10 @@ mod tests {
         fn remove_entity(&mut self, entity: Entity) {
             self.entities.remove(&entity);
         }
+
+        fn entities(&self) -> Vec<Entity> {
+            self.entities.iter().copied().collect()
+        }
     }
 
     impl System for CounterIncrementSystem {
@@ -754,4 +1898,1164 @@ mod tests {
         registry.run_system::<CounterIncrementSystem>(1).unwrap();
         assert_eq!(registry.entities().count(), 4);
     }
+
+    /// A system that claims to only need `CounterComponent`, but also reads an `i32`.
+    struct MisconfiguredSystem {
+        required_components: HashSet<TypeId>,
+        entities: HashSet<Entity>,
+    }
+
+    impl MisconfiguredSystem {
+        fn new() -> Self {
+            let mut required_components = HashSet::new();
+            required_components.insert(TypeId::of::<CounterComponent>());
+            Self {
+                required_components,
+                entities: HashSet::new(),
+            }
+        }
+    }
+
+    impl SystemBase for MisconfiguredSystem {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn required_components(&self) -> &HashSet<TypeId> {
+            &self.required_components
+        }
+
+        fn add_entity(&mut self, entity: Entity) {
+            self.entities.insert(entity);
+        }
+
+        fn remove_entity(&mut self, entity: Entity) {
+            self.entities.remove(&entity);
+        }
+
+        fn entities(&self) -> Vec<Entity> {
+            self.entities.iter().copied().collect()
+        }
+    }
+
+    impl System for MisconfiguredSystem {
+        type Input<'i> = ();
+
+        fn run(&self, ec_manager: &mut EntityComponentWrapper, _input: Self::Input<'_>) {
+            for entity in self.entities.iter() {
+                let _: &i32 = ec_manager
+                    .get_component(*entity)
+                    .unwrap()
+                    .expect("missing i32 component");
+            }
+        }
+    }
+
+    #[test]
+    fn test_run_system_returns_error_on_panic() {
+        let mut registry = Registry::new();
+        let e0 = registry.create_entity();
+        let e1 = registry.create_entity();
+        registry
+            .add_component(e0, CounterComponent { count: 0 })
+            .unwrap();
+        registry.add_component(e0, 1_i32).unwrap();
+        registry
+            .add_component(e1, CounterComponent { count: 0 })
+            .unwrap();
+        registry.add_system(Rc::new(RefCell::new(MisconfiguredSystem::new())));
+        match registry.run_system::<MisconfiguredSystem>(()) {
+            Err(EcsError::SystemPanicked { system, message }) => {
+                assert!(system.ends_with("MisconfiguredSystem"));
+                assert_eq!(message, "missing i32 component");
+            }
+            other => panic!("expected SystemPanicked, got {:?}", other),
+        }
+        // The registry is still usable after a system panicked.
+        registry.add_component(e1, 2_i32).unwrap();
+        registry.run_system::<MisconfiguredSystem>(()).unwrap();
+    }
+
+    #[test]
+    fn test_disabled_system_is_skipped() {
+        let mut registry = Registry::new();
+        let e = registry.create_entity();
+        let system = CounterIncrementSystem::new();
+        let expected_entity_count = system.expected_entity_count.clone();
+        registry
+            .add_component(e, CounterComponent { count: 0 })
+            .unwrap();
+        registry.add_system(Rc::new(RefCell::new(system)));
+        assert!(registry.is_system_enabled::<CounterIncrementSystem>());
+
+        registry
+            .set_system_enabled::<CounterIncrementSystem>(false)
+            .unwrap();
+        assert!(!registry.is_system_enabled::<CounterIncrementSystem>());
+        registry.run_system::<CounterIncrementSystem>(1).unwrap();
+        assert_eq!(
+            registry
+                .get_component::<CounterComponent>(e)
+                .unwrap()
+                .unwrap()
+                .count,
+            0
+        );
+        // Entities added while disabled are still tracked.
+        let e1 = registry.create_entity();
+        registry
+            .add_component(e1, CounterComponent { count: 0 })
+            .unwrap();
+
+        registry
+            .set_system_enabled::<CounterIncrementSystem>(true)
+            .unwrap();
+        *expected_entity_count.lock().unwrap() = 2;
+        registry.run_system::<CounterIncrementSystem>(1).unwrap();
+        assert_eq!(
+            registry
+                .get_component::<CounterComponent>(e)
+                .unwrap()
+                .unwrap()
+                .count,
+            1
+        );
+
+        registry.remove_system::<CounterIncrementSystem>();
+        assert!(registry
+            .set_system_enabled::<CounterIncrementSystem>(true)
+            .is_err());
+    }
+
+    #[test]
+    fn test_replaced_system_is_enabled() {
+        let mut registry = Registry::new();
+        let e = registry.create_entity();
+        registry
+            .add_component(e, CounterComponent { count: 0 })
+            .unwrap();
+        registry.add_system(Rc::new(RefCell::new(CounterIncrementSystem::new())));
+        registry
+            .set_system_enabled::<CounterIncrementSystem>(false)
+            .unwrap();
+
+        let system = CounterIncrementSystem::new();
+        *system.expected_entity_count.lock().unwrap() = 1;
+        registry.add_system(Rc::new(RefCell::new(system)));
+        assert!(registry.is_system_enabled::<CounterIncrementSystem>());
+        registry.run_system::<CounterIncrementSystem>(1).unwrap();
+        assert_eq!(
+            registry
+                .get_component::<CounterComponent>(e)
+                .unwrap()
+                .unwrap()
+                .count,
+            1
+        );
+    }
+
+    #[test]
+    fn test_remove_all() {
+        let mut registry = Registry::new();
+        let system = CounterIncrementSystem::new();
+        let expected_entity_count = system.expected_entity_count.clone();
+        registry.add_system(Rc::new(RefCell::new(system)));
+        let e0 = registry.create_entity();
+        let e1 = registry.create_entity();
+        let e2 = registry.create_entity();
+        registry
+            .add_component(e0, CounterComponent { count: 0 })
+            .unwrap();
+        registry
+            .add_component(e1, CounterComponent { count: 0 })
+            .unwrap();
+        registry.add_component(e1, 1_i32).unwrap();
+        registry.add_component(e2, 2_i32).unwrap();
+
+        registry.remove_all::<CounterComponent>();
+        assert!(registry.get_component::<CounterComponent>(e0).is_err());
+        assert!(registry.get_component::<CounterComponent>(e1).is_err());
+        assert_eq!(registry.get_component::<i32>(e1).unwrap(), Some(&1));
+        assert_eq!(registry.get_component::<i32>(e2).unwrap(), Some(&2));
+        assert!(!registry
+            .ec_manager
+            .has_components(e1)
+            .unwrap()
+            .contains(&TypeId::of::<CounterComponent>()));
+        *expected_entity_count.lock().unwrap() = 0;
+        registry.run_system::<CounterIncrementSystem>(1).unwrap();
+
+        // Components can be added again after the pool was cleared.
+        registry
+            .add_component(e2, CounterComponent { count: 0 })
+            .unwrap();
+        assert_eq!(
+            registry
+                .get_component::<CounterComponent>(e2)
+                .unwrap()
+                .unwrap()
+                .count,
+            0
+        );
+    }
+
+    #[test]
+    fn test_system_entities() {
+        let mut registry = Registry::new();
+        assert!(registry
+            .system_entities::<CounterIncrementSystem>()
+            .is_none());
+        registry.add_system(Rc::new(RefCell::new(CounterIncrementSystem::new())));
+        let e0 = registry.create_entity();
+        let e1 = registry.create_entity();
+        registry
+            .add_component(e0, CounterComponent { count: 0 })
+            .unwrap();
+        registry
+            .add_component(e1, CounterComponent { count: 0 })
+            .unwrap();
+        let mut entities: Vec<Entity> = registry
+            .system_entities::<CounterIncrementSystem>()
+            .unwrap()
+            .collect();
+        entities.sort();
+        assert_eq!(entities, vec![e0, e1]);
+
+        registry.remove_component::<CounterComponent>(e0).unwrap();
+        let entities: Vec<Entity> = registry
+            .system_entities::<CounterIncrementSystem>()
+            .unwrap()
+            .collect();
+        assert_eq!(entities, vec![e1]);
+    }
+
+    #[test]
+    fn test_entities_ordered() {
+        let mut registry = Registry::new();
+        let e0 = registry.create_entity();
+        let e1 = registry.create_entity();
+        let e2 = registry.create_entity();
+        let e3 = registry.create_entity();
+        registry.remove_entity(e1).unwrap();
+        // Reuses the id of e1, but comes last in creation order.
+        let e4 = registry.create_entity();
+        let ordered: Vec<Entity> = registry.entities_ordered().copied().collect();
+        assert_eq!(ordered, vec![e0, e2, e3, e4]);
+        assert_eq!(registry.entities().count(), ordered.len());
+    }
+
+    #[test]
+    fn test_entities_ordered_after_compacting() {
+        let mut registry = Registry::new();
+        let entities: Vec<Entity> = (0..100).map(|_| registry.create_entity()).collect();
+        // Enough removals to compact the creation order more than once.
+        for entity in entities.iter().filter(|entity| entity.id % 4 != 0) {
+            registry.remove_entity(*entity).unwrap();
+        }
+        let remaining: Vec<Entity> = entities
+            .iter()
+            .copied()
+            .filter(|entity| entity.id % 4 == 0)
+            .collect();
+        assert_eq!(registry.entity_count(), remaining.len());
+        assert_eq!(
+            registry.entities_ordered().copied().collect::<Vec<_>>(),
+            remaining
+        );
+        // Entities can still be removed after their place in the creation order moved.
+        registry.remove_entity(remaining[10]).unwrap();
+        let newest = registry.create_entity();
+        let mut expected = remaining.clone();
+        expected.remove(10);
+        expected.push(newest);
+        assert_eq!(
+            registry.entities_ordered().copied().collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_entity_builder() {
+        let mut registry = Registry::new();
+        let system = CounterIncrementSystem::new();
+        *system.expected_entity_count.lock().unwrap() = 1;
+        registry.add_system(Rc::new(RefCell::new(system)));
+        let e = registry
+            .entity_builder()
+            .with(CounterComponent { count: 3 })
+            .with(7_i32)
+            .build();
+        assert_eq!(
+            registry
+                .system_entities::<CounterIncrementSystem>()
+                .unwrap()
+                .collect::<Vec<_>>(),
+            vec![e]
+        );
+        assert_eq!(registry.get_component::<i32>(e).unwrap(), Some(&7));
+        registry.run_system::<CounterIncrementSystem>(1).unwrap();
+        assert_eq!(
+            registry
+                .get_component::<CounterComponent>(e)
+                .unwrap()
+                .unwrap()
+                .count,
+            4
+        );
+    }
+
+    #[test]
+    fn test_created_and_removed_entities() {
+        let mut registry = Registry::new();
+        let e = registry.create_entity();
+        registry
+            .add_component(e, CounterComponent { count: 0 })
+            .unwrap();
+        let system = CounterIncrementSystem::new();
+        *system.expected_entity_count.lock().unwrap() = 1;
+        registry.add_system(Rc::new(RefCell::new(system)));
+        assert_eq!(registry.created_entities().count(), 0);
+        registry.run_system::<CounterIncrementSystem>(1).unwrap();
+        let created: Vec<Entity> = registry.created_entities().copied().collect();
+        assert_eq!(created.len(), 1);
+        assert!(registry.is_alive(created[0]));
+        assert_eq!(registry.removed_entities().count(), 0);
+
+        registry.add_system(Rc::new(RefCell::new(CommandSystem::new())));
+        registry.run_system::<CommandSystem>(&[e]).unwrap();
+        assert_eq!(registry.created_entities().count(), 1);
+        assert_eq!(
+            registry.removed_entities().copied().collect::<Vec<_>>(),
+            vec![e]
+        );
+        // A disabled system doesn't run, so the last run created and removed nothing.
+        registry.set_system_enabled::<CommandSystem>(false).unwrap();
+        registry.run_system::<CommandSystem>(&[created[0]]).unwrap();
+        assert_eq!(registry.created_entities().count(), 0);
+        assert_eq!(registry.removed_entities().count(), 0);
+        assert!(registry.is_alive(created[0]));
+    }
+
+    #[test]
+    fn test_resources() {
+        let mut registry = Registry::new();
+        assert_eq!(registry.get_resource::<u32>(), None);
+        registry.insert_resource(5_u32);
+        registry.insert_resource("five");
+        *registry.get_resource_mut::<u32>().unwrap() += 1;
+        assert_eq!(registry.get_resource::<u32>(), Some(&6));
+        assert_eq!(registry.get_resource::<&str>(), Some(&"five"));
+        registry.insert_resource(7_u32);
+        assert_eq!(registry.get_resource::<u32>(), Some(&7));
+    }
+
+    #[test]
+    fn test_resources_from_wrapper() {
+        struct Gravity(f32);
+
+        let mut registry = Registry::new();
+        registry.insert_resource(Gravity(9.8));
+        registry.run_once(|ec_manager| {
+            assert_eq!(ec_manager.get_resource::<Gravity>().unwrap().0, 9.8);
+            ec_manager.get_resource_mut::<Gravity>().unwrap().0 = 1.6;
+            assert!(ec_manager.get_resource::<f32>().is_none());
+        });
+        assert_eq!(registry.get_resource::<Gravity>().unwrap().0, 1.6);
+    }
+
+    #[test]
+    fn test_system_timings() {
+        let mut registry = Registry::new();
+        let system = CounterIncrementSystem::new();
+        let expected_entity_count = system.expected_entity_count.clone();
+        registry.add_system(Rc::new(RefCell::new(system)));
+        registry.run_system::<CounterIncrementSystem>(1).unwrap();
+        assert!(registry.get_resource::<SystemTimings>().is_none());
+        registry.insert_resource(SystemTimings::new());
+        *expected_entity_count.lock().unwrap() = 1;
+        registry.run_system::<CounterIncrementSystem>(1).unwrap();
+        let system_timings = registry.get_resource::<SystemTimings>().unwrap();
+        let timing = system_timings
+            .get(std::any::type_name::<CounterIncrementSystem>())
+            .unwrap();
+        assert!(timing.last >= 0.0);
+        assert_eq!(timing.last, timing.mean);
+        assert_eq!(system_timings.iter().count(), 1);
+    }
+
+    #[test]
+    fn test_get_or_insert_component() {
+        let mut registry = Registry::new();
+        let system = CounterIncrementSystem::new();
+        *system.expected_entity_count.lock().unwrap() = 1;
+        registry.add_system(Rc::new(RefCell::new(system)));
+        let e = registry.create_entity();
+        registry
+            .get_or_insert_component(e, CounterComponent { count: 3 })
+            .unwrap()
+            .count += 1;
+        registry
+            .get_or_insert_component(e, CounterComponent { count: 10 })
+            .unwrap()
+            .count += 1;
+        assert_eq!(
+            registry
+                .get_component::<CounterComponent>(e)
+                .unwrap()
+                .unwrap()
+                .count,
+            5
+        );
+        assert_eq!(
+            registry
+                .system_entities::<CounterIncrementSystem>()
+                .unwrap()
+                .collect::<Vec<_>>(),
+            vec![e]
+        );
+        registry.remove_entity(e).unwrap();
+        assert!(matches!(
+            registry.get_or_insert_component(e, CounterComponent { count: 0 }),
+            Err(EcsError::DeadEntity)
+        ));
+    }
+
+    #[test]
+    fn test_run_once() {
+        let mut registry = Registry::new();
+        let system = CounterIncrementSystem::new();
+        *system.expected_entity_count.lock().unwrap() = 2;
+        registry.add_system(Rc::new(RefCell::new(system)));
+        registry.insert_resource(3_u32);
+        registry.run_once(|ec_manager| {
+            let count = *ec_manager.get_resource::<u32>().unwrap();
+            for _ in 0..2 {
+                let e = ec_manager.create_entity();
+                ec_manager
+                    .add_component(e, CounterComponent { count })
+                    .unwrap();
+            }
+        });
+        assert_eq!(registry.created_entities().count(), 2);
+        assert_eq!(
+            registry
+                .system_entities::<CounterIncrementSystem>()
+                .unwrap()
+                .count(),
+            2
+        );
+        registry.run_system::<CounterIncrementSystem>(1).unwrap();
+    }
+
+    #[test]
+    fn test_max_entities() {
+        let mut registry = Registry::new();
+        registry.set_max_entities(Some(2));
+        let e0 = registry.try_create_entity().unwrap();
+        registry.try_create_entity().unwrap();
+        assert!(matches!(
+            registry.try_create_entity(),
+            Err(EcsError::TooManyEntities)
+        ));
+        assert_eq!(registry.entity_count(), 2);
+        registry.remove_entity(e0).unwrap();
+        registry.try_create_entity().unwrap();
+        // create_entity only warns.
+        registry.create_entity();
+        assert_eq!(registry.entity_count(), 3);
+        registry.set_max_entities(None);
+        registry.try_create_entity().unwrap();
+    }
+
+    struct SpawnEvent;
+
+    struct SpawnHandler;
+
+    impl Handler<SpawnEvent> for SpawnHandler {
+        fn handle(&mut self, ec_manager: &mut EntityComponentWrapper, _event: &SpawnEvent) {
+            let e = ec_manager.create_entity();
+            ec_manager
+                .add_component(e, CounterComponent { count: 0 })
+                .unwrap();
+        }
+    }
+
+    impl_handler_base!(SpawnHandler: SpawnEvent);
+
+    struct PingEvent;
+
+    struct PongEvent;
+
+    #[derive(Default)]
+    struct PingPongHandler {
+        pings: usize,
+        pongs: usize,
+    }
+
+    impl Handler<PingEvent> for PingPongHandler {
+        fn handle(&mut self, ec_manager: &mut EntityComponentWrapper, _event: &PingEvent) {
+            self.pings += 1;
+            ec_manager.enqueue_event(PongEvent);
+        }
+    }
+
+    impl Handler<PongEvent> for PingPongHandler {
+        fn handle(&mut self, _ec_manager: &mut EntityComponentWrapper, _event: &PongEvent) {
+            self.pongs += 1;
+        }
+    }
+
+    impl_handler_base!(PingPongHandler: PingEvent, PongEvent);
+
+    #[test]
+    fn test_drain_events() {
+        let mut registry = Registry::new();
+        let handler = Rc::new(RefCell::new(PingPongHandler::default()));
+        registry.add_handler::<PingEvent, _>(Rc::clone(&handler));
+        registry.add_handler::<PongEvent, _>(Rc::clone(&handler));
+        registry.enqueue_event(PingEvent);
+        assert_eq!(handler.borrow().pings, 0);
+        registry.drain_events();
+        assert_eq!(handler.borrow().pings, 1);
+        // The handler queued a pong, which waits for the next drain.
+        assert_eq!(handler.borrow().pongs, 0);
+        registry.drain_events();
+        assert_eq!(handler.borrow().pongs, 1);
+        registry.drain_events();
+        assert_eq!((handler.borrow().pings, handler.borrow().pongs), (1, 1));
+    }
+
+    #[test]
+    fn test_remove_handler() {
+        let mut registry = Registry::new();
+        let handler = Rc::new(RefCell::new(SpawnHandler));
+        registry.add_handler::<SpawnEvent, _>(Rc::clone(&handler));
+        registry.dispatch_event(SpawnEvent);
+        assert_eq!(registry.entity_count(), 1);
+        registry.remove_handler::<SpawnEvent, _>(&handler);
+        registry.dispatch_event(SpawnEvent);
+        assert_eq!(registry.entity_count(), 1);
+    }
+
+    #[test]
+    fn test_remove_system_removes_its_handlers() {
+        let mut registry = Registry::new();
+        let collision_system = Rc::new(RefCell::new(CollisionSystem::new()));
+        registry.add_handler::<CollisionEvent, _>(Rc::clone(&collision_system));
+        registry.add_system(collision_system);
+        let entity_a = registry.create_entity();
+        let entity_b = registry.create_entity();
+        registry.remove_system::<CollisionSystem>();
+        registry.dispatch_event(CollisionEvent {
+            entity_a,
+            entity_b,
+            overlap: glam::Vec2::ZERO,
+            contact_point: glam::Vec2::ZERO,
+        });
+        assert!(registry.is_alive(entity_a));
+        assert!(registry.is_alive(entity_b));
+    }
+
+    #[test]
+    fn test_dispatch_event_updates_systems() {
+        let mut registry = Registry::new();
+        registry.add_system(Rc::new(RefCell::new(CounterIncrementSystem::new())));
+        registry.add_handler::<SpawnEvent, _>(Rc::new(RefCell::new(SpawnHandler)));
+        registry.dispatch_event(SpawnEvent);
+        assert_eq!(registry.created_entities().count(), 1);
+        assert_eq!(
+            registry
+                .system_entities::<CounterIncrementSystem>()
+                .unwrap()
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_query_mut() {
+        #[derive(Clone)]
+        struct TotalComponent {
+            total: u32,
+        }
+
+        let mut registry = Registry::new();
+        let e0 = registry
+            .entity_builder()
+            .with(CounterComponent { count: 1 })
+            .with(TotalComponent { total: 10 })
+            .build();
+        let e1 = registry
+            .entity_builder()
+            .with(CounterComponent { count: 2 })
+            .build();
+        let e2 = registry
+            .entity_builder()
+            .with(CounterComponent { count: 3 })
+            .with(TotalComponent { total: 20 })
+            .build();
+        registry.remove_entity(e2).unwrap();
+        registry.run_once(|ec_manager| {
+            let mut entities = Vec::new();
+            for (entity, counter, total) in
+                ec_manager.query_mut::<CounterComponent, TotalComponent>()
+            {
+                total.total += counter.count;
+                counter.count = 0;
+                entities.push(entity);
+            }
+            assert_eq!(entities, vec![e0]);
+        });
+        let counter: &CounterComponent = registry.get_component(e0).unwrap().unwrap();
+        assert_eq!(counter.count, 0);
+        let total: &TotalComponent = registry.get_component(e0).unwrap().unwrap();
+        assert_eq!(total.total, 11);
+        let counter: &CounterComponent = registry.get_component(e1).unwrap().unwrap();
+        assert_eq!(counter.count, 2);
+    }
+
+    #[test]
+    fn test_query_mut_spare_pool_slots() {
+        #[derive(Clone)]
+        struct TotalComponent;
+
+        let mut registry = Registry::new();
+        let entities: Vec<Entity> = (0..20).map(|_| registry.create_entity()).collect();
+        for entity in [entities[0], entities[19]] {
+            registry
+                .add_component(entity, CounterComponent { count: 1 })
+                .unwrap();
+            registry.add_component(entity, TotalComponent).unwrap();
+        }
+        registry.run_once(|ec_manager| {
+            let mut found: Vec<Entity> = ec_manager
+                .query_mut::<CounterComponent, TotalComponent>()
+                .map(|(entity, _, _)| entity)
+                .collect();
+            found.sort();
+            assert_eq!(found, vec![entities[0], entities[19]]);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "two different component types")]
+    fn test_query_mut_same_type_panics() {
+        let mut registry = Registry::new();
+        registry.run_once(|ec_manager| {
+            ec_manager
+                .query_mut::<CounterComponent, CounterComponent>()
+                .count();
+        });
+    }
+
+    struct RecordingSystem<const N: usize> {
+        required_components: HashSet<TypeId>,
+        log: Rc<RefCell<Vec<usize>>>,
+    }
+
+    impl<const N: usize> RecordingSystem<N> {
+        fn new(log: &Rc<RefCell<Vec<usize>>>) -> Self {
+            let mut required_components = HashSet::new();
+            required_components.insert(TypeId::of::<CounterComponent>());
+            Self {
+                required_components,
+                log: Rc::clone(log),
+            }
+        }
+    }
+
+    impl<const N: usize> SystemBase for RecordingSystem<N> {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn required_components(&self) -> &HashSet<TypeId> {
+            &self.required_components
+        }
+
+        fn add_entity(&mut self, _entity: Entity) {
+            self.log.borrow_mut().push(N);
+        }
+
+        fn remove_entity(&mut self, _entity: Entity) {
+            self.log.borrow_mut().push(N);
+        }
+
+        fn entities(&self) -> Vec<Entity> {
+            Vec::new()
+        }
+    }
+
+    impl<const N: usize> System for RecordingSystem<N> {
+        type Input<'i> = ();
+
+        fn run(&self, _ec_manager: &mut EntityComponentWrapper, _input: Self::Input<'_>) {}
+    }
+
+    #[test]
+    fn test_systems_updated_in_registration_order() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut registry = Registry::new();
+        registry.add_system(Rc::new(RefCell::new(RecordingSystem::<2>::new(&log))));
+        registry.add_system(Rc::new(RefCell::new(RecordingSystem::<0>::new(&log))));
+        registry.add_system(Rc::new(RefCell::new(RecordingSystem::<1>::new(&log))));
+        let e = registry.create_entity();
+        registry
+            .add_component(e, CounterComponent { count: 0 })
+            .unwrap();
+        assert_eq!(*log.borrow(), vec![2, 0, 1]);
+        log.borrow_mut().clear();
+        registry.run_once(|ec_manager| {
+            ec_manager.remove_component::<CounterComponent>(e).unwrap();
+        });
+        assert_eq!(*log.borrow(), vec![2, 0, 1]);
+
+        // Replacing a system keeps its place.
+        registry.add_system(Rc::new(RefCell::new(RecordingSystem::<0>::new(&log))));
+        log.borrow_mut().clear();
+        registry
+            .add_component(e, CounterComponent { count: 0 })
+            .unwrap();
+        assert_eq!(*log.borrow(), vec![2, 0, 1]);
+    }
+
+    #[test]
+    fn test_keyed_components() {
+        let mut registry = Registry::new();
+        let e = registry.create_entity();
+        assert_eq!(
+            registry
+                .get_components::<CounterComponent>(e)
+                .unwrap()
+                .count(),
+            0
+        );
+        registry
+            .add_component_keyed(e, 2, CounterComponent { count: 2 })
+            .unwrap();
+        registry
+            .add_component_keyed(e, 1, CounterComponent { count: 1 })
+            .unwrap();
+        let counts: Vec<u32> = registry
+            .get_components::<CounterComponent>(e)
+            .unwrap()
+            .map(|counter| counter.count)
+            .collect();
+        assert_eq!(counts, vec![1, 2]);
+        let counter: &CounterComponent = registry.get_component(e).unwrap().unwrap();
+        assert_eq!(counter.count, 1);
+
+        // The single component API uses key 0.
+        registry
+            .add_component(e, CounterComponent { count: 0 })
+            .unwrap();
+        registry
+            .add_component(e, CounterComponent { count: 10 })
+            .unwrap();
+        let counts: Vec<u32> = registry
+            .get_components::<CounterComponent>(e)
+            .unwrap()
+            .map(|counter| counter.count)
+            .collect();
+        assert_eq!(counts, vec![10, 1, 2]);
+
+        registry.remove_component::<CounterComponent>(e).unwrap();
+        assert_eq!(
+            registry
+                .get_components::<CounterComponent>(e)
+                .unwrap()
+                .count(),
+            0
+        );
+        registry.remove_entity(e).unwrap();
+        assert!(registry.get_components::<CounterComponent>(e).is_err());
+    }
+
+    #[test]
+    fn test_names_and_tags() {
+        let mut registry = Registry::new();
+        let player = registry
+            .entity_builder()
+            .with(NameComponent("player".to_string()))
+            .build();
+        let enemy_0 = registry.create_entity();
+        let enemy_1 = registry.create_entity();
+        registry.tag(enemy_0, "enemy").unwrap();
+        registry.tag(enemy_1, "enemy").unwrap();
+        registry.tag(player, "friendly").unwrap();
+        assert_eq!(registry.find_by_name("player"), Some(player));
+        assert_eq!(registry.find_by_name("enemy"), None);
+        let mut enemies: Vec<Entity> = registry.entities_with_tag("enemy").collect();
+        enemies.sort();
+        assert_eq!(enemies, vec![enemy_0, enemy_1]);
+
+        // Renaming forgets the old name.
+        registry
+            .add_component(player, NameComponent("hero".to_string()))
+            .unwrap();
+        assert_eq!(registry.find_by_name("player"), None);
+        assert_eq!(registry.find_by_name("hero"), Some(player));
+
+        registry.remove_entity(player).unwrap();
+        registry.remove_entity(enemy_0).unwrap();
+        assert_eq!(registry.find_by_name("hero"), None);
+        assert_eq!(registry.entities_with_tag("friendly").count(), 0);
+        assert_eq!(
+            registry.entities_with_tag("enemy").collect::<Vec<_>>(),
+            vec![enemy_1]
+        );
+        assert!(registry.tag(player, "friendly").is_err());
+    }
+
+    #[test]
+    fn test_name_changed_in_place() {
+        let mut registry = Registry::new();
+        let player = registry
+            .entity_builder()
+            .with(NameComponent("player".to_string()))
+            .build();
+        let name: &mut NameComponent = registry.get_component_mut(player).unwrap().unwrap();
+        name.0 = "hero".to_string();
+        assert_eq!(registry.find_by_name("player"), None);
+        registry.remove_entity(player).unwrap();
+        assert_eq!(registry.find_by_name("player"), None);
+        assert_eq!(registry.find_by_name("hero"), None);
+        // The name is free for a new entity, even one that reuses the same id.
+        let other = registry
+            .entity_builder()
+            .with(NameComponent("player".to_string()))
+            .build();
+        assert_eq!(registry.find_by_name("player"), Some(other));
+    }
+
+    fn serializable_registry() -> Registry {
+        let mut registry = Registry::new();
+        registry.register_serializable::<RigidBodyComponent>("rigid_body");
+        registry.register_serializable::<SpriteComponent>("sprite");
+        registry.register_serializable::<NameComponent>("name");
+        registry
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut registry = serializable_registry();
+        let sprite_index = SpriteIndex::for_tests(3);
+        let removed = registry.create_entity();
+        let player = registry
+            .entity_builder()
+            .with(NameComponent("player".to_string()))
+            .with(RigidBodyComponent {
+                velocity: glam::vec2(3.0, 4.0),
+                ..RigidBodyComponent::at(glam::vec2(1.0, 2.0))
+            })
+            .with(SpriteComponent::new(
+                sprite_index,
+                Layer::Air,
+                glam::vec2(16.0, 16.0),
+            ))
+            .build();
+        let tree = registry.create_entity();
+        registry
+            .add_component_keyed(
+                tree,
+                1,
+                SpriteComponent::new(sprite_index, Layer::Ground, glam::vec2(8.0, 8.0)),
+            )
+            .unwrap();
+        registry.tag(tree, "scenery").unwrap();
+        // Not registered, so not saved.
+        registry
+            .add_component(
+                tree,
+                ParentComponent {
+                    parent: player,
+                    local_offset: glam::Vec2::ZERO,
+                },
+            )
+            .unwrap();
+        registry.remove_entity(removed).unwrap();
+        let mut saved = Vec::new();
+        registry.save(&mut saved).unwrap();
+
+        let mut loaded = serializable_registry();
+        loaded.create_entity();
+        loaded.load(saved.as_slice()).unwrap();
+        let mut saved_again = Vec::new();
+        loaded.save(&mut saved_again).unwrap();
+        assert_eq!(saved, saved_again);
+
+        assert_eq!(
+            loaded.entities_ordered().copied().collect::<Vec<_>>(),
+            vec![player, tree]
+        );
+        assert!(loaded.is_dead(removed));
+        assert_eq!(loaded.find_by_name("player"), Some(player));
+        assert_eq!(
+            loaded.entities_with_tag("scenery").collect::<Vec<_>>(),
+            vec![tree]
+        );
+        let rigid_body = loaded
+            .get_component::<RigidBodyComponent>(player)
+            .unwrap()
+            .unwrap();
+        assert_eq!(rigid_body.position, glam::vec2(1.0, 2.0));
+        assert_eq!(rigid_body.velocity, glam::vec2(3.0, 4.0));
+        let sprite = loaded
+            .get_component::<SpriteComponent>(tree)
+            .unwrap()
+            .unwrap();
+        assert_eq!(sprite.sprite_index, sprite_index);
+        assert_eq!(sprite.sprite_layer, Layer::Ground);
+        assert!(!loaded
+            .entities_and_components()
+            .any(|(_, components)| components.contains(&TypeId::of::<ParentComponent>())));
+        // The freed id is reused just as it would have been before saving.
+        assert!(!loaded.is_recycled(removed));
+        assert_eq!(loaded.create_entity(), registry.create_entity());
+        assert!(loaded.is_recycled(removed));
+    }
+
+    #[test]
+    fn test_load_unregistered_component() {
+        let mut registry = serializable_registry();
+        let player = registry.create_entity();
+        registry
+            .add_component(player, NameComponent("player".to_string()))
+            .unwrap();
+        let mut saved = Vec::new();
+        registry.save(&mut saved).unwrap();
+
+        let mut loaded = Registry::new();
+        let existing = loaded.create_entity();
+        assert!(matches!(
+            loaded.load(saved.as_slice()),
+            Err(EcsError::Serialization(_))
+        ));
+        assert!(loaded.is_alive(existing));
+    }
+
+    #[test]
+    fn test_load_bad_component_changes_nothing() {
+        let mut registry = serializable_registry();
+        registry
+            .entity_builder()
+            .with(NameComponent("player".to_string()))
+            .build();
+        let mut saved = Vec::new();
+        registry.save(&mut saved).unwrap();
+        let saved = String::from_utf8(saved).unwrap();
+        assert!(saved.contains(r#""name":[[0,"player"]]"#));
+        let saved = saved.replace(r#""name":[[0,"player"]]"#, r#""name":[[0,5]]"#);
+
+        let mut loaded = serializable_registry();
+        let mut no_op_system = NoOpSystem::new();
+        no_op_system.require_component::<NameComponent>();
+        loaded.add_system(Rc::new(RefCell::new(no_op_system)));
+        let existing = loaded
+            .entity_builder()
+            .with(NameComponent("tree".to_string()))
+            .build();
+        assert!(matches!(
+            loaded.load(saved.as_bytes()),
+            Err(EcsError::Serialization(_))
+        ));
+        assert_eq!(
+            loaded.entities_ordered().copied().collect::<Vec<_>>(),
+            vec![existing]
+        );
+        assert_eq!(loaded.find_by_name("tree"), Some(existing));
+        assert_eq!(
+            loaded
+                .system_entities::<NoOpSystem>()
+                .unwrap()
+                .collect::<Vec<_>>(),
+            vec![existing]
+        );
+    }
+
+    #[test]
+    fn test_load_inconsistent_entities_changes_nothing() {
+        let mut registry = serializable_registry();
+        let player = registry
+            .entity_builder()
+            .with(NameComponent("player".to_string()))
+            .build();
+        let mut saved = Vec::new();
+        registry.save(&mut saved).unwrap();
+        let saved: serde_json::Value = serde_json::from_slice(&saved).unwrap();
+        let edited = |edit: &dyn Fn(&mut serde_json::Value)| {
+            let mut edited = saved.clone();
+            edit(&mut edited);
+            serde_json::to_vec(&edited).unwrap()
+        };
+        let bad_saves = [
+            edited(&|saved| saved["entity_manager"]["next_entity_id"] = 1000.into()),
+            edited(&|saved| saved["entity_manager"]["free_entity_ids"] = serde_json::json!([7])),
+            edited(&|saved| saved["entity_manager"]["creation_order"][0]["generation"] = 3.into()),
+            edited(&|saved| saved["entities"][0]["entity"]["id"] = 1.into()),
+            edited(&|saved| saved["entities"] = serde_json::json!([])),
+        ];
+
+        let mut loaded = serializable_registry();
+        let existing = loaded
+            .entity_builder()
+            .with(NameComponent("tree".to_string()))
+            .build();
+        for bad_save in bad_saves {
+            assert!(matches!(
+                loaded.load(bad_save.as_slice()),
+                Err(EcsError::Serialization(_))
+            ));
+            assert_eq!(
+                loaded.entities_ordered().copied().collect::<Vec<_>>(),
+                vec![existing]
+            );
+            assert_eq!(loaded.find_by_name("tree"), Some(existing));
+        }
+        loaded.load(edited(&|_| {}).as_slice()).unwrap();
+        assert_eq!(loaded.find_by_name("player"), Some(player));
+    }
+
+    #[test]
+    #[should_panic(expected = "already saved as name")]
+    fn test_register_serializable_duplicate_name() {
+        let mut registry = serializable_registry();
+        registry.register_serializable::<Entity>("name");
+    }
+
+    /// Despawns the input entities and spawns a counter, through the command buffer.
+    struct CommandSystem {
+        required_components: HashSet<TypeId>,
+        entities: HashSet<Entity>,
+    }
+
+    impl CommandSystem {
+        fn new() -> Self {
+            let mut required_components = HashSet::new();
+            required_components.insert(TypeId::of::<CounterComponent>());
+            Self {
+                required_components,
+                entities: HashSet::new(),
+            }
+        }
+    }
+
+    impl SystemBase for CommandSystem {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn required_components(&self) -> &HashSet<TypeId> {
+            &self.required_components
+        }
+
+        fn add_entity(&mut self, entity: Entity) {
+            self.entities.insert(entity);
+        }
+
+        fn remove_entity(&mut self, entity: Entity) {
+            self.entities.remove(&entity);
+        }
+
+        fn entities(&self) -> Vec<Entity> {
+            self.entities.iter().copied().collect()
+        }
+    }
+
+    impl System for CommandSystem {
+        type Input<'i> = &'i [Entity];
+
+        fn run(&self, ec_manager: &mut EntityComponentWrapper, to_despawn: Self::Input<'_>) {
+            let entity_count = ec_manager.entity_count();
+            for entity in to_despawn {
+                ec_manager.commands().despawn(*entity);
+            }
+            ec_manager
+                .commands()
+                .spawn()
+                .with(CounterComponent { count: 7 });
+            // Nothing changes until the system returns.
+            assert_eq!(ec_manager.entity_count(), entity_count);
+        }
+    }
+
+    #[test]
+    fn test_command_buffer() {
+        let mut registry = Registry::new();
+        registry.add_system(Rc::new(RefCell::new(CommandSystem::new())));
+        let e0 = registry
+            .entity_builder()
+            .with(CounterComponent { count: 0 })
+            .build();
+        let e1 = registry
+            .entity_builder()
+            .with(CounterComponent { count: 0 })
+            .build();
+        registry.run_system::<CommandSystem>(&[e0, e1]).unwrap();
+        assert!(registry.is_dead(e0));
+        assert!(registry.is_dead(e1));
+        assert_eq!(registry.entity_count(), 1);
+        let spawned = *registry.entities().next().unwrap();
+        assert_eq!(
+            registry
+                .get_component::<CounterComponent>(spawned)
+                .unwrap()
+                .unwrap()
+                .count,
+            7
+        );
+        assert_eq!(
+            registry
+                .system_entities::<CommandSystem>()
+                .unwrap()
+                .collect::<Vec<_>>(),
+            vec![spawned]
+        );
+        assert_eq!(registry.created_entities().count(), 1);
+        assert_eq!(registry.removed_entities().count(), 2);
+
+        // Commands were applied once, so despawning the same entities again is ignored.
+        registry
+            .run_system::<CommandSystem>(&[e0, e1, spawned, spawned])
+            .unwrap();
+        assert_eq!(registry.entity_count(), 1);
+        assert!(registry.is_dead(spawned));
+        assert_eq!(registry.removed_entities().count(), 1);
+    }
+
+    #[test]
+    fn test_same_entity_after_id_reuse() {
+        let mut registry = Registry::new();
+        let e0 = registry.create_entity();
+        let e1 = registry.create_entity();
+        assert!(registry.same_entity(e0));
+        assert!(!registry.is_recycled(e0));
+
+        registry.remove_entity(e0).unwrap();
+        assert!(!registry.same_entity(e0));
+        assert!(registry.is_dead(e0));
+        // The id is free, but not yet reused.
+        assert!(!registry.is_recycled(e0));
+
+        let e2 = registry.create_entity();
+        assert_eq!(e2.id, e0.id);
+        assert_ne!(e2, e0);
+        assert!(!registry.same_entity(e0));
+        assert!(registry.is_recycled(e0));
+        assert!(registry.same_entity(e2));
+        assert!(!registry.is_recycled(e2));
+        assert!(registry.same_entity(e1));
+
+        // Reused twice; the oldest handle is still recycled, never alive again.
+        registry.remove_entity(e2).unwrap();
+        assert!(!registry.is_recycled(e0));
+        assert!(!registry.is_recycled(e2));
+        let e3 = registry.create_entity();
+        assert_eq!(e3.id, e0.id);
+        assert!(registry.is_recycled(e0));
+        assert!(registry.is_recycled(e2));
+        assert!(registry.same_entity(e3));
+        assert!(!registry.same_entity(e0));
+        assert!(!registry.same_entity(e2));
+    }
 }
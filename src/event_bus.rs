@@ -12,14 +12,50 @@ pub trait Handler<E>: HandlerBase {
     fn handle(&mut self, ec_manager: &mut EntityComponentWrapper, event: &E);
 }
 
+/// Implements `HandlerBase` for a type, given every event type it has a `Handler` for.
+///
+/// ```ignore
+/// impl_handler_base!(CollisionSystem: CollisionEvent, PhysicalKey);
+/// ```
+#[macro_export]
+macro_rules! impl_handler_base {
+    ($handler:ty: $($event:ty),+ $(,)?) => {
+        impl $crate::event_bus::HandlerBase for $handler {
+            fn handle_any(
+                &mut self,
+                ec_manager: &mut $crate::ecs::EntityComponentWrapper,
+                event: &dyn std::any::Any,
+            ) {
+                $(
+                    if let Some(event) = event.downcast_ref::<$event>() {
+                        <$handler as $crate::event_bus::Handler<$event>>::handle(
+                            self, ec_manager, event,
+                        );
+                        return;
+                    }
+                )+
+            }
+        }
+    };
+}
+
 pub struct EventBus {
     handlers: HashMap<TypeId, Vec<Rc<RefCell<dyn HandlerBase>>>>,
+    /// Events waiting for the next `Registry::drain_events`.
+    queue: Vec<(TypeId, Box<dyn Any>)>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl EventBus {
     pub fn new() -> Self {
         Self {
             handlers: HashMap::new(),
+            queue: Vec::new(),
         }
     }
 
@@ -35,6 +71,44 @@ impl EventBus {
         }
     }
 
+    /// Queue an event to be dispatched by the next `Registry::drain_events`, rather than immediately.
+    pub fn enqueue<E: 'static>(&mut self, event: E) {
+        self.queue.push((TypeId::of::<E>(), Box::new(event)));
+    }
+
+    /// Like `enqueue`, for an event whose type is only known at runtime.
+    pub(crate) fn enqueue_any(&mut self, type_id: TypeId, event: Box<dyn Any>) {
+        self.queue.push((type_id, event));
+    }
+
+    /// Take the queued events, leaving the queue empty.
+    pub(crate) fn take_queue(&mut self) -> Vec<(TypeId, Box<dyn Any>)> {
+        std::mem::take(&mut self.queue)
+    }
+
+    /// Stop sending events of type E to the handler.
+    pub fn remove_handler<E: 'static, H: Handler<E> + 'static>(
+        &mut self,
+        handler: &Rc<RefCell<H>>,
+    ) {
+        let type_id = TypeId::of::<E>();
+        if let Some(handlers) = self.handlers.get_mut(&type_id) {
+            handlers.retain(|h| !std::ptr::addr_eq(Rc::as_ptr(h), Rc::as_ptr(handler)));
+            if handlers.is_empty() {
+                self.handlers.remove(&type_id);
+            }
+        }
+    }
+
+    /// Stop sending events of any type to the handler at this address.
+    /// Used when the handler is also a system that's being removed, and its type is unknown.
+    pub(crate) fn remove_handler_at(&mut self, handler: *const ()) {
+        self.handlers.retain(|_, handlers| {
+            handlers.retain(|h| !std::ptr::addr_eq(Rc::as_ptr(h), handler));
+            !handlers.is_empty()
+        });
+    }
+
     pub fn dispatch(
         &mut self,
         ec_manager: &mut EntityComponentWrapper,
@@ -45,7 +119,54 @@ impl EventBus {
             for handler in handlers {
                 handler.borrow_mut().handle_any(ec_manager, event);
             }
-        } else {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Handler;
+    use crate::ecs::{EntityComponentWrapper, Registry};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct Jump(u32);
+
+    struct Shoot(&'static str);
+
+    struct Ignored;
+
+    #[derive(Default)]
+    struct Player {
+        jumps: Vec<u32>,
+        shots: Vec<&'static str>,
+    }
+
+    impl Handler<Jump> for Player {
+        fn handle(&mut self, _ec_manager: &mut EntityComponentWrapper, event: &Jump) {
+            self.jumps.push(event.0);
+        }
+    }
+
+    impl Handler<Shoot> for Player {
+        fn handle(&mut self, _ec_manager: &mut EntityComponentWrapper, event: &Shoot) {
+            self.shots.push(event.0);
+        }
+    }
+
+    impl_handler_base!(Player: Jump, Shoot);
+
+    #[test]
+    fn test_impl_handler_base_dispatches_each_event_type() {
+        let mut registry = Registry::new();
+        let player = Rc::new(RefCell::new(Player::default()));
+        registry.add_handler::<Jump, _>(Rc::clone(&player));
+        registry.add_handler::<Shoot, _>(Rc::clone(&player));
+        registry.dispatch_event(Jump(3));
+        registry.dispatch_event(Shoot("laser"));
+        registry.dispatch_event(Ignored);
+        registry.dispatch_event(Jump(5));
+        assert_eq!(player.borrow().jumps, vec![3, 5]);
+        assert_eq!(player.borrow().shots, vec!["laser"]);
+    }
+}
@@ -1,3 +1,45 @@
+/// Estimates one percentile of a stream of samples, without storing the samples.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PercentileEstimator {
+    /// The fraction of samples that should fall below the estimate, such as 0.99.
+    target: f32,
+    estimate: f32,
+}
+
+impl PercentileEstimator {
+    pub fn new(target: f32, initial_estimate: f32) -> Self {
+        assert!(
+            0.0 < target && target < 1.0,
+            "percentile target must be between 0 and 1"
+        );
+        Self {
+            target,
+            estimate: initial_estimate,
+        }
+    }
+
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+
+    pub fn estimate(&self) -> f32 {
+        self.estimate
+    }
+
+    /// Move the estimate towards the sample.
+    /// Steps down are smaller than steps up by a factor of (1 - target) / target,
+    /// so the estimate settles where a `target` fraction of samples are below it.
+    pub fn update(&mut self, sample: f32, step: f32) {
+        if sample < self.estimate {
+            self.estimate -= step / self.target;
+        }
+        if sample > self.estimate {
+            self.estimate += step / (1.0 - self.target);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct FPSStats {
     /// The half life (in seconds) of samples
     half_life: f32,
@@ -5,30 +47,55 @@ pub struct FPSStats {
     mean: f32,
     /// variance
     variance: f32,
-    /// 99th percentile
-    percentile_99: f32,
+    percentiles: Vec<PercentileEstimator>,
 }
 
 impl FPSStats {
+    /// Tracks the 50th, 95th, and 99th percentiles.
     pub fn new(half_life: f32) -> Self {
+        Self::with_percentiles(half_life, &[0.5, 0.95, 0.99])
+    }
+
+    /// Track the given percentiles, each from 0 to 1, such as 0.999 for the 99.9th percentile.
+    /// The 99th percentile is always tracked, for `percentile_99`.
+    pub fn with_percentiles(half_life: f32, targets: &[f32]) -> Self {
+        Self::with_initial_fps(half_life, targets, 60.0)
+    }
+
+    fn with_initial_fps(half_life: f32, targets: &[f32], initial_fps: f32) -> Self {
+        let mut percentiles: Vec<PercentileEstimator> = targets
+            .iter()
+            .map(|target| PercentileEstimator::new(*target, 1.0 / initial_fps))
+            .collect();
+        if !percentiles.iter().any(|p| same_target(p.target(), 0.99)) {
+            percentiles.push(PercentileEstimator::new(0.99, 1.0 / initial_fps));
+        }
         Self {
             half_life,
-            mean: 1.0 / 60.0,
+            mean: 1.0 / initial_fps,
             variance: 0.0,
-            percentile_99: 1.0 / 60.0,
+            percentiles,
         }
     }
 
+    /// Forget all frame times, such as after loading a level, keeping the half life.
+    pub fn reset(&mut self) {
+        self.reset_to(60.0);
+    }
+
+    /// Forget all frame times, and start from the expectation of a steady `initial_fps`.
+    pub fn reset_to(&mut self, initial_fps: f32) {
+        let targets: Vec<f32> = self.percentiles.iter().map(|p| p.target()).collect();
+        *self = Self::with_initial_fps(self.half_life, &targets, initial_fps);
+    }
+
     pub fn update(&mut self, frame_time: f32) {
         let alpha: f32 = 2.0_f32.powf(-frame_time / self.half_life);
         self.mean = alpha * self.mean + (1.0 - alpha) * frame_time;
         self.variance = alpha * self.variance + (1.0 - alpha) * (self.mean - frame_time).powi(2);
         let percentile_step = self.std() / 100.0;
-        if frame_time < self.percentile_99 {
-            self.percentile_99 -= percentile_step / 0.99;
-        }
-        if frame_time > self.percentile_99 {
-            self.percentile_99 += percentile_step / (1.0 - 0.99);
+        for percentile in self.percentiles.iter_mut() {
+            percentile.update(frame_time, percentile_step);
         }
     }
 
@@ -45,7 +112,92 @@ impl FPSStats {
         self.variance.sqrt()
     }
 
+    /// The estimated frame time below which a `target` fraction of frames fall.
+    /// `None` if the percentile isn't tracked.
+    pub fn percentile(&self, target: f32) -> Option<f32> {
+        self.percentiles
+            .iter()
+            .find(|p| same_target(p.target(), target))
+            .map(PercentileEstimator::estimate)
+    }
+
     pub fn percentile_99(&self) -> f32 {
-        self.percentile_99
+        self.percentile(0.99)
+            .expect("the 99th percentile is always tracked")
+    }
+}
+
+/// Whether two percentile targets are the same, allowing for rounding, such as 0.9 * 1.1 and 0.99.
+fn same_target(a: f32, b: f32) -> bool {
+    (a - b).abs() < 1e-6
+}
+
+/// A window title showing the frame rate, such as "pikuma — 142 fps (99th: 120)".
+/// The 99th percentile frame rate is how fast the slowest 1% of frames are.
+pub fn fps_title(name: &str, fps: f32, fps_99th: f32) -> String {
+    format!("{} — {:.0} fps (99th: {:.0})", name, fps, fps_99th)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fps_title, FPSStats};
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_reset() {
+        let mut stats = FPSStats::new(1.0);
+        for frame_time in [0.01, 0.2, 0.05, 0.5] {
+            stats.update(frame_time);
+        }
+        assert_ne!(stats, FPSStats::new(1.0));
+        stats.reset();
+        assert_eq!(stats, FPSStats::new(1.0));
+    }
+
+    #[test]
+    fn test_reset_to() {
+        let mut stats = FPSStats::new(1.0);
+        stats.update(0.5);
+        stats.reset_to(144.0);
+        assert_eq!(stats.mean(), 1.0 / 144.0);
+        assert_eq!(stats.percentile_99(), 1.0 / 144.0);
+        assert_eq!(stats.variance(), 0.0);
+    }
+
+    #[test]
+    fn test_percentile_not_tracked() {
+        let stats = FPSStats::with_percentiles(1.0, &[0.5]);
+        assert!(stats.percentile(0.5).is_some());
+        assert!(stats.percentile(0.95).is_none());
+        // The 99th percentile is tracked even when it isn't asked for.
+        assert_eq!(stats.percentile_99(), 1.0 / 60.0);
+        assert_eq!(stats.percentile(0.9 * 1.1), Some(stats.percentile_99()));
+    }
+
+    #[test]
+    fn test_fps_title() {
+        assert_eq!(
+            fps_title("pikuma", 142.3, 119.8),
+            "pikuma — 142 fps (99th: 120)"
+        );
+        assert_eq!(fps_title("game", 60.0, 30.4), "game — 60 fps (99th: 30)");
+    }
+
+    #[test]
+    fn test_percentiles_converge_in_order() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1547);
+        let mut stats = FPSStats::with_percentiles(1.0, &[0.5, 0.95, 0.99]);
+        for _ in 0..20_000 {
+            // Mostly fast frames, with a long tail of slow ones.
+            let frame_time = 0.01 - 0.01 * rng.gen::<f32>().ln();
+            stats.update(frame_time);
+        }
+        let p50 = stats.percentile(0.5).unwrap();
+        let p95 = stats.percentile(0.95).unwrap();
+        let p99 = stats.percentile(0.99).unwrap();
+        assert!(p50 < p95 && p95 < p99, "{p50} {p95} {p99}");
+        // The true median is 0.01 + 0.01 * ln(2).
+        assert!((p50 - 0.0169).abs() < 0.003, "{p50}");
+        assert_eq!(stats.percentile_99(), p99);
     }
 }
@@ -0,0 +1,87 @@
+/// The position of an analog stick, with each axis from -1 to 1.
+/// Like the world, positive y is down.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct StickState {
+    pub position: glam::Vec2,
+}
+
+impl StickState {
+    /// From raw axis values, as reported by a gamepad library, where positive y is up.
+    pub fn from_axes(x: f32, y: f32) -> Self {
+        Self {
+            position: glam::Vec2::new(x, -y).clamp(glam::Vec2::NEG_ONE, glam::Vec2::ONE),
+        }
+    }
+
+    /// The stick position with a radial deadzone applied, so a resting stick doesn't drift.
+    /// Outside the deadzone, the length is rescaled to go smoothly from 0 up to at most 1,
+    /// so diagonals aren't faster than straight lines.
+    pub fn with_deadzone(&self, deadzone: f32) -> glam::Vec2 {
+        let length = self.position.length();
+        if length <= deadzone {
+            return glam::Vec2::ZERO;
+        }
+        let scaled_length = ((length - deadzone) / (1.0 - deadzone)).min(1.0);
+        self.position / length * scaled_length
+    }
+}
+
+#[cfg(feature = "gamepad")]
+#[derive(Debug)]
+pub enum GamepadError {
+    /// Gamepads can't be read on this platform.
+    Unsupported,
+    Other(String),
+}
+
+/// Reads gamepads through gilrs. Call `poll` once per frame.
+#[cfg(feature = "gamepad")]
+pub struct Gamepads {
+    gilrs: gilrs::Gilrs,
+    /// The gamepad used most recently, which is the one that steers.
+    active: Option<gilrs::GamepadId>,
+}
+
+#[cfg(feature = "gamepad")]
+impl Gamepads {
+    pub fn new() -> Result<Self, GamepadError> {
+        let gilrs = gilrs::Gilrs::new().map_err(|e| match e {
+            gilrs::Error::NotImplemented(_) => GamepadError::Unsupported,
+            e => GamepadError::Other(e.to_string()),
+        })?;
+        let active = gilrs.gamepads().next().map(|(id, _gamepad)| id);
+        Ok(Self { gilrs, active })
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Handle the gamepad events since the last poll, and read the active gamepad's left stick.
+    /// The stick is centered when no gamepad is connected.
+    pub fn poll(&mut self) -> StickState {
+        while let Some(gilrs::Event { id, event, .. }) = self.gilrs.next_event() {
+            match event {
+                gilrs::EventType::Disconnected => {
+                    if self.active == Some(id) {
+                        self.active = self
+                            .gilrs
+                            .gamepads()
+                            .map(|(other_id, _gamepad)| other_id)
+                            .find(|other_id| *other_id != id);
+                    }
+                }
+                _ => self.active = Some(id),
+            }
+        }
+        self.active
+            .and_then(|id| self.gilrs.connected_gamepad(id))
+            .map(|gamepad| {
+                StickState::from_axes(
+                    gamepad.value(gilrs::Axis::LeftStickX),
+                    gamepad.value(gilrs::Axis::LeftStickY),
+                )
+            })
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,417 @@
+/// How two overlapping shapes touch.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact {
+    /// The minimum translation vector: moving the first shape by this much
+    /// separates it from the second.
+    pub overlap: glam::Vec2,
+    /// A point in the overlapping region.
+    pub contact_point: glam::Vec2,
+}
+
+/// An axis aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rectangle {
+    pub top_left: glam::Vec2,
+    pub bottom_right: glam::Vec2,
+}
+
+impl Rectangle {
+    pub fn new(top_left: glam::Vec2, bottom_right: glam::Vec2) -> Self {
+        Self {
+            top_left,
+            bottom_right,
+        }
+    }
+
+    pub fn from_top_left(top_left: glam::Vec2, width_height: glam::Vec2) -> Self {
+        Self {
+            top_left,
+            bottom_right: top_left + width_height,
+        }
+    }
+
+    pub fn from_center(center: glam::Vec2, width_height: glam::Vec2) -> Self {
+        Self {
+            top_left: center - (width_height / 2.0),
+            bottom_right: center + (width_height / 2.0),
+        }
+    }
+
+    pub fn width_height(&self) -> glam::Vec2 {
+        self.bottom_right - self.top_left
+    }
+
+    pub fn center(&self) -> glam::Vec2 {
+        (self.top_left + self.bottom_right) / 2.0
+    }
+
+    fn range_intersects(a0: f32, a1: f32, b0: f32, b1: f32) -> bool {
+        (a0 <= b0 && b0 <= a1) || (a0 <= b1 && b1 <= a1) || (b0 <= a0 && a0 <= b1)
+    }
+
+    /// Returns true if the rectangles overlap.
+    /// Rectangles that only touch at an edge are considered overlapping.
+    pub fn intersects(&self, other: &Rectangle) -> bool {
+        let x_axis_intersects = Self::range_intersects(
+            self.top_left.x,
+            self.bottom_right.x,
+            other.top_left.x,
+            other.bottom_right.x,
+        );
+        let y_axis_intersects = Self::range_intersects(
+            self.top_left.y,
+            self.bottom_right.y,
+            other.top_left.y,
+            other.bottom_right.y,
+        );
+        x_axis_intersects && y_axis_intersects
+    }
+
+    pub fn contains_point(&self, point: glam::Vec2) -> bool {
+        self.top_left.x <= point.x
+            && point.x <= self.bottom_right.x
+            && self.top_left.y <= point.y
+            && point.y <= self.bottom_right.y
+    }
+
+    /// Wrap a point around the edges of the rectangle, so a point past the right edge
+    /// reappears on the left, and a point past the bottom edge reappears at the top.
+    pub fn wrap(&self, point: glam::Vec2) -> glam::Vec2 {
+        self.top_left + (point - self.top_left).rem_euclid(self.width_height())
+    }
+
+    /// The overlapping region of the two rectangles, if they overlap.
+    pub fn intersection(&self, other: &Rectangle) -> Option<Rectangle> {
+        if !self.intersects(other) {
+            return None;
+        }
+        Some(Rectangle {
+            top_left: self.top_left.max(other.top_left),
+            bottom_right: self.bottom_right.min(other.bottom_right),
+        })
+    }
+
+    /// How the rectangles touch, if they overlap.
+    /// The overlap pushes along whichever axis needs the smaller push.
+    pub fn contact(&self, other: &Rectangle) -> Option<Contact> {
+        let intersection = self.intersection(other)?;
+        let size = intersection.width_height();
+        let direction = (self.center() - other.center()).signum();
+        let overlap = if size.x < size.y {
+            glam::Vec2::new(direction.x * size.x, 0.0)
+        } else {
+            glam::Vec2::new(0.0, direction.y * size.y)
+        };
+        Some(Contact {
+            overlap,
+            contact_point: intersection.center(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Circle {
+    pub center: glam::Vec2,
+    pub radius: f32,
+}
+
+impl Circle {
+    pub fn new(center: glam::Vec2, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    /// Returns true if the circles overlap.
+    /// Circles that only touch at an edge are considered overlapping.
+    pub fn intersects(&self, other: &Circle) -> bool {
+        let radii = self.radius + other.radius;
+        self.center.distance_squared(other.center) <= radii * radii
+    }
+
+    /// Returns true if the circle and rectangle overlap.
+    /// Shapes that only touch at an edge are considered overlapping.
+    pub fn intersects_rectangle(&self, rectangle: &Rectangle) -> bool {
+        let closest = self
+            .center
+            .clamp(rectangle.top_left, rectangle.bottom_right);
+        self.center.distance_squared(closest) <= self.radius * self.radius
+    }
+
+    /// How the circles touch, if they overlap.
+    pub fn contact(&self, other: &Circle) -> Option<Contact> {
+        if !self.intersects(other) {
+            return None;
+        }
+        let offset = self.center - other.center;
+        let distance = offset.length();
+        // Concentric circles have no preferred direction, so push along x.
+        let direction = offset.try_normalize().unwrap_or(glam::Vec2::X);
+        let depth = self.radius + other.radius - distance;
+        Some(Contact {
+            overlap: direction * depth,
+            contact_point: other.center + direction * (other.radius - depth / 2.0),
+        })
+    }
+
+    /// How the circle touches the rectangle, if they overlap.
+    /// The overlap pushes the circle out of the rectangle.
+    pub fn contact_rectangle(&self, rectangle: &Rectangle) -> Option<Contact> {
+        if !self.intersects_rectangle(rectangle) {
+            return None;
+        }
+        let closest = self
+            .center
+            .clamp(rectangle.top_left, rectangle.bottom_right);
+        if closest != self.center {
+            let offset = self.center - closest;
+            let distance = offset.length();
+            return Some(Contact {
+                overlap: offset / distance * (self.radius - distance),
+                contact_point: closest,
+            });
+        }
+        // The center is inside the rectangle, so push out through the nearest edge.
+        let to_top_left = self.center - rectangle.top_left;
+        let to_bottom_right = rectangle.bottom_right - self.center;
+        let edges = [
+            (to_top_left.x, glam::Vec2::NEG_X),
+            (to_bottom_right.x, glam::Vec2::X),
+            (to_top_left.y, glam::Vec2::NEG_Y),
+            (to_bottom_right.y, glam::Vec2::Y),
+        ];
+        let (distance, direction) = edges
+            .into_iter()
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .unwrap();
+        Some(Contact {
+            overlap: direction * (distance + self.radius),
+            contact_point: self.center + direction * distance,
+        })
+    }
+
+    /// The smallest axis aligned bounding box containing the circle.
+    pub fn bounding_rectangle(&self) -> Rectangle {
+        Rectangle::from_center(self.center, glam::Vec2::splat(self.radius * 2.0))
+    }
+}
+
+/// A collision shape in world space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    Rectangle(Rectangle),
+    Circle(Circle),
+}
+
+impl Shape {
+    /// Returns true if the shapes overlap.
+    /// Shapes that only touch at an edge are considered overlapping.
+    pub fn intersects(&self, other: &Shape) -> bool {
+        match (self, other) {
+            (Shape::Rectangle(a), Shape::Rectangle(b)) => a.intersects(b),
+            (Shape::Circle(a), Shape::Circle(b)) => a.intersects(b),
+            (Shape::Circle(circle), Shape::Rectangle(rectangle))
+            | (Shape::Rectangle(rectangle), Shape::Circle(circle)) => {
+                circle.intersects_rectangle(rectangle)
+            }
+        }
+    }
+
+    /// How the shapes touch, if they overlap.
+    pub fn contact(&self, other: &Shape) -> Option<Contact> {
+        match (self, other) {
+            (Shape::Rectangle(a), Shape::Rectangle(b)) => a.contact(b),
+            (Shape::Circle(a), Shape::Circle(b)) => a.contact(b),
+            (Shape::Circle(circle), Shape::Rectangle(rectangle)) => {
+                circle.contact_rectangle(rectangle)
+            }
+            (Shape::Rectangle(rectangle), Shape::Circle(circle)) => {
+                let contact = circle.contact_rectangle(rectangle)?;
+                Some(Contact {
+                    overlap: -contact.overlap,
+                    ..contact
+                })
+            }
+        }
+    }
+
+    pub fn bounding_rectangle(&self) -> Rectangle {
+        match self {
+            Shape::Rectangle(rectangle) => *rectangle,
+            Shape::Circle(circle) => circle.bounding_rectangle(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Circle, Contact, Rectangle, Shape};
+
+    #[test]
+    fn test_rectangle_intersects() {
+        let a = Rectangle::from_top_left(glam::Vec2::new(0.0, 0.0), glam::Vec2::new(10.0, 10.0));
+        let b = Rectangle::from_top_left(glam::Vec2::new(5.0, 5.0), glam::Vec2::new(10.0, 10.0));
+        let c = Rectangle::from_top_left(glam::Vec2::new(20.0, 0.0), glam::Vec2::new(10.0, 10.0));
+        let inside = Rectangle::from_center(glam::Vec2::new(5.0, 5.0), glam::Vec2::new(2.0, 2.0));
+        let touching =
+            Rectangle::from_top_left(glam::Vec2::new(10.0, 0.0), glam::Vec2::new(10.0, 10.0));
+        assert!(a.intersects(&b));
+        assert!(b.intersects(&a));
+        assert!(!a.intersects(&c));
+        assert!(a.intersects(&inside));
+        assert!(inside.intersects(&a));
+        assert!(a.intersects(&touching));
+    }
+
+    #[test]
+    fn test_rectangle_contains_point() {
+        let a = Rectangle::from_center(glam::Vec2::new(0.0, 0.0), glam::Vec2::new(4.0, 2.0));
+        assert!(a.contains_point(glam::Vec2::new(0.0, 0.0)));
+        assert!(a.contains_point(glam::Vec2::new(2.0, 1.0)));
+        assert!(!a.contains_point(glam::Vec2::new(2.5, 0.0)));
+        assert!(!a.contains_point(glam::Vec2::new(0.0, -1.5)));
+    }
+
+    #[test]
+    fn test_rectangle_intersection() {
+        let a = Rectangle::from_top_left(glam::Vec2::new(0.0, 0.0), glam::Vec2::new(10.0, 10.0));
+        let b = Rectangle::from_top_left(glam::Vec2::new(5.0, 8.0), glam::Vec2::new(10.0, 10.0));
+        let c = Rectangle::from_top_left(glam::Vec2::new(20.0, 0.0), glam::Vec2::new(10.0, 10.0));
+        assert_eq!(
+            a.intersection(&b),
+            Some(Rectangle::new(
+                glam::Vec2::new(5.0, 8.0),
+                glam::Vec2::new(10.0, 10.0)
+            ))
+        );
+        assert_eq!(a.intersection(&c), None);
+    }
+
+    #[test]
+    fn test_rectangle_wrap() {
+        let a = Rectangle::from_top_left(glam::Vec2::new(10.0, 20.0), glam::Vec2::new(100.0, 50.0));
+        assert_eq!(
+            a.wrap(glam::Vec2::new(50.0, 30.0)),
+            glam::Vec2::new(50.0, 30.0)
+        );
+        assert_eq!(
+            a.wrap(glam::Vec2::new(115.0, 30.0)),
+            glam::Vec2::new(15.0, 30.0)
+        );
+        assert_eq!(
+            a.wrap(glam::Vec2::new(5.0, 10.0)),
+            glam::Vec2::new(105.0, 60.0)
+        );
+    }
+
+    #[test]
+    fn test_circle_intersects_circle() {
+        let a = Circle::new(glam::Vec2::new(0.0, 0.0), 5.0);
+        let overlapping = Circle::new(glam::Vec2::new(6.0, 0.0), 2.0);
+        let touching = Circle::new(glam::Vec2::new(0.0, 8.0), 3.0);
+        let inside = Circle::new(glam::Vec2::new(1.0, 1.0), 1.0);
+        let separate = Circle::new(glam::Vec2::new(6.0, 6.0), 2.0);
+        assert!(a.intersects(&overlapping));
+        assert!(overlapping.intersects(&a));
+        assert!(a.intersects(&touching));
+        assert!(a.intersects(&inside));
+        assert!(inside.intersects(&a));
+        assert!(!a.intersects(&separate));
+        assert!(!separate.intersects(&a));
+    }
+
+    #[test]
+    fn test_circle_intersects_rectangle() {
+        let rectangle =
+            Rectangle::from_top_left(glam::Vec2::new(0.0, 0.0), glam::Vec2::new(10.0, 10.0));
+        let overlapping = Circle::new(glam::Vec2::new(12.0, 5.0), 3.0);
+        let touching_edge = Circle::new(glam::Vec2::new(5.0, -2.0), 2.0);
+        let touching_corner = Circle::new(glam::Vec2::new(13.0, 14.0), 5.0);
+        let near_corner = Circle::new(glam::Vec2::new(13.0, 14.0), 4.9);
+        let inside = Circle::new(glam::Vec2::new(5.0, 5.0), 1.0);
+        let containing = Circle::new(glam::Vec2::new(5.0, 5.0), 20.0);
+        let separate = Circle::new(glam::Vec2::new(20.0, 5.0), 3.0);
+        assert!(overlapping.intersects_rectangle(&rectangle));
+        assert!(touching_edge.intersects_rectangle(&rectangle));
+        assert!(touching_corner.intersects_rectangle(&rectangle));
+        assert!(!near_corner.intersects_rectangle(&rectangle));
+        assert!(inside.intersects_rectangle(&rectangle));
+        assert!(containing.intersects_rectangle(&rectangle));
+        assert!(!separate.intersects_rectangle(&rectangle));
+    }
+
+    #[test]
+    fn test_shape_intersects() {
+        let rectangle = Shape::Rectangle(Rectangle::from_top_left(
+            glam::Vec2::new(0.0, 0.0),
+            glam::Vec2::new(10.0, 10.0),
+        ));
+        let touching_rectangle = Shape::Rectangle(Rectangle::from_top_left(
+            glam::Vec2::new(10.0, 0.0),
+            glam::Vec2::new(10.0, 10.0),
+        ));
+        let circle = Shape::Circle(Circle::new(glam::Vec2::new(12.0, 5.0), 3.0));
+        let far_circle = Shape::Circle(Circle::new(glam::Vec2::new(30.0, 5.0), 3.0));
+        assert!(rectangle.intersects(&touching_rectangle));
+        assert!(rectangle.intersects(&circle));
+        assert!(circle.intersects(&rectangle));
+        assert!(circle.intersects(&touching_rectangle));
+        assert!(!far_circle.intersects(&rectangle));
+        assert!(!rectangle.intersects(&far_circle));
+        assert!(!circle.intersects(&far_circle));
+    }
+
+    #[test]
+    fn test_rectangle_contact() {
+        let a = Rectangle::from_top_left(glam::Vec2::new(0.0, 0.0), glam::Vec2::new(10.0, 10.0));
+        let right =
+            Rectangle::from_top_left(glam::Vec2::new(8.0, 1.0), glam::Vec2::new(10.0, 10.0));
+        let below =
+            Rectangle::from_top_left(glam::Vec2::new(-1.0, 7.0), glam::Vec2::new(10.0, 10.0));
+        let far = Rectangle::from_top_left(glam::Vec2::new(20.0, 0.0), glam::Vec2::new(10.0, 10.0));
+        assert_eq!(
+            a.contact(&right),
+            Some(Contact {
+                overlap: glam::Vec2::new(-2.0, 0.0),
+                contact_point: glam::Vec2::new(9.0, 5.5),
+            })
+        );
+        assert_eq!(
+            right.contact(&a).unwrap().overlap,
+            glam::Vec2::new(2.0, 0.0)
+        );
+        assert_eq!(
+            a.contact(&below),
+            Some(Contact {
+                overlap: glam::Vec2::new(0.0, -3.0),
+                contact_point: glam::Vec2::new(4.5, 8.5),
+            })
+        );
+        assert_eq!(a.contact(&far), None);
+    }
+
+    #[test]
+    fn test_circle_contact() {
+        let a = Circle::new(glam::Vec2::new(0.0, 0.0), 5.0);
+        let b = Circle::new(glam::Vec2::new(0.0, 6.0), 3.0);
+        let contact = a.contact(&b).unwrap();
+        assert!((contact.overlap - glam::Vec2::new(0.0, -2.0)).length() < 1e-5);
+        assert!((contact.contact_point - glam::Vec2::new(0.0, 4.0)).length() < 1e-5);
+        assert!(a
+            .contact(&Circle::new(glam::Vec2::new(10.0, 0.0), 3.0))
+            .is_none());
+
+        let rectangle =
+            Rectangle::from_top_left(glam::Vec2::new(0.0, 0.0), glam::Vec2::new(10.0, 10.0));
+        let outside = Circle::new(glam::Vec2::new(12.0, 5.0), 3.0);
+        let contact = outside.contact_rectangle(&rectangle).unwrap();
+        assert!((contact.overlap - glam::Vec2::new(1.0, 0.0)).length() < 1e-5);
+        assert_eq!(contact.contact_point, glam::Vec2::new(10.0, 5.0));
+        let inside = Circle::new(glam::Vec2::new(5.0, 1.0), 2.0);
+        let contact = inside.contact_rectangle(&rectangle).unwrap();
+        assert!((contact.overlap - glam::Vec2::new(0.0, -3.0)).length() < 1e-5);
+        assert_eq!(contact.contact_point, glam::Vec2::new(5.0, 0.0));
+        let contact = Shape::Rectangle(rectangle)
+            .contact(&Shape::Circle(outside))
+            .unwrap();
+        assert!((contact.overlap - glam::Vec2::new(-1.0, 0.0)).length() < 1e-5);
+    }
+}
@@ -1,5 +1,14 @@
+pub mod audio;
 pub mod components_systems;
+pub mod control_flow;
 pub mod ecs;
 pub mod event_bus;
 pub mod fps_stats;
+pub mod gamepad;
+pub mod geometry;
+pub mod mouse;
+pub mod profiling;
+pub mod random;
 pub mod renderer;
+pub mod tilemap;
+pub mod timestep;
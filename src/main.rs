@@ -2,7 +2,6 @@
 // TODO: Game.process_input
 // TODO: Game.update
 // TODO: Game.render
-// TODO: How will I play sounds?
 // TODO: Clear window with a color
 // TODO: I will need to track keystate myself, possible with a set
 // TODO: Simulate a lower resolution
@@ -10,23 +9,96 @@
 // TODO: Setup a good logging system, write some logs
 // TODO: Load an image and show it on the screen
 // TODO: Come up with something better than unwrap-based error handling
-use pikuma_game_engine::fps_stats::FPSStats;
+use pikuma_game_engine::control_flow::{ControlFlow, ControlRequest};
+use pikuma_game_engine::fps_stats::{fps_title, FPSStats};
+use pikuma_game_engine::mouse::MouseState;
+use pikuma_game_engine::profiling::SystemTimings;
+use pikuma_game_engine::random::Rng;
 use pikuma_game_engine::renderer::Sprite;
+use pikuma_game_engine::tilemap::{Tile, TileVariants};
+use pikuma_game_engine::timestep::{
+    clamp_delta, FixedTimestep, FIXED_DELTA_SECONDS, MAX_DELTA_SECONDS,
+};
 use pikuma_game_engine::{components_systems, ecs, renderer};
 use std::cell::RefCell;
 use std::io::BufRead as _;
 use std::rc::Rc;
 
+/// Pressing this key pauses or resumes the game.
+const PAUSE_KEY: winit::keyboard::PhysicalKey =
+    winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyP);
+
+/// Shaders are read from this directory, if it's set, and reloaded when they change.
+const SHADER_DIR_VAR: &str = "PIKUMA_SHADER_DIR";
+
+/// Where to read shaders from, so they can be edited while the game runs.
+/// Debug builds read them from the source tree, wherever the game is run from.
+/// Otherwise, the shaders built into the game are used.
+fn shader_dir() -> Option<std::path::PathBuf> {
+    if let Some(shader_dir) = std::env::var_os(SHADER_DIR_VAR) {
+        return Some(shader_dir.into());
+    }
+    if cfg!(debug_assertions) {
+        return Some(std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src/shaders"));
+    }
+    None
+}
+
+/// Set to 1 to show the frame rate in the window title, or 0 to hide it.
+const SHOW_FPS_VAR: &str = "PIKUMA_SHOW_FPS";
+
+/// Whether to show the frame rate in the window title.
+/// Debug builds show it unless it's turned off.
+fn show_fps_in_title() -> bool {
+    match std::env::var(SHOW_FPS_VAR) {
+        Ok(show_fps) => show_fps != "0",
+        Err(_) => cfg!(debug_assertions),
+    }
+}
+
+/// Set to 1 to pass systems the mean frame time rather than the last frame's time.
+const SMOOTH_DELTA_VAR: &str = "PIKUMA_SMOOTH_DELTA";
+
+/// Whether to pass systems the mean frame time rather than the last frame's time.
+/// A single slow or fast frame then doesn't make entities jump or stall, but the
+/// simulation drifts from real time while the frame rate changes, since the mean lags behind.
+/// Off unless turned on.
+fn smooth_delta() -> bool {
+    std::env::var(SMOOTH_DELTA_VAR).is_ok_and(|smooth_delta| smooth_delta != "0")
+}
+
 struct Game {
     renderer: renderer::Renderer,
     registry: ecs::Registry,
     pressed_keys: std::collections::HashSet<winit::keyboard::PhysicalKey>,
+    /// Movement and collision run in fixed steps, however long frames take.
+    timestep: FixedTimestep,
+    /// While paused the scene is still drawn, but nothing moves.
+    paused: bool,
+    /// Show the frame rate in the window title, updated every second.
+    show_fps_in_title: bool,
+    /// `None` if gamepads can't be read on this platform.
+    #[cfg(feature = "gamepad")]
+    gamepads: Option<pikuma_game_engine::gamepad::Gamepads>,
+    /// Kept so its play limit can start over every frame.
+    #[cfg(feature = "audio")]
+    audio_handler: Rc<RefCell<pikuma_game_engine::audio::AudioHandler>>,
 }
 
 impl Game {
     fn new(window: winit::window::Window, width: u32, height: u32) -> Self {
         let mut registry = ecs::Registry::new();
-        let mut renderer = renderer::Renderer::new(window, width, height);
+        registry.insert_resource(Rng::new(0));
+        registry.insert_resource(SystemTimings::new());
+        registry.insert_resource(ControlFlow::new());
+        registry.insert_resource(MouseState::new());
+        let mut renderer = match shader_dir() {
+            Some(shader_dir) => {
+                renderer::Renderer::new_with_shader_dir(window, width, height, shader_dir)
+            }
+            None => renderer::Renderer::new(window, width, height),
+        }
+        .expect("can't create renderer");
         renderer.configure_surface();
 
         let tree = registry.create_entity();
@@ -36,47 +108,44 @@ impl Game {
         registry
             .add_component(
                 tree,
-                components_systems::RigidBodyComponent {
-                    position: glam::Vec2::new(20.0, 10.0),
-                    velocity: glam::Vec2::new(0.0, 0.0),
-                },
+                components_systems::RigidBodyComponent::at(glam::Vec2::new(20.0, 10.0)),
             )
             .unwrap();
         registry
             .add_component(
                 tree,
-                components_systems::SpriteComponent {
-                    sprite_index: renderer.load_sprite(Sprite::new(
+                components_systems::SpriteComponent::new(
+                    renderer.load_sprite(Sprite::new(
                         "assets/images/tree.png".into(),
                         glam::UVec2::new(0, 0),
                         glam::UVec2::new(16, 32),
                     )),
-                    sprite_layer: components_systems::Layer::Ground,
-                    size: glam::Vec2::new(16.0, 32.0),
-                },
+                    components_systems::Layer::Ground,
+                    glam::Vec2::new(16.0, 32.0),
+                ),
             )
             .unwrap();
         registry
             .add_component(
                 tank_1,
                 components_systems::RigidBodyComponent {
-                    position: glam::Vec2::new(0.0, 50.0),
                     velocity: glam::Vec2::new(10.0, 4.0),
+                    ..components_systems::RigidBodyComponent::at(glam::Vec2::new(0.0, 50.0))
                 },
             )
             .unwrap();
         registry
             .add_component(
                 tank_1,
-                components_systems::SpriteComponent {
-                    sprite_index: renderer.load_sprite(Sprite::new(
+                components_systems::SpriteComponent::new(
+                    renderer.load_sprite(Sprite::new(
                         "assets/images/tank-panther-right.png".into(),
                         glam::UVec2::new(0, 0),
                         glam::UVec2::new(32, 32),
                     )),
-                    sprite_layer: components_systems::Layer::Ground,
-                    size: glam::Vec2::new(32.0, 32.0),
-                },
+                    components_systems::Layer::Ground,
+                    glam::Vec2::new(32.0, 32.0),
+                ),
             )
             .unwrap();
         registry
@@ -84,7 +153,9 @@ impl Game {
                 tank_1,
                 components_systems::CollisionComponent {
                     offset: glam::Vec2::new(6.0, 6.0),
-                    width_height: glam::Vec2::new(20.0, 20.0),
+                    shape: components_systems::ColliderShape::Rectangle {
+                        width_height: glam::Vec2::new(20.0, 20.0),
+                    },
                 },
             )
             .unwrap();
@@ -92,23 +163,23 @@ impl Game {
             .add_component(
                 tank_2,
                 components_systems::RigidBodyComponent {
-                    position: glam::Vec2::new(0.0, 100.0),
                     velocity: glam::Vec2::new(10.0, 8.0),
+                    ..components_systems::RigidBodyComponent::at(glam::Vec2::new(0.0, 100.0))
                 },
             )
             .unwrap();
         registry
             .add_component(
                 tank_2,
-                components_systems::SpriteComponent {
-                    sprite_index: renderer.load_sprite(Sprite::new(
+                components_systems::SpriteComponent::new(
+                    renderer.load_sprite(Sprite::new(
                         "assets/images/tank-panther-right.png".into(),
                         glam::UVec2::new(0, 0),
                         glam::UVec2::new(32, 32),
                     )),
-                    sprite_layer: components_systems::Layer::Ground,
-                    size: glam::Vec2::new(32.0, 32.0),
-                },
+                    components_systems::Layer::Ground,
+                    glam::Vec2::new(32.0, 32.0),
+                ),
             )
             .unwrap();
         registry
@@ -116,7 +187,9 @@ impl Game {
                 tank_2,
                 components_systems::CollisionComponent {
                     offset: glam::Vec2::new(6.0, 6.0),
-                    width_height: glam::Vec2::new(20.0, 20.0),
+                    shape: components_systems::ColliderShape::Rectangle {
+                        width_height: glam::Vec2::new(20.0, 20.0),
+                    },
                 },
             )
             .unwrap();
@@ -124,78 +197,42 @@ impl Game {
             .add_component(
                 chopper,
                 components_systems::RigidBodyComponent {
-                    position: glam::Vec2::new(0.0, 200.0),
                     velocity: glam::Vec2::new(10.0, -3.0),
+                    ..components_systems::RigidBodyComponent::at(glam::Vec2::new(0.0, 200.0))
                 },
             )
             .unwrap();
         registry
             .add_component(
                 chopper,
-                components_systems::SpriteComponent {
-                    sprite_index: renderer.load_sprite(Sprite::new(
+                components_systems::SpriteComponent::new(
+                    renderer.load_sprite(Sprite::new(
                         "assets/images/chopper-spritesheet.png".into(),
                         glam::UVec2::new(0, 0),
                         glam::UVec2::new(32, 32),
                     )),
-                    sprite_layer: components_systems::Layer::Air,
-                    size: glam::Vec2::new(32.0, 32.0),
-                },
+                    components_systems::Layer::Air,
+                    glam::Vec2::new(32.0, 32.0),
+                ),
             )
             .unwrap();
+        // The chopper sprite sheet has a row of 32 x 32 frames for each direction.
+        let mut chopper_frame = |column: u32, row: u32| {
+            renderer.load_sprite(Sprite::new(
+                "assets/images/chopper-spritesheet.png".into(),
+                glam::UVec2::new(32 * column, 32 * row),
+                glam::UVec2::new(32, 32),
+            ))
+        };
         registry
             .add_component(
                 chopper,
                 components_systems::MotionAnimationComponent::new(
                     1.0 / 15.0,
-                    vec![
-                        renderer.load_sprite(Sprite::new(
-                            "assets/images/chopper-spritesheet.png".into(),
-                            glam::UVec2::new(32 * 0, 32 * 3),
-                            glam::UVec2::new(32, 32),
-                        )),
-                        renderer.load_sprite(Sprite::new(
-                            "assets/images/chopper-spritesheet.png".into(),
-                            glam::UVec2::new(32 * 1, 32 * 3),
-                            glam::UVec2::new(32, 32),
-                        )),
-                    ],
-                    vec![
-                        renderer.load_sprite(Sprite::new(
-                            "assets/images/chopper-spritesheet.png".into(),
-                            glam::UVec2::new(32 * 0, 32 * 2),
-                            glam::UVec2::new(32, 32),
-                        )),
-                        renderer.load_sprite(Sprite::new(
-                            "assets/images/chopper-spritesheet.png".into(),
-                            glam::UVec2::new(32 * 1, 32 * 2),
-                            glam::UVec2::new(32, 32),
-                        )),
-                    ],
-                    vec![
-                        renderer.load_sprite(Sprite::new(
-                            "assets/images/chopper-spritesheet.png".into(),
-                            glam::UVec2::new(32 * 0, 32 * 1),
-                            glam::UVec2::new(32, 32),
-                        )),
-                        renderer.load_sprite(Sprite::new(
-                            "assets/images/chopper-spritesheet.png".into(),
-                            glam::UVec2::new(32 * 1, 32 * 1),
-                            glam::UVec2::new(32, 32),
-                        )),
-                    ],
-                    vec![
-                        renderer.load_sprite(Sprite::new(
-                            "assets/images/chopper-spritesheet.png".into(),
-                            glam::UVec2::new(32 * 0, 32 * 0),
-                            glam::UVec2::new(32, 32),
-                        )),
-                        renderer.load_sprite(Sprite::new(
-                            "assets/images/chopper-spritesheet.png".into(),
-                            glam::UVec2::new(32 * 1, 32 * 0),
-                            glam::UVec2::new(32, 32),
-                        )),
-                    ],
+                    vec![chopper_frame(0, 3), chopper_frame(1, 3)],
+                    vec![chopper_frame(0, 2), chopper_frame(1, 2)],
+                    vec![chopper_frame(0, 1), chopper_frame(1, 1)],
+                    vec![chopper_frame(0, 0), chopper_frame(1, 0)],
                 ),
             )
             .unwrap();
@@ -204,7 +241,9 @@ impl Game {
                 chopper,
                 components_systems::CollisionComponent {
                     offset: glam::Vec2::new(6.0, 6.0),
-                    width_height: glam::Vec2::new(20.0, 20.0),
+                    shape: components_systems::ColliderShape::Rectangle {
+                        width_height: glam::Vec2::new(20.0, 20.0),
+                    },
                 },
             )
             .unwrap();
@@ -216,21 +255,51 @@ impl Game {
                 chopper,
                 components_systems::CameraFocusComponent {
                     focus_offset: glam::Vec2::new(16.0, 16.0),
-                    viewport_size: glam::Vec2::new(800.0, 600.0),
                     map_top_left: glam::Vec2::ZERO,
                     map_bottom_right: glam::Vec2::new(25.0 * 32.0 * 2.0, 20.0 * 32.0 * 2.0),
+                    zoom: 1.0,
+                    slot: 0,
                 },
             )
             .unwrap();
-        registry.add_system(Rc::new(RefCell::new(
-            components_systems::MovementSystem::new(),
-        )));
-        registry.add_system(Rc::new(RefCell::new(
-            components_systems::AnimationSystem::new(),
-        )));
-        registry.add_system(Rc::new(RefCell::new(
-            components_systems::MotionAnimationSystem::new(),
-        )));
+        #[cfg(feature = "gamepad")]
+        let gamepads = match pikuma_game_engine::gamepad::Gamepads::new() {
+            Ok(gamepads) => Some(gamepads),
+            Err(e) => {
+                log::warn!("Playing without gamepads: {:?}", e);
+                None
+            }
+        };
+        // With a gamepad connected, the chopper is steered by the gamepad instead of the keyboard.
+        #[cfg(feature = "gamepad")]
+        if gamepads
+            .as_ref()
+            .is_some_and(|gamepads| gamepads.is_connected())
+        {
+            registry
+                .remove_component::<components_systems::KeyboardControlComponent>(chopper)
+                .unwrap();
+            registry
+                .add_component(chopper, components_systems::GamepadControlComponent)
+                .unwrap();
+        }
+        components_systems::add_update_systems(&mut registry);
+        #[cfg(feature = "audio")]
+        let audio_handler = {
+            match pikuma_game_engine::audio::AudioEngine::new() {
+                Ok(audio_engine) => registry.insert_resource(audio_engine),
+                Err(e) => log::warn!("Playing without sound: {:?}", e),
+            }
+            let sound_system = Rc::new(RefCell::new(pikuma_game_engine::audio::SoundSystem::new()));
+            registry.add_handler::<components_systems::CollisionEvent, _>(Rc::clone(&sound_system));
+            registry.add_handler::<components_systems::DamageEvent, _>(sound_system);
+            let audio_handler = Rc::new(RefCell::new(
+                pikuma_game_engine::audio::AudioHandler::new("assets/sounds/explosion.wav"),
+            ));
+            registry
+                .add_handler::<components_systems::CollisionEvent, _>(Rc::clone(&audio_handler));
+            audio_handler
+        };
         registry.add_system(Rc::new(RefCell::new(
             components_systems::CameraFocusSystem::new(),
         )));
@@ -238,62 +307,74 @@ impl Game {
             components_systems::RenderSystem::new(),
         )));
         registry.add_system(Rc::new(RefCell::new(
-            components_systems::KeyboardControlSystem::new(),
+            components_systems::UiRenderSystem::new(),
         )));
-        let collision_system = Rc::new(RefCell::new(components_systems::CollisionSystem::new()));
-        registry.add_handler::<components_systems::CollisionEvent, _>(Rc::clone(&collision_system));
-        registry.add_handler::<winit::keyboard::PhysicalKey, _>(Rc::clone(&collision_system));
-        registry.add_system(collision_system);
 
         let mut game = Game {
             renderer,
             registry,
             pressed_keys: std::collections::HashSet::new(),
+            timestep: FixedTimestep::new(FIXED_DELTA_SECONDS),
+            paused: false,
+            show_fps_in_title: show_fps_in_title(),
+            #[cfg(feature = "gamepad")]
+            gamepads,
+            #[cfg(feature = "audio")]
+            audio_handler,
         };
-        game.load_map("assets/tilemaps/jungle.map");
+        game.load_map("assets/tilemaps/jungle.map", &TileVariants::new());
         game
     }
 
     /// Read tilemap and create entities for each background tile.
-    fn load_map<P: AsRef<std::path::Path>>(&mut self, map_file: P) {
+    /// Tiles may be flipped or rotated using Tiled's flag bits.
+    /// Tiles with variants get a random variant, drawn from the registry's Rng.
+    fn load_map<P: AsRef<std::path::Path>>(&mut self, map_file: P, tile_variants: &TileVariants) {
         let map_file = std::fs::File::open(&map_file)
             .unwrap_or_else(|_| panic!("can't read map file ({:?})", map_file.as_ref()));
         let reader = std::io::BufReader::new(map_file);
+        let mut tiles = Vec::new();
+        let mut sprites = Vec::new();
         for (row, line) in reader.lines().enumerate() {
             let line = line.expect("can't read map file line");
             for (col, tile) in line.split(',').enumerate() {
-                let tile = tile.trim().parse::<u32>().expect("can't parse tile index");
-                let sprite = Sprite::new(
-                    "assets/tilemaps/jungle.png".into(),
-                    glam::UVec2::new(32 * (tile % 10), 32 * (tile / 10)),
-                    glam::UVec2::new(32, 32),
-                );
-                let background_tile = self.registry.create_entity();
-                let map_scale = 2.0;
-                self.registry
-                    .add_component(
-                        background_tile,
-                        components_systems::RigidBodyComponent {
-                            position: glam::Vec2::new(
-                                32.0 * map_scale * col as f32,
-                                32.0 * map_scale * row as f32,
-                            ),
-                            velocity: glam::Vec2::new(0.0, 0.0),
-                        },
-                    )
-                    .unwrap();
-                self.registry
-                    .add_component(
-                        background_tile,
-                        components_systems::SpriteComponent {
-                            sprite_index: self.renderer.load_sprite(sprite),
-                            sprite_layer: components_systems::Layer::Background,
-                            size: glam::Vec2::new(32.0 * map_scale, 32.0 * map_scale),
-                        },
-                    )
-                    .unwrap();
+                let tile =
+                    Tile::decode(tile.trim().parse::<u32>().expect("can't parse tile index"));
+                let sprite = match tile_variants
+                    .choose(tile.index, self.registry.get_resource_mut::<Rng>().unwrap())
+                {
+                    Some(sprite) => sprite.clone(),
+                    None => Sprite::new(
+                        "assets/tilemaps/jungle.png".into(),
+                        glam::UVec2::new(32 * (tile.index % 10), 32 * (tile.index / 10)),
+                        glam::UVec2::new(32, 32),
+                    ),
+                };
+                tiles.push((row, col, tile));
+                sprites.push(sprite);
             }
         }
+        // Only the variants that were chosen are loaded.
+        let sprite_indexes = self.renderer.preload(&sprites);
+        for ((row, col, tile), sprite_index) in tiles.into_iter().zip(sprite_indexes) {
+            let map_scale = 2.0;
+            let mut sprite_component = components_systems::SpriteComponent::new(
+                sprite_index,
+                components_systems::Layer::Background,
+                glam::Vec2::new(32.0 * map_scale, 32.0 * map_scale),
+            );
+            sprite_component.flip_x = tile.orientation.flip_x;
+            sprite_component.flip_y = tile.orientation.flip_y;
+            sprite_component.rotation = tile.orientation.rotation;
+            self.registry
+                .entity_builder()
+                .with(components_systems::RigidBodyComponent::at(glam::Vec2::new(
+                    32.0 * map_scale * col as f32,
+                    32.0 * map_scale * row as f32,
+                )))
+                .with(sprite_component)
+                .build();
+        }
     }
 
     fn configure_surface(&self) {
@@ -301,35 +382,66 @@ impl Game {
     }
 
     fn render(&mut self, delta_t: f32) {
-        self.registry
-            .run_system::<components_systems::KeyboardControlSystem>(&self.pressed_keys)
-            .unwrap();
-        self.registry
-            .run_system::<components_systems::MovementSystem>(delta_t)
-            .unwrap();
-        self.registry
-            .run_system::<components_systems::CollisionSystem>(&mut self.renderer)
-            .unwrap();
-        self.registry
-            .run_system::<components_systems::AnimationSystem>(delta_t)
-            .unwrap();
-        self.registry
-            .run_system::<components_systems::MotionAnimationSystem>(delta_t)
+        self.renderer.poll_shader_reload();
+        #[cfg(feature = "audio")]
+        self.audio_handler.borrow_mut().new_frame();
+        self.registry.drain_events();
+        #[cfg(feature = "gamepad")]
+        if let Some(gamepads) = &mut self.gamepads {
+            self.registry.insert_resource(gamepads.poll());
+        }
+        for _ in 0..self.timestep.advance(delta_t) {
+            components_systems::run_fixed_update_systems(
+                &mut self.registry,
+                &self.pressed_keys,
+                self.timestep.step(),
+            )
             .unwrap();
+        }
+        components_systems::run_frame_update_systems(
+            &mut self.registry,
+            delta_t,
+            Some(&mut self.renderer),
+        )
+        .unwrap();
         self.registry
             .run_system::<components_systems::CameraFocusSystem>(&mut self.renderer)
             .unwrap();
+        self.renderer.begin_world();
         self.registry
             .run_system::<components_systems::RenderSystem>(&mut self.renderer)
             .unwrap();
+        self.registry
+            .run_system::<components_systems::UiRenderSystem>(&mut self.renderer)
+            .unwrap();
+        if self.paused {
+            self.renderer.begin_ui();
+            self.renderer.draw_rectangle(
+                glam::Vec2::ZERO,
+                self.renderer.canvas_size(),
+                glam::Vec4::new(0.0, 0.0, 0.0, 0.5),
+                renderer::RectangleStyle::Filled,
+            );
+        }
         self.renderer.draw();
     }
 
+    /// Take whatever the systems asked the main loop to do this frame.
+    fn take_control_request(&mut self) -> Option<ControlRequest> {
+        self.registry
+            .get_resource_mut::<ControlFlow>()
+            .and_then(|control_flow| control_flow.take_request())
+    }
+
     fn key_event(&mut self, key_event: winit::event::RawKeyEvent) {
         match key_event.state {
             winit::event::ElementState::Pressed => {
                 let new_keypress = self.pressed_keys.insert(key_event.physical_key);
-                if new_keypress {
+                if new_keypress && key_event.physical_key == PAUSE_KEY {
+                    self.paused = !self.paused;
+                    components_systems::set_simulation_paused(&mut self.registry, self.paused)
+                        .unwrap();
+                } else if new_keypress {
                     self.registry.dispatch_event(key_event.physical_key);
                 }
             }
@@ -338,6 +450,36 @@ impl Game {
             }
         }
     }
+
+    /// Record where the cursor is, in canvas pixels, or `None` if it left the window.
+    fn cursor_moved(&mut self, window_position: Option<winit::dpi::PhysicalPosition<f64>>) {
+        let position = window_position.map(|window_position| {
+            self.renderer.window_to_canvas(glam::Vec2::new(
+                window_position.x as f32,
+                window_position.y as f32,
+            ))
+        });
+        self.registry
+            .get_resource_mut::<MouseState>()
+            .unwrap()
+            .position = position;
+    }
+
+    fn mouse_input(
+        &mut self,
+        button: winit::event::MouseButton,
+        state: winit::event::ElementState,
+    ) {
+        let mouse_state = self.registry.get_resource_mut::<MouseState>().unwrap();
+        match state {
+            winit::event::ElementState::Pressed => {
+                mouse_state.pressed_buttons.insert(button);
+            }
+            winit::event::ElementState::Released => {
+                mouse_state.pressed_buttons.remove(&button);
+            }
+        }
+    }
 }
 
 fn main() {
@@ -352,7 +494,9 @@ fn main() {
     let mut last_render_time = start_time;
     let mut frame_render_seconds: f32 = 0.0;
     let mut last_fps_log_time = start_time;
+    let mut last_fps_title_time = start_time;
     let mut render_time_stats = FPSStats::new(1.0);
+    let smooth_delta = smooth_delta();
     event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
     event_loop
         .run(move |event, event_loop_window_target| match event {
@@ -399,6 +543,22 @@ fn main() {
                         state,
                     });
                 }
+                winit::event::WindowEvent::CursorMoved {
+                    device_id: _,
+                    position,
+                } => {
+                    game.cursor_moved(Some(position));
+                }
+                winit::event::WindowEvent::CursorLeft { device_id: _ } => {
+                    game.cursor_moved(None);
+                }
+                winit::event::WindowEvent::MouseInput {
+                    device_id: _,
+                    state,
+                    button,
+                } => {
+                    game.mouse_input(button, state);
+                }
                 winit::event::WindowEvent::Resized(_) => {
                     game.configure_surface();
                 }
@@ -406,25 +566,54 @@ fn main() {
             },
             winit::event::Event::DeviceEvent {
                 device_id: _,
-                event: device_event,
-            } => match device_event {
-                winit::event::DeviceEvent::Key(raw_key_event) => {
-                    game.key_event(raw_key_event);
-                }
-                _ => {}
-            },
+                event: winit::event::DeviceEvent::Key(raw_key_event),
+            } => {
+                game.key_event(raw_key_event);
+            }
             winit::event::Event::AboutToWait => {
-                game.render(frame_render_seconds);
+                let delta_t = if smooth_delta {
+                    render_time_stats.mean()
+                } else {
+                    frame_render_seconds
+                };
+                game.render(clamp_delta(delta_t, MAX_DELTA_SECONDS));
+                match game.take_control_request() {
+                    Some(ControlRequest::Exit) => {
+                        event_loop_window_target.exit();
+                    }
+                    Some(ControlRequest::SceneChange(scene)) => {
+                        // TODO: There is only one scene so far.
+                        log::warn!("Scene change requested, but there are no scenes: {}", scene);
+                    }
+                    None => {}
+                }
                 let now = std::time::Instant::now();
                 frame_render_seconds = (now - last_render_time).as_secs_f32();
                 render_time_stats.update(frame_render_seconds);
                 last_render_time = now;
+                if game.show_fps_in_title
+                    && now - last_fps_title_time > std::time::Duration::from_secs(1)
+                {
+                    last_fps_title_time = now;
+                    game.renderer.set_window_title(&fps_title(
+                        "pikuma",
+                        1.0 / render_time_stats.mean(),
+                        1.0 / render_time_stats.percentile_99(),
+                    ));
+                }
                 if now - last_fps_log_time > std::time::Duration::from_secs(10) {
                     last_fps_log_time = now;
                     let fps = 1.0 / render_time_stats.mean();
                     let fps_std = render_time_stats.std() / render_time_stats.mean().powi(2);
                     let fps_99th = 1.0 / render_time_stats.percentile_99();
                     log::info!("FPS: {:.0} ({:.0} ± {:.0})", fps_99th, fps, fps_std);
+                    if let Some((system, timing)) = game
+                        .registry
+                        .get_resource::<SystemTimings>()
+                        .and_then(|system_timings| system_timings.slowest())
+                    {
+                        log::debug!("Slowest system: {} ({:.6}s)", system, timing.mean);
+                    }
                 }
             }
             _ => {}
@@ -0,0 +1,22 @@
+use std::collections::HashSet;
+
+use winit::event::MouseButton;
+
+/// The mouse, stored as a registry resource and kept up to date by the main loop.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MouseState {
+    /// The cursor in canvas pixels, the same coordinates UI is drawn in.
+    /// `None` while the cursor is outside the window.
+    pub position: Option<glam::Vec2>,
+    pub pressed_buttons: HashSet<MouseButton>,
+}
+
+impl MouseState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_pressed(&self, button: MouseButton) -> bool {
+        self.pressed_buttons.contains(&button)
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+/// How much a new sample moves the mean.
+const MEAN_SMOOTHING: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SystemTiming {
+    /// Seconds the system took the last time it ran.
+    pub last: f32,
+    /// Exponentially smoothed mean of seconds the system takes to run.
+    pub mean: f32,
+}
+
+/// How long each system takes to run.
+/// Insert this as a registry resource, and `run_system` will record into it.
+#[derive(Default)]
+pub struct SystemTimings {
+    timings: HashMap<&'static str, SystemTiming>,
+}
+
+impl SystemTimings {
+    pub fn new() -> Self {
+        Self {
+            timings: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, system: &'static str, seconds: f32) {
+        self.timings
+            .entry(system)
+            .and_modify(|timing| {
+                timing.last = seconds;
+                timing.mean += (seconds - timing.mean) * MEAN_SMOOTHING;
+            })
+            .or_insert(SystemTiming {
+                last: seconds,
+                mean: seconds,
+            });
+    }
+
+    pub fn get(&self, system: &str) -> Option<SystemTiming> {
+        self.timings.get(system).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, SystemTiming)> + '_ {
+        self.timings
+            .iter()
+            .map(|(system, timing)| (*system, *timing))
+    }
+
+    /// The system with the largest mean run time.
+    pub fn slowest(&self) -> Option<(&'static str, SystemTiming)> {
+        self.iter()
+            .max_by(|(_, a), (_, b)| a.mean.total_cmp(&b.mean))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SystemTimings;
+
+    #[test]
+    fn test_system_timings() {
+        let mut timings = SystemTimings::new();
+        assert_eq!(timings.slowest(), None);
+        timings.record("fast", 1.0);
+        timings.record("slow", 5.0);
+        timings.record("fast", 2.0);
+        let fast = timings.get("fast").unwrap();
+        assert_eq!(fast.last, 2.0);
+        assert!(fast.mean > 1.0 && fast.mean < 2.0);
+        assert_eq!(timings.slowest().unwrap().0, "slow");
+        assert_eq!(timings.get("missing"), None);
+    }
+
+    #[test]
+    fn test_slowest_with_nan() {
+        let mut timings = SystemTimings::new();
+        timings.record("broken", f32::NAN);
+        timings.record("slow", 5.0);
+        assert!(timings.slowest().is_some());
+    }
+}
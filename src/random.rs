@@ -0,0 +1,50 @@
+use rand::SeedableRng as _;
+
+/// A seeded random number generator.
+/// Store one as a registry resource and draw all randomness from it,
+/// so that a fixed seed reproduces a whole session.
+pub struct Rng {
+    rng: rand::rngs::SmallRng,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: rand::rngs::SmallRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl rand::RngCore for Rng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rng;
+    use rand::Rng as _;
+
+    #[test]
+    fn test_same_seed_same_numbers() {
+        let numbers = |seed: u64| -> Vec<u32> {
+            let mut rng = Rng::new(seed);
+            (0..10).map(|_| rng.gen_range(0..1000)).collect()
+        };
+        assert_eq!(numbers(3), numbers(3));
+        assert_ne!(numbers(3), numbers(4));
+    }
+}
@@ -1,14 +1,41 @@
 use pollster::FutureExt as _;
+use serde::{Deserialize, Serialize};
 use wgpu::util::DeviceExt as _;
 
-#[derive(Clone, Copy)]
-pub struct SpriteIndex(u32);
+use crate::geometry::Rectangle;
+
+/// A handle to a loaded sprite.
+/// It remembers which sprite atlas it came from, so it can't be used with a different renderer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpriteIndex {
+    index: u32,
+    atlas_id: u32,
+}
+
+impl SpriteIndex {
+    /// A sprite index that isn't from any renderer, for tests that never draw it.
+    #[doc(hidden)]
+    pub fn for_tests(index: u32) -> Self {
+        Self {
+            index,
+            atlas_id: u32::MAX,
+        }
+    }
+}
+
+/// Every sprite atlas gets a unique id, so stale sprite indices can be detected.
+fn next_atlas_id() -> u32 {
+    static NEXT_ATLAS_ID: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+    NEXT_ATLAS_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Sprite {
     file: std::path::PathBuf,
     top_left: glam::UVec2,
     width_height: glam::UVec2,
+    /// Pixels of the first color are replaced with the second color when the sprite is loaded.
+    palette_swap: Vec<(image::Rgba<u8>, image::Rgba<u8>)>,
 }
 
 impl Sprite {
@@ -17,15 +44,164 @@ impl Sprite {
             file,
             top_left,
             width_height,
+            palette_swap: Vec::new(),
+        }
+    }
+
+    /// Recolor the sprite when it's loaded, replacing each source color with its target color.
+    /// A palette swapped sprite is loaded separately from the original sprite.
+    pub fn with_palette_swap(
+        mut self,
+        palette_swap: Vec<(image::Rgba<u8>, image::Rgba<u8>)>,
+    ) -> Self {
+        self.palette_swap = palette_swap;
+        self
+    }
+
+    pub fn width_height(&self) -> glam::UVec2 {
+        self.width_height
+    }
+
+    fn apply_palette_swap(&self, image: &mut image::RgbaImage) {
+        if self.palette_swap.is_empty() {
+            return;
+        }
+        for pixel in image.pixels_mut() {
+            if let Some((_source, target)) = self
+                .palette_swap
+                .iter()
+                .find(|(source, _target)| source == pixel)
+            {
+                *pixel = *target;
+            }
         }
     }
 }
 
-#[repr(C)]
-#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+/// Clockwise rotation in quarter turns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Rotation {
+    #[default]
+    None,
+    Clockwise90,
+    Clockwise180,
+    Clockwise270,
+}
+
+/// How a sprite is rotated and flipped when drawn.
+/// Rotation is applied first, then flipping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SpriteOrientation {
+    pub flip_x: bool,
+    pub flip_y: bool,
+    pub rotation: Rotation,
+}
+
+impl SpriteOrientation {
+    /// Maps a uv coordinate of the drawn quad to the uv coordinate sampled from the sprite.
+    fn sample_uv(&self, uv: glam::Vec2) -> glam::Vec2 {
+        // Work relative to the center of the sprite, so flips and rotations are about the center.
+        let mut centered = uv - glam::Vec2::new(0.5, 0.5);
+        // Undo the flips, then undo the rotation.
+        if self.flip_x {
+            centered.x = -centered.x;
+        }
+        if self.flip_y {
+            centered.y = -centered.y;
+        }
+        let centered = match self.rotation {
+            Rotation::None => centered,
+            Rotation::Clockwise90 => glam::Vec2::new(centered.y, -centered.x),
+            Rotation::Clockwise180 => -centered,
+            Rotation::Clockwise270 => glam::Vec2::new(-centered.y, centered.x),
+        };
+        centered + glam::Vec2::new(0.5, 0.5)
+    }
+}
+
+/// How the colors in sprite files are interpreted.
+/// Sprite files of any color type (e.g., grayscale masks) are converted to RGBA when loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpriteColorSpace {
+    /// Sprites hold sRGB colors, which are converted to linear when sampled.
+    #[default]
+    Srgb,
+    /// Sprites hold linear data, which is sampled as is. Useful for non-color data used by effects.
+    Linear,
+}
+
+impl SpriteColorSpace {
+    fn texture_format(&self) -> wgpu::TextureFormat {
+        match self {
+            SpriteColorSpace::Srgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+            SpriteColorSpace::Linear => wgpu::TextureFormat::Rgba8Unorm,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Camera {
     pub top_left: glam::Vec2,
     pub width_height: glam::Vec2,
+    /// Scales the view around the center of the camera.
+    /// Greater than 1 zooms in, showing less of the world, and 1 shows exactly `width_height`.
+    pub zoom: f32,
+}
+
+impl Camera {
+    /// The part of the world the camera can see.
+    pub fn bounds(&self) -> Rectangle {
+        let center = self.top_left + self.width_height / 2.0;
+        Rectangle::from_center(center, self.width_height / self.zoom)
+    }
+}
+
+/// The camera as the shader sees it. Uniforms are padded to a multiple of 8 bytes.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct CameraUniform {
+    top_left: glam::Vec2,
+    width_height: glam::Vec2,
+    zoom: f32,
+    _padding: f32,
+}
+
+impl From<Camera> for CameraUniform {
+    fn from(camera: Camera) -> Self {
+        Self {
+            top_left: camera.top_left,
+            width_height: camera.width_height,
+            zoom: camera.zoom,
+            _padding: 0.0,
+        }
+    }
+}
+
+/// How sprite positions are rounded to canvas pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PixelRounding {
+    /// Don't round. Sprites at fractional positions may shimmer as they move.
+    None,
+    /// Round to the nearest canvas pixel.
+    #[default]
+    Nearest,
+    /// Round down to the canvas pixel to the top left.
+    Floor,
+}
+
+impl PixelRounding {
+    /// Round a world position so it lands on a canvas pixel.
+    fn round(&self, position: glam::Vec2, camera: &Camera, canvas_size: glam::Vec2) -> glam::Vec2 {
+        let bounds = camera.bounds();
+        let world_to_canvas = canvas_size / bounds.width_height();
+        let canvas_position = (position - bounds.top_left) * world_to_canvas;
+        let canvas_position = match self {
+            PixelRounding::None => return position,
+            PixelRounding::Nearest => canvas_position.round(),
+            PixelRounding::Floor => canvas_position.floor(),
+        };
+        canvas_position / world_to_canvas + bounds.top_left
+    }
 }
 
 #[repr(C)]
@@ -54,6 +230,11 @@ struct TextureVertex {
     position: glam::Vec3,
     uv: glam::Vec2,
     lower_right: glam::UVec3,
+    /// Pixels less opaque than this are discarded, and the rest are drawn fully opaque.
+    /// Zero means the sprite is alpha blended instead.
+    alpha_cutoff: f32,
+    /// Multiplies the sprite's color.
+    tint: [f32; 4],
 }
 
 const TEXTURE_VERTEX_ATTRIBUTES: &[wgpu::VertexAttribute] = &[
@@ -72,10 +253,100 @@ const TEXTURE_VERTEX_ATTRIBUTES: &[wgpu::VertexAttribute] = &[
         offset: 20,
         shader_location: 2,
     },
+    wgpu::VertexAttribute {
+        format: wgpu::VertexFormat::Float32, // alpha_cutoff size = 4
+        offset: 32,
+        shader_location: 3,
+    },
+    wgpu::VertexAttribute {
+        format: wgpu::VertexFormat::Float32x4, // tint size = 4 * 4 = 16
+        offset: 36,
+        shader_location: 4,
+    },
+];
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct ColorVertex {
+    position: glam::Vec3,
+    color: [f32; 4],
+}
+
+const COLOR_VERTEX_ATTRIBUTES: &[wgpu::VertexAttribute] = &[
+    wgpu::VertexAttribute {
+        format: wgpu::VertexFormat::Float32x3, // position size = 4 * 3 = 12
+        offset: 0,
+        shader_location: 0,
+    },
+    wgpu::VertexAttribute {
+        format: wgpu::VertexFormat::Float32x4, // color size = 4 * 4 = 16
+        offset: 12,
+        shader_location: 1,
+    },
 ];
 
+/// How a sprite's transparent pixels are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AlphaMode {
+    /// Blend partially transparent pixels with what's already been drawn behind them.
+    /// Fully transparent pixels are discarded, so they never hide anything,
+    /// but partially transparent sprites should be drawn after whatever is behind them.
+    #[default]
+    Blend,
+    /// Discard mostly transparent pixels and draw the rest fully opaque.
+    /// Suited to pixel art, which is usually either fully opaque or fully transparent.
+    Cutout,
+}
+
+impl AlphaMode {
+    fn alpha_cutoff(&self) -> f32 {
+        match self {
+            AlphaMode::Blend => 0.0,
+            AlphaMode::Cutout => 0.5,
+        }
+    }
+}
+
+/// A sprite to draw with `Renderer::draw_image`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawImage {
+    pub sprite_index: SpriteIndex,
+    /// Sprites with a higher z, from 0 to 1, are drawn on top, whatever order they're drawn in.
+    pub z: f32,
+    /// The top left corner.
+    pub location: glam::Vec2,
+    pub size: glam::Vec2,
+    pub orientation: SpriteOrientation,
+    pub alpha_mode: AlphaMode,
+    /// The sprite's colors are multiplied by this; white leaves them unchanged.
+    pub tint: glam::Vec4,
+}
+
+impl DrawImage {
+    /// The sprite at z 0, neither rotated nor flipped, blended, and untinted.
+    pub fn new(sprite_index: SpriteIndex, location: glam::Vec2, size: glam::Vec2) -> Self {
+        Self {
+            sprite_index,
+            z: 0.0,
+            location,
+            size,
+            orientation: SpriteOrientation::default(),
+            alpha_mode: AlphaMode::default(),
+            tint: glam::Vec4::ONE,
+        }
+    }
+}
+
+/// How `draw_rectangle` draws a rectangle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RectangleStyle {
+    Filled,
+    /// An outline this many canvas pixels thick, drawn inside the rectangle.
+    Outline(f32),
+}
+
 const SQUARE_VERTS: u32 = 6;
-const SQUARE_OUTLINE_VERTS: u32 = 8;
+const SQUARE_OUTLINE_VERTS: u32 = SQUARE_VERTS * 4;
 
 /// Normalized device coordinates (NDC)
 fn ndc_square() -> [Vertex; SQUARE_VERTS as usize] {
@@ -98,74 +369,141 @@ fn ndc_square() -> [Vertex; SQUARE_VERTS as usize] {
     [v0, v1, v2, v2, v3, v0]
 }
 
-fn square(
-    position: glam::Vec2,
-    z: f32,
-    texture_size: glam::UVec2,
-    texture_index: u32,
-    quad_size: glam::Vec2,
-) -> [TextureVertex; SQUARE_VERTS as usize] {
-    let lower_right = glam::UVec3::new(texture_size.x, texture_size.y, texture_index);
+/// Sprites with a higher z are drawn on top. The depth buffer wants the opposite,
+/// so z in the range 0 to 1 becomes a depth in the range 1 to 0.
+fn sprite_depth(z: f32) -> f32 {
+    1.0 - z.clamp(0.0, 1.0)
+}
+
+/// The quad for the image, drawn exactly at its location, sampling a sprite of `texture_size`.
+fn square(image: DrawImage, texture_size: glam::UVec2) -> [TextureVertex; SQUARE_VERTS as usize] {
+    let DrawImage {
+        sprite_index,
+        z,
+        location: position,
+        size: quad_size,
+        orientation,
+        alpha_mode,
+        tint,
+    } = image;
+    let lower_right = glam::UVec3::new(texture_size.x, texture_size.y, sprite_index.index);
+    let alpha_cutoff = alpha_mode.alpha_cutoff();
+    let tint = tint.to_array();
+    let z = sprite_depth(z);
     let v0 = TextureVertex {
         position: glam::Vec3::new(position.x, position.y, z),
-        uv: glam::Vec2::new(0.0, 0.0),
+        uv: orientation.sample_uv(glam::Vec2::new(0.0, 0.0)),
         lower_right,
+        alpha_cutoff,
+        tint,
     };
     let v1 = TextureVertex {
         position: glam::Vec3::new(position.x, position.y + quad_size.y, z),
-        uv: glam::Vec2::new(0.0, 1.0),
+        uv: orientation.sample_uv(glam::Vec2::new(0.0, 1.0)),
         lower_right,
+        alpha_cutoff,
+        tint,
     };
     let v2 = TextureVertex {
         position: glam::Vec3::new(position.x + quad_size.x, position.y + quad_size.y, z),
-        uv: glam::Vec2::new(1.0, 1.0),
+        uv: orientation.sample_uv(glam::Vec2::new(1.0, 1.0)),
         lower_right,
+        alpha_cutoff,
+        tint,
     };
     let v3 = TextureVertex {
         position: glam::Vec3::new(position.x + quad_size.x, position.y, z),
-        uv: glam::Vec2::new(1.0, 0.0),
+        uv: orientation.sample_uv(glam::Vec2::new(1.0, 0.0)),
         lower_right,
+        alpha_cutoff,
+        tint,
     };
     [v0, v1, v2, v2, v3, v0]
 }
 
-fn square_outline(
+fn color_square(
     position: glam::Vec2,
-    width_height: glam::Vec2,
-) -> [TextureVertex; SQUARE_OUTLINE_VERTS as usize] {
-    let lower_right = glam::UVec3::new(
-        width_height.x.max(0.0) as u32,
-        width_height.y.max(0.0) as u32,
-        0,
-    );
-    let v0 = TextureVertex {
+    quad_size: glam::Vec2,
+    color: glam::Vec4,
+) -> [ColorVertex; SQUARE_VERTS as usize] {
+    let color = color.to_array();
+    let v0 = ColorVertex {
         position: glam::Vec3::new(position.x, position.y, 0.0),
-        uv: glam::Vec2::new(0.0, 0.0),
-        lower_right,
+        color,
     };
-    let v1 = TextureVertex {
-        position: glam::Vec3::new(position.x, position.y + width_height.y, 0.0),
-        uv: glam::Vec2::new(0.0, 1.0),
-        lower_right,
+    let v1 = ColorVertex {
+        position: glam::Vec3::new(position.x, position.y + quad_size.y, 0.0),
+        color,
     };
-    let v2 = TextureVertex {
-        position: glam::Vec3::new(
-            position.x + width_height.x,
-            position.y + width_height.y,
-            0.0,
-        ),
-        uv: glam::Vec2::new(1.0, 1.0),
-        lower_right,
+    let v2 = ColorVertex {
+        position: glam::Vec3::new(position.x + quad_size.x, position.y + quad_size.y, 0.0),
+        color,
     };
-    let v3 = TextureVertex {
-        position: glam::Vec3::new(position.x + width_height.x, position.y, 0.0),
-        uv: glam::Vec2::new(1.0, 0.0),
-        lower_right,
+    let v3 = ColorVertex {
+        position: glam::Vec3::new(position.x + quad_size.x, position.y, 0.0),
+        color,
+    };
+    [v0, v1, v2, v2, v3, v0]
+}
+
+/// An outline made of four quads, one for each edge.
+/// The outline is drawn inside the given rectangle.
+fn square_outline(
+    position: glam::Vec2,
+    width_height: glam::Vec2,
+    thickness: f32,
+    color: glam::Vec4,
+) -> [ColorVertex; SQUARE_OUTLINE_VERTS as usize] {
+    let width_height = width_height.max(glam::Vec2::ZERO);
+    let thickness = thickness
+        .min(width_height.x / 2.0)
+        .min(width_height.y / 2.0)
+        .max(0.0);
+    let top = color_square(position, glam::Vec2::new(width_height.x, thickness), color);
+    let bottom = color_square(
+        position + glam::Vec2::new(0.0, width_height.y - thickness),
+        glam::Vec2::new(width_height.x, thickness),
+        color,
+    );
+    let left = color_square(
+        position + glam::Vec2::new(0.0, thickness),
+        glam::Vec2::new(thickness, width_height.y - 2.0 * thickness),
+        color,
+    );
+    let right = color_square(
+        position + glam::Vec2::new(width_height.x - thickness, thickness),
+        glam::Vec2::new(thickness, width_height.y - 2.0 * thickness),
+        color,
+    );
+    let mut vertices = [top[0]; SQUARE_OUTLINE_VERTS as usize];
+    for (i, vertex) in [top, bottom, left, right].iter().flatten().enumerate() {
+        vertices[i] = *vertex;
+    }
+    vertices
+}
+
+/// A line from `a` to `b`, drawn as a quad centered on the line.
+fn line_quad(
+    a: glam::Vec2,
+    b: glam::Vec2,
+    thickness: f32,
+    color: glam::Vec4,
+) -> [ColorVertex; SQUARE_VERTS as usize] {
+    let direction = (b - a).normalize_or_zero();
+    let offset = direction.perp() * thickness.max(0.0) / 2.0;
+    let color = color.to_array();
+    let vertex = |position: glam::Vec2| ColorVertex {
+        position: position.extend(0.0),
+        color,
     };
-    [v0, v1, v1, v2, v2, v3, v3, v0]
+    let v0 = vertex(a + offset);
+    let v1 = vertex(a - offset);
+    let v2 = vertex(b - offset);
+    let v3 = vertex(b + offset);
+    [v0, v1, v2, v2, v3, v0]
 }
 
-/// Counter-clockwise rotation matrix
+// Counter-clockwise rotation matrix
 // fn rotate_cc(angle_degrees: f32) -> glam::Mat2 {
 //     let angle_radians = angle_degrees.to_radians();
 //     glam::Mat2::from_cols_array(&[
@@ -176,25 +514,257 @@ fn square_outline(
 //     ])
 // }
 
+/// Whether things are drawn with the camera, or pinned to the canvas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DrawSpace {
+    World,
+    Ui,
+}
+
+/// A run of sprites and shapes drawn in the same space.
+/// Batches are drawn in order, so a later batch is drawn on top of an earlier one.
+struct DrawBatch {
+    space: DrawSpace,
+    /// The first sprite in the batch.
+    sprite_start: u32,
+    /// The first shape vertex in the batch.
+    shape_vertex_start: u32,
+}
+
+/// A region of the canvas, in canvas pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    pub top_left: glam::UVec2,
+    pub size: glam::UVec2,
+}
+
+/// How the canvas is divided between cameras.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SplitScreen {
+    /// Cameras are arranged left to right.
+    #[default]
+    SideBySide,
+    /// Cameras are arranged top to bottom.
+    Stacked,
+}
+
+impl SplitScreen {
+    /// The viewport of the camera at `index`, out of `count` cameras.
+    /// The canvas is divided evenly; viewports differ in size by at most a pixel.
+    pub fn viewport(&self, canvas_size: glam::UVec2, count: usize, index: usize) -> Viewport {
+        let count = count.max(1) as u32;
+        let index = index as u32;
+        let length = match self {
+            SplitScreen::SideBySide => canvas_size.x,
+            SplitScreen::Stacked => canvas_size.y,
+        };
+        let start = length * index / count;
+        let end = length * (index + 1) / count;
+        match self {
+            SplitScreen::SideBySide => Viewport {
+                top_left: glam::UVec2::new(start, 0),
+                size: glam::UVec2::new(end - start, canvas_size.y),
+            },
+            SplitScreen::Stacked => Viewport {
+                top_left: glam::UVec2::new(0, start),
+                size: glam::UVec2::new(canvas_size.x, end - start),
+            },
+        }
+    }
+
+    pub fn viewports(&self, canvas_size: glam::UVec2, count: usize) -> Vec<Viewport> {
+        (0..count)
+            .map(|index| self.viewport(canvas_size, count, index))
+            .collect()
+    }
+}
+
+/// A camera, with its own uniform buffer and bind groups, so several cameras can share a pass.
+struct CameraView {
+    camera: Camera,
+    camera_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    shape_bind_group: wgpu::BindGroup,
+}
+
+impl CameraView {
+    fn new(
+        device: &wgpu::Device,
+        camera: Camera,
+        pipeline: &wgpu::RenderPipeline,
+        shape_pipeline: &wgpu::RenderPipeline,
+        sampler: &wgpu::Sampler,
+        sprites: &wgpu::Texture,
+    ) -> Self {
+        let camera_buffer =
+            LowResPass::create_camera_buffer(device, "low res camera buffer", camera);
+        let bind_group = LowResPass::create_bind_group(
+            device,
+            pipeline,
+            "low res bind group",
+            &camera_buffer,
+            sampler,
+            sprites,
+        );
+        let shape_bind_group = LowResPass::create_shape_bind_group(
+            device,
+            shape_pipeline,
+            "low res shape bind group",
+            &camera_buffer,
+        );
+        Self {
+            camera,
+            camera_buffer,
+            bind_group,
+            shape_bind_group,
+        }
+    }
+}
+
+/// One draw of a batch, into one viewport.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DrawCall {
+    viewport: Viewport,
+    /// Index into the cameras, or `None` for the UI camera.
+    camera: Option<usize>,
+    sprites: std::ops::Range<u32>,
+    shape_vertices: std::ops::Range<u32>,
+}
+
+/// Batches in the world are drawn once per camera, each into its own viewport,
+/// while UI batches are drawn once over the whole canvas.
+/// Draw order follows batch order, so later batches are still on top.
+fn draw_calls(
+    batches: &[DrawBatch],
+    sprite_count: u32,
+    shape_vertex_count: u32,
+    viewports: &[Viewport],
+    canvas_size: glam::UVec2,
+) -> Vec<DrawCall> {
+    let mut draw_calls = Vec::new();
+    for (i, batch) in batches.iter().enumerate() {
+        let (sprite_end, shape_vertex_end) = match batches.get(i + 1) {
+            Some(next_batch) => (next_batch.sprite_start, next_batch.shape_vertex_start),
+            None => (sprite_count, shape_vertex_count),
+        };
+        let sprites = batch.sprite_start..sprite_end;
+        let shape_vertices = batch.shape_vertex_start..shape_vertex_end;
+        match batch.space {
+            DrawSpace::World => {
+                for (camera, viewport) in viewports.iter().enumerate() {
+                    draw_calls.push(DrawCall {
+                        viewport: *viewport,
+                        camera: Some(camera),
+                        sprites: sprites.clone(),
+                        shape_vertices: shape_vertices.clone(),
+                    });
+                }
+            }
+            DrawSpace::Ui => draw_calls.push(DrawCall {
+                viewport: Viewport {
+                    top_left: glam::UVec2::ZERO,
+                    size: canvas_size,
+                },
+                camera: None,
+                sprites,
+                shape_vertices,
+            }),
+        }
+    }
+    draw_calls
+}
+
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// The sprites texture starts out with layers this size, and grows to fit bigger sprites.
+const INITIAL_SPRITES_SIZE: glam::UVec2 = glam::UVec2::new(32, 32);
+const MAX_SPRITES: u32 = 256;
+
+/// If a sprite doesn't fit in the sprites texture's layers, the size to grow the layers to.
+fn grown_sprites_size(current_size: glam::UVec2, sprite_size: glam::UVec2) -> Option<glam::UVec2> {
+    (sprite_size.x > current_size.x || sprite_size.y > current_size.y)
+        .then(|| current_size.max(sprite_size))
+}
+
+/// Vertex buffers start out this many bytes, and grow when a frame needs more.
+const INITIAL_VERTEX_BUFFER_SIZE: u64 = 100_000;
+const VERTEX_BUFFER_LABEL: &str = "low res vertex buffer";
+const SHAPE_VERTEX_BUFFER_LABEL: &str = "low res shape vertex buffer";
+
+fn create_vertex_buffer(device: &wgpu::Device, label: &str, size: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// If a buffer is too small for the required bytes, the size to grow it to.
+/// Growing to a power of two means a steadily growing scene only reallocates a few times.
+fn grown_buffer_size(current_size: u64, required_size: u64) -> Option<u64> {
+    (required_size > current_size).then(|| required_size.next_power_of_two())
+}
+
+/// Replace the buffer with a bigger one if it can't hold the required bytes.
+/// The old contents are not copied, so this must happen before the buffer is written.
+fn grow_vertex_buffer(
+    device: &wgpu::Device,
+    buffer: &mut wgpu::Buffer,
+    label: &str,
+    required_size: usize,
+) {
+    if let Some(new_size) = grown_buffer_size(buffer.size(), required_size as u64) {
+        log::debug!(
+            "Growing {} from {} to {} bytes",
+            label,
+            buffer.size(),
+            new_size
+        );
+        *buffer = create_vertex_buffer(device, label, new_size);
+    }
+}
+
+const DEFAULT_CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.1,
+    g: 0.15,
+    b: 0.1,
+    a: 1.0,
+};
+
 struct LowResPass {
     low_res_texture: wgpu::Texture,
     low_res_texture_view: wgpu::TextureView,
-    camera: Camera,
-    camera_buffer: wgpu::Buffer,
+    /// Lets the GPU sort sprites by z, so they can be drawn in any order.
+    depth_texture_view: wgpu::TextureView,
+    /// There's always at least one camera. Each draws the world into its own viewport.
+    cameras: Vec<CameraView>,
+    split_screen: SplitScreen,
+    ui_camera_buffer: wgpu::Buffer,
+    pixel_rounding: PixelRounding,
+    /// The background, wherever nothing is drawn.
+    clear_color: wgpu::Color,
+    batches: Vec<DrawBatch>,
     // Sprite drawing
     pipeline: wgpu::RenderPipeline,
-    bind_group: wgpu::BindGroup,
+    ui_bind_group: wgpu::BindGroup,
     vertex_buffer_cpu: Vec<u8>,
     vertex_buffer: wgpu::Buffer,
     vertex_buffer_vert_count: u32,
-    // Line drawing
-    line_pipeline: wgpu::RenderPipeline,
-    line_bind_group: wgpu::BindGroup,
-    line_vertex_buffer_cpu: Vec<u8>,
-    line_vertex_buffer: wgpu::Buffer,
-    line_vertex_buffer_vert_count: u32,
+    // Colored shape drawing
+    shape_pipeline: wgpu::RenderPipeline,
+    ui_shape_bind_group: wgpu::BindGroup,
+    shape_vertex_buffer_cpu: Vec<u8>,
+    shape_vertex_buffer: wgpu::Buffer,
+    shape_vertex_buffer_vert_count: u32,
     // Sprites
+    // Each sprite gets its own layer of the sprites texture array, and sprites are never unloaded,
+    // so the layers in use are always contiguous and never need to be repacked.
+    // A SpriteIndex is the sprite's layer, so it stays valid for the life of the renderer.
+    // Every layer is the size of the largest sprite; smaller sprites use the top left corner.
+    sampler: wgpu::Sampler,
     sprites: wgpu::Texture,
+    sprites_atlas_id: u32,
     loaded_sprites: Vec<Sprite>,
 }
 
@@ -204,68 +774,22 @@ impl LowResPass {
         canvas_width: u32,
         canvas_height: u32,
         preferred_format: wgpu::TextureFormat,
+        sprite_color_space: SpriteColorSpace,
+        shader_source: &str,
     ) -> Self {
-        let low_res_texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("low res texture"),
-            size: wgpu::Extent3d {
-                width: canvas_width,
-                height: canvas_height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: preferred_format,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[],
-        });
-        let low_res_texture_view =
-            low_res_texture.create_view(&wgpu::TextureViewDescriptor::default());
-        // TODO: Stop including the shader in the compiled binary. Compile them at runtime.
-        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/low_res.wgsl"));
-        let pipeline: wgpu::RenderPipeline =
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("low res pipeline"),
-                layout: None,
-                vertex: wgpu::VertexState {
-                    module: &shader,
-                    entry_point: "vertex_main",
-                    // TODO: We should use instance buffers for repeated values
-                    buffers: &[wgpu::VertexBufferLayout {
-                        array_stride: std::mem::size_of::<TextureVertex>() as u64,
-                        step_mode: wgpu::VertexStepMode::Vertex,
-                        attributes: TEXTURE_VERTEX_ATTRIBUTES,
-                    }],
-                },
-                primitive: wgpu::PrimitiveState::default(),
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState::default(),
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader,
-                    entry_point: "fragment_main",
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: preferred_format,
-                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                }),
-                multiview: None,
-            });
+        let (low_res_texture, low_res_texture_view) =
+            Self::create_low_res_texture(device, canvas_width, canvas_height, preferred_format);
+        let depth_texture_view = Self::create_depth_texture(device, canvas_width, canvas_height);
+        let (pipeline, shape_pipeline) =
+            Self::create_pipelines(device, preferred_format, shader_source);
         let camera = Camera {
             top_left: glam::Vec2::new(0.0, 0.0),
             width_height: glam::Vec2::new(canvas_width as f32, canvas_height as f32),
+            zoom: 1.0,
         };
-        let camera_buffer: wgpu::Buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("low res camera buffer"),
-            size: std::mem::size_of::<Camera>() as u64,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: true,
-        });
-        camera_buffer
-            .slice(..)
-            .get_mapped_range_mut()
-            .copy_from_slice(bytemuck::bytes_of(&camera));
-        camera_buffer.unmap();
+        // UI is drawn with a camera that exactly covers the canvas, so positions are canvas pixels.
+        let ui_camera_buffer =
+            Self::create_camera_buffer(device, "low res ui camera buffer", camera);
         let sampler: wgpu::Sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             label: Some("low res sampler"),
             address_mode_u: wgpu::AddressMode::ClampToEdge,
@@ -280,61 +804,153 @@ impl LowResPass {
             anisotropy_clamp: 1,
             border_color: None,
         });
-        let sprites: wgpu::Texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("low res sprites"),
-            size: wgpu::Extent3d {
-                width: 32,
-                height: 32,
-                depth_or_array_layers: 256,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[],
-        });
-        let sprites_view: wgpu::TextureView =
-            sprites.create_view(&wgpu::TextureViewDescriptor::default());
-        let bind_group: wgpu::BindGroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("low res bind group"),
-            layout: &pipeline.get_bind_group_layout(0),
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                        buffer: &camera_buffer,
-                        offset: 0,
-                        size: None,
-                    }),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::TextureView(&sprites_view),
-                },
-            ],
-        });
+        let sprites = Self::create_sprites_texture(
+            device,
+            sprite_color_space.texture_format(),
+            INITIAL_SPRITES_SIZE,
+        );
+        let camera_view = CameraView::new(
+            device,
+            camera,
+            &pipeline,
+            &shape_pipeline,
+            &sampler,
+            &sprites,
+        );
+        let ui_bind_group = Self::create_bind_group(
+            device,
+            &pipeline,
+            "low res ui bind group",
+            &ui_camera_buffer,
+            &sampler,
+            &sprites,
+        );
+        let ui_shape_bind_group = Self::create_shape_bind_group(
+            device,
+            &shape_pipeline,
+            "low res ui shape bind group",
+            &ui_camera_buffer,
+        );
         // TODO: Use an instance buffer as well
-        // TODO: What should we do about this hard-coded static buffer size?
-        let vertex_buffer: wgpu::Buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("low res vertex buffer"),
-            size: 100_000,
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
+        let vertex_buffer =
+            create_vertex_buffer(device, VERTEX_BUFFER_LABEL, INITIAL_VERTEX_BUFFER_SIZE);
+        let shape_vertex_buffer = create_vertex_buffer(
+            device,
+            SHAPE_VERTEX_BUFFER_LABEL,
+            INITIAL_VERTEX_BUFFER_SIZE,
+        );
+        Self {
+            low_res_texture,
+            low_res_texture_view,
+            depth_texture_view,
+            cameras: vec![camera_view],
+            split_screen: SplitScreen::default(),
+            ui_camera_buffer,
+            pixel_rounding: PixelRounding::default(),
+            clear_color: DEFAULT_CLEAR_COLOR,
+            batches: vec![DrawBatch {
+                space: DrawSpace::World,
+                sprite_start: 0,
+                shape_vertex_start: 0,
+            }],
+            pipeline,
+            ui_bind_group,
+            vertex_buffer_cpu: Vec::new(),
+            vertex_buffer,
+            vertex_buffer_vert_count: 0,
+            sampler,
+            sprites,
+            sprites_atlas_id: next_atlas_id(),
+            loaded_sprites: Vec::new(),
+            shape_pipeline,
+            ui_shape_bind_group,
+            shape_vertex_buffer_cpu: Vec::new(),
+            shape_vertex_buffer,
+            shape_vertex_buffer_vert_count: 0,
+        }
+    }
+
+    /// Rebuild the pipelines from new shader source.
+    /// If they fail to build, the old pipelines are kept.
+    fn reload_shader(
+        &mut self,
+        device: &wgpu::Device,
+        shader_source: &str,
+    ) -> Result<(), RendererError> {
+        let (pipeline, shape_pipeline) =
+            catch_validation_error(device, LOW_RES_SHADER_FILE, || {
+                Self::create_pipelines(device, self.low_res_texture.format(), shader_source)
+            })?;
+        self.pipeline = pipeline;
+        self.shape_pipeline = shape_pipeline;
+        // Bind groups are tied to the pipeline they were created from, so they're rebuilt too.
+        self.recreate_bind_groups(device);
+        Ok(())
+    }
+
+    fn create_low_res_texture(
+        device: &wgpu::Device,
+        canvas_width: u32,
+        canvas_height: u32,
+        preferred_format: wgpu::TextureFormat,
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let low_res_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("low res texture"),
+            size: wgpu::Extent3d {
+                width: canvas_width,
+                height: canvas_height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: preferred_format,
+            // Copied from when capturing frames.
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
         });
-        let line_vertex_buffer: wgpu::Buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("low res line vertex buffer"),
-            size: 100_000,
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
+        let low_res_texture_view =
+            low_res_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        (low_res_texture, low_res_texture_view)
+    }
+
+    fn create_depth_texture(
+        device: &wgpu::Device,
+        canvas_width: u32,
+        canvas_height: u32,
+    ) -> wgpu::TextureView {
+        let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("low res depth texture"),
+            size: wgpu::Extent3d {
+                width: canvas_width,
+                height: canvas_height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
         });
-        let line_pipeline: wgpu::RenderPipeline =
+        depth_texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    /// Creates the sprite and shape pipelines from the low res shader.
+    fn create_pipelines(
+        device: &wgpu::Device,
+        preferred_format: wgpu::TextureFormat,
+        shader_source: &str,
+    ) -> (wgpu::RenderPipeline, wgpu::RenderPipeline) {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(LOW_RES_SHADER_FILE),
+            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+        });
+        let pipeline: wgpu::RenderPipeline =
             device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("low res line pipeline"),
+                label: Some("low res pipeline"),
                 layout: None,
                 vertex: wgpu::VertexState {
                     module: &shader,
@@ -346,20 +962,54 @@ impl LowResPass {
                         attributes: TEXTURE_VERTEX_ATTRIBUTES,
                     }],
                 },
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::LineList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: None,
-                    unclipped_depth: false,
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    conservative: false,
+                primitive: wgpu::PrimitiveState::default(),
+                // Less or equal, so among sprites at the same z, the last one drawn is on top.
+                // This also keeps UI sprites, which are drawn last, on top of the world.
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DEPTH_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::LessEqual,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fragment_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: preferred_format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+            });
+        let shape_pipeline: wgpu::RenderPipeline =
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("low res shape pipeline"),
+                layout: None,
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vertex_color",
+                    buffers: &[wgpu::VertexBufferLayout {
+                        array_stride: std::mem::size_of::<ColorVertex>() as u64,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: COLOR_VERTEX_ATTRIBUTES,
+                    }],
                 },
-                depth_stencil: None,
+                primitive: wgpu::PrimitiveState::default(),
+                // Shapes, such as debug outlines, are always drawn over the sprites in their batch.
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DEPTH_FORMAT,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::Always,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
                 multisample: wgpu::MultisampleState::default(),
                 fragment: Some(wgpu::FragmentState {
                     module: &shader,
-                    entry_point: "fragment_line",
+                    entry_point: "fragment_color",
                     targets: &[Some(wgpu::ColorTargetState {
                         format: preferred_format,
                         blend: Some(wgpu::BlendState::ALPHA_BLENDING),
@@ -368,52 +1018,266 @@ impl LowResPass {
                 }),
                 multiview: None,
             });
-        let line_bind_group: wgpu::BindGroup =
-            device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("low res line bind group"),
-                layout: &line_pipeline.get_bind_group_layout(0),
-                entries: &[wgpu::BindGroupEntry {
+        (pipeline, shape_pipeline)
+    }
+
+    fn create_camera_buffer(device: &wgpu::Device, label: &str, camera: Camera) -> wgpu::Buffer {
+        let camera_buffer: wgpu::Buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: std::mem::size_of::<CameraUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: true,
+        });
+        camera_buffer
+            .slice(..)
+            .get_mapped_range_mut()
+            .copy_from_slice(bytemuck::bytes_of(&CameraUniform::from(camera)));
+        camera_buffer.unmap();
+        camera_buffer
+    }
+
+    fn create_shape_bind_group(
+        device: &wgpu::Device,
+        shape_pipeline: &wgpu::RenderPipeline,
+        label: &str,
+        camera_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(label),
+            layout: &shape_pipeline.get_bind_group_layout(0),
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: camera_buffer,
+                    offset: 0,
+                    size: None,
+                }),
+            }],
+        })
+    }
+
+    fn create_sprites_texture(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: glam::UVec2,
+    ) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("low res sprites"),
+            size: wgpu::Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: MAX_SPRITES,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
+    }
+
+    /// The world and UI bind groups for the sprite pipeline.
+    fn create_bind_group(
+        device: &wgpu::Device,
+        pipeline: &wgpu::RenderPipeline,
+        label: &str,
+        camera_buffer: &wgpu::Buffer,
+        sampler: &wgpu::Sampler,
+        sprites: &wgpu::Texture,
+    ) -> wgpu::BindGroup {
+        let sprites_view: wgpu::TextureView =
+            sprites.create_view(&wgpu::TextureViewDescriptor::default());
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(label),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
                     binding: 0,
                     resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                        buffer: &camera_buffer,
+                        buffer: camera_buffer,
                         offset: 0,
                         size: None,
                     }),
-                }],
-            });
-        Self {
-            low_res_texture,
-            low_res_texture_view,
-            camera,
-            camera_buffer,
-            pipeline,
-            bind_group,
-            vertex_buffer_cpu: Vec::new(),
-            vertex_buffer,
-            vertex_buffer_vert_count: 0,
-            sprites,
-            loaded_sprites: Vec::new(),
-            line_pipeline,
-            line_bind_group,
-            line_vertex_buffer_cpu: Vec::new(),
-            line_vertex_buffer,
-            line_vertex_buffer_vert_count: 0,
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&sprites_view),
+                },
+            ],
+        })
+    }
+
+    /// Bind groups refer to the pipelines and the sprites texture,
+    /// so they must be recreated when either changes.
+    fn recreate_bind_groups(&mut self, device: &wgpu::Device) {
+        for camera_view in self.cameras.iter_mut() {
+            *camera_view = CameraView::new(
+                device,
+                camera_view.camera,
+                &self.pipeline,
+                &self.shape_pipeline,
+                &self.sampler,
+                &self.sprites,
+            );
+        }
+        self.ui_bind_group = Self::create_bind_group(
+            device,
+            &self.pipeline,
+            "low res ui bind group",
+            &self.ui_camera_buffer,
+            &self.sampler,
+            &self.sprites,
+        );
+        self.ui_shape_bind_group = Self::create_shape_bind_group(
+            device,
+            &self.shape_pipeline,
+            "low res ui shape bind group",
+            &self.ui_camera_buffer,
+        );
+    }
+
+    /// Recreate the low res texture at a new size.
+    /// The cameras keep their top lefts, but their sizes are changed to match their viewports.
+    fn set_canvas_size(&mut self, device: &wgpu::Device, canvas_width: u32, canvas_height: u32) {
+        let (low_res_texture, low_res_texture_view) = Self::create_low_res_texture(
+            device,
+            canvas_width,
+            canvas_height,
+            self.low_res_texture.format(),
+        );
+        self.low_res_texture = low_res_texture;
+        self.low_res_texture_view = low_res_texture_view;
+        self.depth_texture_view = Self::create_depth_texture(device, canvas_width, canvas_height);
+        let viewports = self.viewports(self.cameras.len());
+        for (camera_view, viewport) in self.cameras.iter_mut().zip(viewports) {
+            camera_view.camera.width_height = viewport.size.as_vec2();
+        }
+    }
+
+    /// Keep a camera view for each camera, creating or dropping views as the count changes.
+    fn set_cameras(&mut self, device: &wgpu::Device, cameras: &[Camera]) {
+        if cameras.is_empty() {
+            log::error!("At least one camera is needed, keeping the current cameras");
+            return;
+        }
+        self.cameras.truncate(cameras.len());
+        for (camera_view, camera) in self.cameras.iter_mut().zip(cameras) {
+            camera_view.camera = *camera;
+        }
+        for camera in &cameras[self.cameras.len()..] {
+            self.cameras.push(CameraView::new(
+                device,
+                *camera,
+                &self.pipeline,
+                &self.shape_pipeline,
+                &self.sampler,
+                &self.sprites,
+            ));
+        }
+    }
+
+    fn cameras(&self) -> Vec<Camera> {
+        self.cameras
+            .iter()
+            .map(|camera_view| camera_view.camera)
+            .collect()
+    }
+
+    fn set_split_screen(&mut self, split_screen: SplitScreen) {
+        self.split_screen = split_screen;
+    }
+
+    fn canvas_size(&self) -> glam::UVec2 {
+        glam::UVec2::new(self.low_res_texture.width(), self.low_res_texture.height())
+    }
+
+    /// The viewports for the given number of cameras.
+    fn viewports(&self, count: usize) -> Vec<Viewport> {
+        self.split_screen.viewports(self.canvas_size(), count)
+    }
+
+    /// The viewport of one of the current cameras.
+    fn viewport(&self, index: usize) -> Viewport {
+        self.split_screen
+            .viewport(self.canvas_size(), self.cameras.len(), index)
+    }
+
+    fn ui_camera(&self) -> Camera {
+        Camera {
+            top_left: glam::Vec2::ZERO,
+            width_height: glam::Vec2::new(
+                self.low_res_texture.width() as f32,
+                self.low_res_texture.height() as f32,
+            ),
+            zoom: 1.0,
+        }
+    }
+
+    /// Start a new batch if the space is changing.
+    fn begin(&mut self, space: DrawSpace) {
+        if self.batches.last().map(|batch| batch.space) == Some(space) {
+            return;
         }
+        self.batches.push(DrawBatch {
+            space,
+            sprite_start: self.vertex_buffer_vert_count,
+            shape_vertex_start: self.shape_vertex_buffer_vert_count,
+        });
+    }
+
+    fn set_pixel_rounding(&mut self, pixel_rounding: PixelRounding) {
+        self.pixel_rounding = pixel_rounding;
+    }
+
+    fn set_clear_color(&mut self, clear_color: wgpu::Color) {
+        self.clear_color = clear_color;
     }
 
-    fn set_camera(&mut self, camera: Camera) {
-        self.camera = camera;
+    fn clear_color(&self) -> wgpu::Color {
+        self.clear_color
     }
 
-    fn load_sprite(&mut self, queue: &wgpu::Queue, sprite: Sprite) -> SpriteIndex {
+    fn load_sprite(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        sprite: Sprite,
+    ) -> SpriteIndex {
         if let Some(existing_index) = self
             .loaded_sprites
             .iter()
             .position(|loaded_sprite| *loaded_sprite == sprite)
         {
-            return SpriteIndex(existing_index as u32);
+            return SpriteIndex {
+                index: existing_index as u32,
+                atlas_id: self.sprites_atlas_id,
+            };
+        }
+        let sprite_image = Self::decode_sprite(&sprite);
+        let sprites_size = glam::UVec2::new(self.sprites.width(), self.sprites.height());
+        if let Some(new_size) = grown_sprites_size(
+            sprites_size,
+            glam::UVec2::new(sprite_image.width(), sprite_image.height()),
+        ) {
+            self.grow_sprites(device, queue, new_size);
         }
-        let sprite_image: image::RgbaImage = image::io::Reader::open(&sprite.file)
+        let sprite_index = self.loaded_sprites.len() as u32;
+        self.write_sprite(queue, sprite_index, &sprite_image);
+        self.loaded_sprites.push(sprite);
+        log::debug!("Loaded new sprite at index: {}", sprite_index);
+        SpriteIndex {
+            index: sprite_index,
+            atlas_id: self.sprites_atlas_id,
+        }
+    }
+
+    fn decode_sprite(sprite: &Sprite) -> image::RgbaImage {
+        let mut sprite_image: image::RgbaImage = image::io::Reader::open(&sprite.file)
             .unwrap_or_else(|_| panic!("couldn't open sprite file ({:?})", &sprite.file))
             .decode()
             .unwrap_or_else(|_| panic!("couldn't decode sprite file ({:?})", &sprite.file))
@@ -424,7 +1288,17 @@ impl LowResPass {
                 sprite.width_height.y,
             )
             .into_rgba8();
-        let sprite_index = self.loaded_sprites.len() as u32;
+        sprite.apply_palette_swap(&mut sprite_image);
+        sprite_image
+    }
+
+    /// Copy the image into the top left corner of the sprite's layer.
+    fn write_sprite(
+        &self,
+        queue: &wgpu::Queue,
+        sprite_index: u32,
+        sprite_image: &image::RgbaImage,
+    ) {
         let bytes_per_pixel = 4;
         queue.write_texture(
             wgpu::ImageCopyTexture {
@@ -449,91 +1323,301 @@ impl LowResPass {
                 depth_or_array_layers: 1,
             },
         );
-        self.loaded_sprites.push(sprite);
-        log::debug!("Loaded new sprite at index: {}", sprite_index);
-        SpriteIndex(sprite_index)
     }
 
-    fn draw_image(
-        &mut self,
-        sprite_index: SpriteIndex,
-        sprite_z: f32,
-        location: glam::Vec2,
-        size: glam::Vec2,
-    ) {
+    /// Recreate the sprites texture with bigger layers, and load the existing sprites again.
+    /// Sprite indices stay the same, so they remain valid.
+    fn grow_sprites(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, new_size: glam::UVec2) {
+        log::debug!(
+            "Growing sprite layers from {}x{} to {}x{}",
+            self.sprites.width(),
+            self.sprites.height(),
+            new_size.x,
+            new_size.y
+        );
+        self.sprites = Self::create_sprites_texture(device, self.sprites.format(), new_size);
+        self.recreate_bind_groups(device);
+        for (sprite_index, sprite) in self.loaded_sprites.iter().enumerate() {
+            self.write_sprite(queue, sprite_index as u32, &Self::decode_sprite(sprite));
+        }
+    }
+
+    fn draw_image(&mut self, image: DrawImage) {
+        if image.sprite_index.atlas_id != self.sprites_atlas_id {
+            log::error!(
+                "Sprite index {:?} is from a different renderer, not drawing it",
+                image.sprite_index
+            );
+            return;
+        }
         let sprite_width_height: glam::UVec2 =
-            self.loaded_sprites[sprite_index.0 as usize].width_height;
+            self.loaded_sprites[image.sprite_index.index as usize].width_height;
+        // With split screen, world sprites are rounded to the pixels of the first camera.
+        let (camera, canvas_size) = match self.batches.last().map(|batch| batch.space) {
+            Some(DrawSpace::Ui) => {
+                let ui_camera = self.ui_camera();
+                (ui_camera, ui_camera.width_height)
+            }
+            _ => (self.cameras[0].camera, self.viewport(0).size.as_vec2()),
+        };
         let square_vertices = square(
-            location,
-            sprite_z,
+            DrawImage {
+                location: self
+                    .pixel_rounding
+                    .round(image.location, &camera, canvas_size),
+                ..image
+            },
             sprite_width_height,
-            sprite_index.0,
-            size,
         );
         let square_bytes: &[u8] = bytemuck::cast_slice(square_vertices.as_slice());
         self.vertex_buffer_cpu.extend_from_slice(square_bytes);
         self.vertex_buffer_vert_count += 1;
     }
 
-    fn draw_rectangle(&mut self, location: glam::Vec2, width_height: glam::Vec2) {
-        let square_vertices = square_outline(location, width_height);
-        let square_bytes: &[u8] = bytemuck::cast_slice(square_vertices.as_slice());
-        self.line_vertex_buffer_cpu.extend_from_slice(square_bytes);
-        self.line_vertex_buffer_vert_count += 1;
+    fn draw_rectangle(
+        &mut self,
+        top_left: glam::Vec2,
+        size: glam::Vec2,
+        color: glam::Vec4,
+        style: RectangleStyle,
+    ) {
+        match style {
+            RectangleStyle::Filled => {
+                let square_vertices = color_square(top_left, size, color);
+                self.shape_vertex_buffer_cpu
+                    .extend_from_slice(bytemuck::cast_slice(square_vertices.as_slice()));
+                self.shape_vertex_buffer_vert_count += SQUARE_VERTS;
+            }
+            RectangleStyle::Outline(thickness) => {
+                let square_vertices = square_outline(top_left, size, thickness, color);
+                self.shape_vertex_buffer_cpu
+                    .extend_from_slice(bytemuck::cast_slice(square_vertices.as_slice()));
+                self.shape_vertex_buffer_vert_count += SQUARE_OUTLINE_VERTS;
+            }
+        }
+    }
+
+    fn draw_line(&mut self, a: glam::Vec2, b: glam::Vec2, thickness: f32, color: glam::Vec4) {
+        let line_vertices = line_quad(a, b, thickness, color);
+        self.shape_vertex_buffer_cpu
+            .extend_from_slice(bytemuck::cast_slice(line_vertices.as_slice()));
+        self.shape_vertex_buffer_vert_count += SQUARE_VERTS;
     }
 
-    fn draw(&mut self, queue: &wgpu::Queue, command_encoder: &mut wgpu::CommandEncoder) {
+    /// Draw to the low res texture, or to the given target view.
+    /// The target must be the same size and format as the low res texture.
+    fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        command_encoder: &mut wgpu::CommandEncoder,
+        target_view: Option<&wgpu::TextureView>,
+    ) {
+        // Update cameras
+        for camera_view in &self.cameras {
+            queue.write_buffer(
+                &camera_view.camera_buffer,
+                0,
+                bytemuck::bytes_of(&CameraUniform::from(camera_view.camera)),
+            );
+        }
+        queue.write_buffer(
+            &self.ui_camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::from(self.ui_camera())),
+        );
+        // Update vertices
+        grow_vertex_buffer(
+            device,
+            &mut self.vertex_buffer,
+            VERTEX_BUFFER_LABEL,
+            self.vertex_buffer_cpu.len(),
+        );
+        grow_vertex_buffer(
+            device,
+            &mut self.shape_vertex_buffer,
+            SHAPE_VERTEX_BUFFER_LABEL,
+            self.shape_vertex_buffer_cpu.len(),
+        );
+        queue.write_buffer(&self.vertex_buffer, 0, self.vertex_buffer_cpu.as_slice());
+        queue.write_buffer(
+            &self.shape_vertex_buffer,
+            0,
+            self.shape_vertex_buffer_cpu.as_slice(),
+        );
         let mut pass: wgpu::RenderPass =
             command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("low res render pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &self.low_res_texture_view,
+                    view: target_view.unwrap_or(&self.low_res_texture_view),
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.15,
-                            b: 0.1,
-                            a: 1.0,
-                        }),
+                        load: wgpu::LoadOp::Clear(self.clear_color),
                         store: wgpu::StoreOp::Store,
                     },
                 })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_texture_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
                 occlusion_query_set: None,
             });
-        // Update camera
-        queue.write_buffer(&self.camera_buffer, 0, bytemuck::bytes_of(&self.camera));
-        // Draw sprites
-        queue.write_buffer(&self.vertex_buffer, 0, self.vertex_buffer_cpu.as_slice());
-        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        pass.set_pipeline(&self.pipeline);
-        pass.set_bind_group(0, &self.bind_group, &[]);
-        pass.draw(0..self.vertex_buffer_vert_count * SQUARE_VERTS, 0..1);
+        let draw_calls = draw_calls(
+            &self.batches,
+            self.vertex_buffer_vert_count,
+            self.shape_vertex_buffer_vert_count,
+            &self.viewports(self.cameras.len()),
+            self.canvas_size(),
+        );
+        for draw_call in draw_calls {
+            let (bind_group, shape_bind_group) = match draw_call.camera {
+                Some(camera) => (
+                    &self.cameras[camera].bind_group,
+                    &self.cameras[camera].shape_bind_group,
+                ),
+                None => (&self.ui_bind_group, &self.ui_shape_bind_group),
+            };
+            let Viewport { top_left, size } = draw_call.viewport;
+            pass.set_viewport(
+                top_left.x as f32,
+                top_left.y as f32,
+                size.x as f32,
+                size.y as f32,
+                0.0,
+                1.0,
+            );
+            pass.set_scissor_rect(top_left.x, top_left.y, size.x, size.y);
+            // Draw sprites
+            pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.draw(
+                draw_call.sprites.start * SQUARE_VERTS..draw_call.sprites.end * SQUARE_VERTS,
+                0..1,
+            );
+            // Draw shapes
+            pass.set_vertex_buffer(0, self.shape_vertex_buffer.slice(..));
+            pass.set_pipeline(&self.shape_pipeline);
+            pass.set_bind_group(0, shape_bind_group, &[]);
+            pass.draw(draw_call.shape_vertices, 0..1);
+        }
         self.vertex_buffer_cpu.clear();
         self.vertex_buffer_vert_count = 0;
-        // Draw lines
-        queue.write_buffer(
-            &self.line_vertex_buffer,
-            0,
-            self.line_vertex_buffer_cpu.as_slice(),
+        self.shape_vertex_buffer_cpu.clear();
+        self.shape_vertex_buffer_vert_count = 0;
+        // Every frame starts out drawing the world.
+        self.batches = vec![DrawBatch {
+            space: DrawSpace::World,
+            sprite_start: 0,
+            shape_vertex_start: 0,
+        }];
+    }
+
+    /// Read the low res texture back from the GPU.
+    /// Waits for the GPU, so this is too slow to call every frame.
+    fn capture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<image::RgbaImage, RendererError> {
+        let format = self.low_res_texture.format();
+        if !matches!(
+            format,
+            wgpu::TextureFormat::Rgba8Unorm
+                | wgpu::TextureFormat::Rgba8UnormSrgb
+                | wgpu::TextureFormat::Bgra8Unorm
+                | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            return Err(RendererError::UnsupportedCaptureFormat(format));
+        }
+        let size = self.canvas_size();
+        let padded_bytes_per_row = padded_bytes_per_row(size.x);
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("low res readback buffer"),
+            size: (padded_bytes_per_row * size.y) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut command_encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("capture command encoder"),
+        });
+        command_encoder.copy_texture_to_buffer(
+            self.low_res_texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &readback_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(size.y),
+                },
+            },
+            self.low_res_texture.size(),
         );
-        pass.set_vertex_buffer(0, self.line_vertex_buffer.slice(..));
-        pass.set_pipeline(&self.line_pipeline);
-        pass.set_bind_group(0, &self.line_bind_group, &[]);
-        pass.draw(
-            0..self.line_vertex_buffer_vert_count * SQUARE_OUTLINE_VERTS,
-            0..1,
+        queue.submit([command_encoder.finish()]);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+        device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .expect("the readback buffer was never mapped")
+            .map_err(RendererError::CaptureMap)?;
+        let image = readback_image(
+            &readback_buffer.slice(..).get_mapped_range(),
+            size,
+            padded_bytes_per_row,
+            format,
         );
-        self.line_vertex_buffer_cpu.clear();
-        self.line_vertex_buffer_vert_count = 0;
+        readback_buffer.unmap();
+        Ok(image)
     }
 }
 
+/// Rows copied out of a texture must be aligned, so each row may be followed by padding.
+fn padded_bytes_per_row(width: u32) -> u32 {
+    let bytes_per_row = width * 4;
+    let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    bytes_per_row.div_ceil(alignment) * alignment
+}
+
+/// Turn the rows read back from an 8 bit RGBA or BGRA texture into an image,
+/// dropping the row padding and putting the channels in RGBA order.
+fn readback_image(
+    data: &[u8],
+    size: glam::UVec2,
+    padded_bytes_per_row: u32,
+    format: wgpu::TextureFormat,
+) -> image::RgbaImage {
+    let bytes_per_row = (size.x * 4) as usize;
+    let mut pixels: Vec<u8> = data
+        .chunks(padded_bytes_per_row as usize)
+        .take(size.y as usize)
+        .flat_map(|row| &row[..bytes_per_row])
+        .copied()
+        .collect();
+    if matches!(
+        format,
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+    ) {
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+    image::RgbaImage::from_raw(size.x, size.y, pixels).expect("readback is the size of the image")
+}
+
 struct SurfacePass {
     pipeline: wgpu::RenderPipeline,
     aspect_ratio_uniform: wgpu::Buffer,
+    sampler: wgpu::Sampler,
     bind_group: wgpu::BindGroup,
     vertex_buffer: wgpu::Buffer,
 }
@@ -543,10 +1627,62 @@ impl SurfacePass {
         device: &wgpu::Device,
         preferred_format: wgpu::TextureFormat,
         low_res_texture_view: &wgpu::TextureView,
+        shader_source: &str,
     ) -> Self {
-        // TODO: Stop including the shader in the compiled binary. Compile them at runtime.
-        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/surface.wgsl"));
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        let pipeline = Self::create_pipeline(device, preferred_format, shader_source);
+        let aspect_ratio_uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("surface uniform"),
+            size: std::mem::size_of::<glam::Vec2>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sampler: wgpu::Sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("surface sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 0.0,
+            compare: None,
+            anisotropy_clamp: 1,
+            border_color: None,
+        });
+        let bind_group = Self::create_bind_group(
+            device,
+            &pipeline,
+            &aspect_ratio_uniform,
+            &sampler,
+            low_res_texture_view,
+        );
+        let ndc_square = ndc_square();
+        let ndc_square_bytes: &[u8] = bytemuck::cast_slice(ndc_square.as_slice());
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("surface vertex buffer"),
+            contents: ndc_square_bytes,
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        Self {
+            pipeline,
+            aspect_ratio_uniform,
+            sampler,
+            bind_group,
+            vertex_buffer,
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        preferred_format: wgpu::TextureFormat,
+        shader_source: &str,
+    ) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(SURFACE_SHADER_FILE),
+            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("surface pipeline"),
             layout: None,
             vertex: wgpu::VertexState {
@@ -571,62 +1707,77 @@ impl SurfacePass {
                 })],
             }),
             multiview: None,
-        });
-        let aspect_ratio_uniform = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("surface uniform"),
-            size: std::mem::size_of::<glam::Vec2>() as u64,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-        let sampler: wgpu::Sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            label: Some("surface sampler"),
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Linear,
-            lod_min_clamp: 0.0,
-            lod_max_clamp: 0.0,
-            compare: None,
-            anisotropy_clamp: 1,
-            border_color: None,
-        });
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        })
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        pipeline: &wgpu::RenderPipeline,
+        aspect_ratio_uniform: &wgpu::Buffer,
+        sampler: &wgpu::Sampler,
+        low_res_texture_view: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("surface bind group"),
             layout: &pipeline.get_bind_group_layout(0),
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
                     resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                        buffer: &aspect_ratio_uniform,
+                        buffer: aspect_ratio_uniform,
                         offset: 0,
                         size: None,
                     }),
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
+                    resource: wgpu::BindingResource::Sampler(sampler),
                 },
                 wgpu::BindGroupEntry {
                     binding: 2,
-                    resource: wgpu::BindingResource::TextureView(&low_res_texture_view),
+                    resource: wgpu::BindingResource::TextureView(low_res_texture_view),
                 },
             ],
-        });
-        let ndc_square = ndc_square();
-        let ndc_square_bytes: &[u8] = bytemuck::cast_slice(ndc_square.as_slice());
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("surface vertex buffer"),
-            contents: ndc_square_bytes,
-            usage: wgpu::BufferUsages::VERTEX,
-        });
-        Self {
-            pipeline,
-            aspect_ratio_uniform,
-            bind_group,
-            vertex_buffer,
-        }
+        })
+    }
+
+    /// Rebuild the pipeline from new shader source.
+    /// If it fails to build, the old pipeline is kept.
+    fn reload_shader(
+        &mut self,
+        device: &wgpu::Device,
+        preferred_format: wgpu::TextureFormat,
+        shader_source: &str,
+        low_res_texture_view: &wgpu::TextureView,
+    ) -> Result<(), RendererError> {
+        let pipeline = catch_validation_error(device, SURFACE_SHADER_FILE, || {
+            Self::create_pipeline(device, preferred_format, shader_source)
+        })?;
+        // The bind group is tied to the pipeline it was created from, so it's rebuilt too.
+        self.bind_group = Self::create_bind_group(
+            device,
+            &pipeline,
+            &self.aspect_ratio_uniform,
+            &self.sampler,
+            low_res_texture_view,
+        );
+        self.pipeline = pipeline;
+        Ok(())
+    }
+
+    /// Sample from a different low res texture; e.g., after the canvas was resized.
+    fn set_low_res_texture_view(
+        &mut self,
+        device: &wgpu::Device,
+        low_res_texture_view: &wgpu::TextureView,
+    ) {
+        self.bind_group = Self::create_bind_group(
+            device,
+            &self.pipeline,
+            &self.aspect_ratio_uniform,
+            &self.sampler,
+            low_res_texture_view,
+        );
     }
 
     fn update_aspect_ratio(&self, queue: &wgpu::Queue, scales: glam::Vec2) {
@@ -638,7 +1789,7 @@ impl SurfacePass {
             command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("surface render pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &surface_view,
+                    view: surface_view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
@@ -656,6 +1807,185 @@ impl SurfacePass {
     }
 }
 
+#[derive(Debug)]
+pub enum RendererError {
+    /// The surface doesn't support any texture formats on this adapter.
+    NoSupportedFormat,
+    /// A shader file exists but couldn't be read.
+    ShaderIo {
+        path: std::path::PathBuf,
+        message: String,
+    },
+    /// A shader failed to parse or validate.
+    ShaderCompile { file: String, message: String },
+    /// Frames can only be captured from 8 bit per channel canvases.
+    UnsupportedCaptureFormat(wgpu::TextureFormat),
+    /// The captured frame couldn't be read back from the GPU.
+    CaptureMap(wgpu::BufferAsyncError),
+}
+
+const LOW_RES_SHADER_FILE: &str = "low_res.wgsl";
+const SURFACE_SHADER_FILE: &str = "surface.wgsl";
+const EMBEDDED_LOW_RES_SHADER: &str = include_str!("shaders/low_res.wgsl");
+const EMBEDDED_SURFACE_SHADER: &str = include_str!("shaders/surface.wgsl");
+
+/// Read a shader from the shader directory, or use the embedded copy if there's no directory.
+/// Either way, the shader is checked, so errors are returned rather than panicking later.
+fn load_shader(
+    shader_dir: Option<&std::path::Path>,
+    file_name: &str,
+    embedded: &'static str,
+) -> Result<std::borrow::Cow<'static, str>, RendererError> {
+    let source = match shader_dir {
+        Some(shader_dir) if shader_dir.is_dir() => {
+            let path = shader_dir.join(file_name);
+            log::debug!("Loading shader from {:?}", path);
+            let source = std::fs::read_to_string(&path).map_err(|e| RendererError::ShaderIo {
+                path,
+                message: e.to_string(),
+            })?;
+            std::borrow::Cow::Owned(source)
+        }
+        Some(shader_dir) => {
+            log::warn!(
+                "Shader directory {:?} doesn't exist, using embedded {}",
+                shader_dir,
+                file_name
+            );
+            std::borrow::Cow::Borrowed(embedded)
+        }
+        None => std::borrow::Cow::Borrowed(embedded),
+    };
+    check_shader(file_name, &source)?;
+    Ok(source)
+}
+
+/// Parse and validate WGSL.
+fn check_shader(file_name: &str, source: &str) -> Result<(), RendererError> {
+    let compile_error = |message: String| RendererError::ShaderCompile {
+        file: file_name.to_string(),
+        message,
+    };
+    let module = naga::front::wgsl::parse_str(source)
+        .map_err(|e| compile_error(e.emit_to_string(source)))?;
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::default(),
+    )
+    .validate(&module)
+    .map_err(|e| compile_error(e.to_string()))?;
+    Ok(())
+}
+
+/// Run `f`, returning the first validation error it causes instead of panicking.
+/// Catches what naga's checks miss, like a shader that doesn't match the vertex layout.
+fn catch_validation_error<T>(
+    device: &wgpu::Device,
+    file_name: &str,
+    f: impl FnOnce() -> T,
+) -> Result<T, RendererError> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let result = f();
+    match device.pop_error_scope().block_on() {
+        Some(e) => Err(RendererError::ShaderCompile {
+            file: file_name.to_string(),
+            message: e.to_string(),
+        }),
+        None => Ok(result),
+    }
+}
+
+/// Notices changes to shader files, using the operating system's file notifications.
+/// The directory is watched rather than the files, because many editors save by replacing a file.
+struct ShaderWatcher {
+    shader_dir: std::path::PathBuf,
+    file_names: Vec<&'static str>,
+    // Notifications stop when the watcher is dropped, so it's kept even though it's never used.
+    _watcher: notify::RecommendedWatcher,
+    events: std::sync::mpsc::Receiver<notify::Result<notify::Event>>,
+}
+
+impl ShaderWatcher {
+    fn new(
+        shader_dir: &std::path::Path,
+        file_names: &[&'static str],
+    ) -> Result<Self, notify::Error> {
+        use notify::Watcher as _;
+        let (sender, events) = std::sync::mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(shader_dir, notify::RecursiveMode::NonRecursive)?;
+        Ok(Self {
+            shader_dir: shader_dir.to_path_buf(),
+            file_names: file_names.to_vec(),
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    /// The files that changed since the last call, each reported once.
+    /// Deleted files aren't reported, because there's nothing to reload.
+    fn changed_files(&mut self) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        for event in self.events.try_iter() {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    log::warn!("Error watching shaders: {}", e);
+                    continue;
+                }
+            };
+            if !(event.kind.is_create() || event.kind.is_modify()) {
+                continue;
+            }
+            for path in event.paths {
+                let Some(&file_name) = self
+                    .file_names
+                    .iter()
+                    .find(|&&file_name| path.file_name() == Some(file_name.as_ref()))
+                else {
+                    continue;
+                };
+                if !changed.contains(&file_name) {
+                    changed.push(file_name);
+                }
+            }
+        }
+        // A file that was changed and then deleted can't be reloaded.
+        changed.retain(|file_name| self.shader_dir.join(file_name).is_file());
+        changed
+    }
+}
+
+/// The fraction of the window the canvas covers along each axis.
+/// The canvas keeps its aspect ratio, so it fills one axis and is letterboxed along the other.
+fn canvas_scales(canvas_size: glam::Vec2, window_size: glam::Vec2) -> glam::Vec2 {
+    let canvas_to_surface_ratio = canvas_size / window_size;
+    canvas_to_surface_ratio / canvas_to_surface_ratio.max_element()
+}
+
+/// The inverse of how the surface pass draws the canvas, centered in the window.
+fn window_to_canvas(
+    window_position: glam::Vec2,
+    canvas_size: glam::Vec2,
+    window_size: glam::Vec2,
+) -> glam::Vec2 {
+    let canvas_window_size = canvas_scales(canvas_size, window_size) * window_size;
+    let canvas_window_top_left = (window_size - canvas_window_size) / 2.0;
+    (window_position - canvas_window_top_left) / canvas_window_size * canvas_size
+}
+
+/// Prefer an sRGB format, so colors aren't washed out, otherwise take the first supported format.
+fn choose_surface_format(
+    formats: &[wgpu::TextureFormat],
+) -> Result<wgpu::TextureFormat, RendererError> {
+    formats
+        .iter()
+        .find(|format| format.is_srgb())
+        .or(formats.first())
+        .copied()
+        .ok_or(RendererError::NoSupportedFormat)
+}
+
 pub struct Renderer {
     // WGPU stuff
     surface: wgpu::Surface,
@@ -665,13 +1995,79 @@ pub struct Renderer {
     // Render passes
     low_res_pass: LowResPass,
     surface_pass: SurfacePass,
+    /// Skip the surface pass when the window is the same size as the canvas.
+    direct_render: bool,
+    /// Only set when shaders were loaded from a directory.
+    shader_watcher: Option<ShaderWatcher>,
     // Window
     // unsafe: window must live longer than surface.
     window: winit::window::Window,
 }
 
 impl Renderer {
-    pub fn new(window: winit::window::Window, canvas_width: u32, canvas_height: u32) -> Self {
+    pub fn new(
+        window: winit::window::Window,
+        canvas_width: u32,
+        canvas_height: u32,
+    ) -> Result<Self, RendererError> {
+        Self::with_sprite_color_space(
+            window,
+            canvas_width,
+            canvas_height,
+            SpriteColorSpace::default(),
+        )
+    }
+
+    /// Like `new`, but sprites are interpreted in the given color space.
+    pub fn with_sprite_color_space(
+        window: winit::window::Window,
+        canvas_width: u32,
+        canvas_height: u32,
+        sprite_color_space: SpriteColorSpace,
+    ) -> Result<Self, RendererError> {
+        Self::build(
+            window,
+            canvas_width,
+            canvas_height,
+            sprite_color_space,
+            None,
+        )
+    }
+
+    /// Like `new`, but shaders are read from the given directory at startup,
+    /// so they can be changed without recompiling.
+    /// If the directory doesn't exist, the shaders built into the game are used.
+    pub fn new_with_shader_dir<P: AsRef<std::path::Path>>(
+        window: winit::window::Window,
+        canvas_width: u32,
+        canvas_height: u32,
+        shader_dir: P,
+    ) -> Result<Self, RendererError> {
+        Self::build(
+            window,
+            canvas_width,
+            canvas_height,
+            SpriteColorSpace::default(),
+            Some(shader_dir.as_ref()),
+        )
+    }
+
+    fn build(
+        window: winit::window::Window,
+        canvas_width: u32,
+        canvas_height: u32,
+        sprite_color_space: SpriteColorSpace,
+        shader_dir: Option<&std::path::Path>,
+    ) -> Result<Self, RendererError> {
+        let low_res_shader = load_shader(shader_dir, LOW_RES_SHADER_FILE, EMBEDDED_LOW_RES_SHADER)?;
+        let surface_shader = load_shader(shader_dir, SURFACE_SHADER_FILE, EMBEDDED_SURFACE_SHADER)?;
+        let shader_watcher = shader_dir
+            .filter(|shader_dir| shader_dir.is_dir())
+            .and_then(|shader_dir| {
+                ShaderWatcher::new(shader_dir, &[LOW_RES_SHADER_FILE, SURFACE_SHADER_FILE])
+                    .map_err(|e| log::warn!("Shaders won't reload when changed: {}", e))
+                    .ok()
+            });
         let instance: wgpu::Instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
         // unsafe: The window must live longer than its surface.
         let surface: wgpu::Surface = unsafe { instance.create_surface(&window) }.unwrap();
@@ -680,20 +2076,28 @@ impl Renderer {
             .block_on()
             .unwrap();
         let preferred_format: wgpu::TextureFormat =
-            *surface.get_capabilities(&adapter).formats.get(0).unwrap();
+            choose_surface_format(&surface.get_capabilities(&adapter).formats)?;
         log::debug!("Preferred format is: {:?}", &preferred_format);
         let (device, queue): (wgpu::Device, wgpu::Queue) = adapter
             .request_device(&wgpu::DeviceDescriptor::default(), None)
             .block_on()
             .unwrap();
         log::debug!("WGPU setup");
-        let low_res_pass = LowResPass::new(&device, canvas_width, canvas_height, preferred_format);
+        let low_res_pass = LowResPass::new(
+            &device,
+            canvas_width,
+            canvas_height,
+            preferred_format,
+            sprite_color_space,
+            &low_res_shader,
+        );
         let surface_pass = SurfacePass::new(
             &device,
             preferred_format,
             &low_res_pass.low_res_texture_view,
+            &surface_shader,
         );
-        Self {
+        Ok(Self {
             window,
             surface,
             preferred_format,
@@ -701,21 +2105,58 @@ impl Renderer {
             queue,
             low_res_pass,
             surface_pass,
+            direct_render: false,
+            shader_watcher,
+        })
+    }
+
+    /// Rebuild pipelines whose shader files changed on disk. Call once per frame.
+    /// Does nothing unless the renderer was created with a shader directory.
+    /// Shaders that fail to compile are logged, and the old pipeline is kept.
+    pub fn poll_shader_reload(&mut self) {
+        let Some(shader_watcher) = &mut self.shader_watcher else {
+            return;
+        };
+        let shader_dir = shader_watcher.shader_dir.clone();
+        for file_name in shader_watcher.changed_files() {
+            log::info!("Reloading shader {}", file_name);
+            let result = if file_name == LOW_RES_SHADER_FILE {
+                load_shader(Some(&shader_dir), file_name, EMBEDDED_LOW_RES_SHADER)
+                    .and_then(|source| self.low_res_pass.reload_shader(&self.device, &source))
+            } else {
+                load_shader(Some(&shader_dir), file_name, EMBEDDED_SURFACE_SHADER).and_then(
+                    |source| {
+                        self.surface_pass.reload_shader(
+                            &self.device,
+                            self.preferred_format,
+                            &source,
+                            &self.low_res_pass.low_res_texture_view,
+                        )
+                    },
+                )
+            };
+            if let Err(e) = result {
+                log::error!("Keeping the old {} pipeline: {:?}", file_name, e);
+            }
         }
     }
 
+    /// When enabled, and the window is exactly the size of the canvas,
+    /// the canvas is drawn directly to the window instead of being resampled by the surface pass.
+    pub fn set_direct_render(&mut self, direct_render: bool) {
+        self.direct_render = direct_render;
+    }
+
+    fn can_render_direct(&self) -> bool {
+        let window_inner_size = self.window.inner_size();
+        self.direct_render
+            && window_inner_size.width == self.low_res_pass.low_res_texture.width()
+            && window_inner_size.height == self.low_res_pass.low_res_texture.height()
+    }
+
     pub fn configure_surface(&self) {
         let window_inner_size = self.window.inner_size();
-        let canvas_to_surface_ratio_width: f32 =
-            (self.low_res_pass.low_res_texture.width() as f32) / (window_inner_size.width as f32);
-        let canvas_to_surface_ratio_height: f32 =
-            (self.low_res_pass.low_res_texture.height() as f32) / (window_inner_size.height as f32);
-        let maximum_canvas_to_surface_ratio: f32 =
-            canvas_to_surface_ratio_width.max(canvas_to_surface_ratio_height);
-        let canvas_scales = glam::Vec2::new(
-            canvas_to_surface_ratio_width / maximum_canvas_to_surface_ratio,
-            canvas_to_surface_ratio_height / maximum_canvas_to_surface_ratio,
-        );
+        let canvas_scales = canvas_scales(self.canvas_size(), self.window_size());
         self.surface_pass
             .update_aspect_ratio(&self.queue, canvas_scales);
         self.surface.configure(
@@ -733,27 +2174,158 @@ impl Renderer {
         );
     }
 
-    pub fn set_camera(&mut self, camera: Camera) {
-        self.low_res_pass.set_camera(camera);
+    /// Set one camera per viewport. With more than one camera, the canvas is split between them.
+    /// Each camera should be the size of its viewport, see `viewports`.
+    pub fn set_cameras(&mut self, cameras: &[Camera]) {
+        self.low_res_pass.set_cameras(&self.device, cameras);
+    }
+
+    pub fn cameras(&self) -> Vec<Camera> {
+        self.low_res_pass.cameras()
+    }
+
+    /// Set how the canvas is split between cameras.
+    pub fn set_split_screen(&mut self, split_screen: SplitScreen) {
+        self.low_res_pass.set_split_screen(split_screen);
+    }
+
+    /// The viewports the canvas would be split into for the given number of cameras.
+    pub fn viewports(&self, count: usize) -> Vec<Viewport> {
+        self.low_res_pass.viewports(count)
     }
 
+    /// Draw what follows in world coordinates, through each camera.
+    /// Each frame starts out drawing the world.
+    pub fn begin_world(&mut self) {
+        self.low_res_pass.begin(DrawSpace::World);
+    }
+
+    /// Draw what follows in canvas pixels, ignoring the camera, on top of what came before.
+    /// Useful for HUDs and menus.
+    pub fn begin_ui(&mut self) {
+        self.low_res_pass.begin(DrawSpace::Ui);
+    }
+
+    /// Set how sprite positions are rounded to canvas pixels.
+    /// The cameras should be set before drawing images, because rounding is relative to the camera.
+    pub fn set_pixel_rounding(&mut self, pixel_rounding: PixelRounding) {
+        self.low_res_pass.set_pixel_rounding(pixel_rounding);
+    }
+
+    /// Set the background color of the canvas, wherever nothing is drawn.
+    /// Defaults to a dark green.
+    pub fn set_clear_color(&mut self, clear_color: wgpu::Color) {
+        self.low_res_pass.set_clear_color(clear_color);
+    }
+
+    pub fn clear_color(&self) -> wgpu::Color {
+        self.low_res_pass.clear_color()
+    }
+
+    /// Change the size of the low res canvas.
+    /// A larger canvas shows more of the world, with smaller pixels.
+    pub fn set_canvas_size(&mut self, canvas_width: u32, canvas_height: u32) {
+        self.low_res_pass
+            .set_canvas_size(&self.device, canvas_width, canvas_height);
+        self.surface_pass
+            .set_low_res_texture_view(&self.device, &self.low_res_pass.low_res_texture_view);
+        self.configure_surface();
+    }
+
+    fn window_size(&self) -> glam::Vec2 {
+        let window_inner_size = self.window.inner_size();
+        glam::Vec2::new(
+            window_inner_size.width as f32,
+            window_inner_size.height as f32,
+        )
+    }
+
+    /// Convert a position in window pixels, such as the cursor's, to canvas pixels.
+    /// Positions in the letterbox bars are outside the canvas.
+    pub fn window_to_canvas(&self, window_position: glam::Vec2) -> glam::Vec2 {
+        window_to_canvas(window_position, self.canvas_size(), self.window_size())
+    }
+
+    pub fn set_window_title(&self, title: &str) {
+        self.window.set_title(title);
+    }
+
+    /// The size of the low res canvas, in pixels.
+    pub fn canvas_size(&self) -> glam::Vec2 {
+        glam::Vec2::new(
+            self.low_res_pass.low_res_texture.width() as f32,
+            self.low_res_pass.low_res_texture.height() as f32,
+        )
+    }
+
+    /// The part of the world that is visible, in world coordinates.
+    /// With several cameras, this is the smallest rectangle containing everything they see.
+    pub fn visible_bounds(&self) -> Rectangle {
+        self.cameras()
+            .iter()
+            .map(Camera::bounds)
+            .reduce(|a, b| {
+                Rectangle::new(
+                    a.top_left.min(b.top_left),
+                    a.bottom_right.max(b.bottom_right),
+                )
+            })
+            .unwrap()
+    }
+
+    /// Copy the canvas, as of the last `draw`, into an image the size of the canvas.
+    /// When rendering directly to the window, the canvas isn't drawn to, so turn that off first.
+    /// Waits for the GPU, so this is meant for screenshots and debugging, not every frame.
+    pub fn capture_frame(&self) -> Result<image::RgbaImage, RendererError> {
+        self.low_res_pass.capture(&self.device, &self.queue)
+    }
+
+    /// Load a sprite, or return the index of the same sprite if it was already loaded.
+    /// Sprites stay loaded for the life of the renderer.
     pub fn load_sprite(&mut self, sprite: Sprite) -> SpriteIndex {
-        self.low_res_pass.load_sprite(&self.queue, sprite)
+        self.low_res_pass
+            .load_sprite(&self.device, &self.queue, sprite)
     }
 
-    pub fn draw_image(
+    /// Load many sprites up front, so decoding and uploading them doesn't cause a hitch later.
+    /// Sprites that are already loaded are not loaded again.
+    pub fn preload(&mut self, sprites: &[Sprite]) -> Vec<SpriteIndex> {
+        sprites
+            .iter()
+            .map(|sprite| self.load_sprite(sprite.clone()))
+            .collect()
+    }
+
+    /// The image's location is snapped to the nearest canvas pixel, unless changed with
+    /// `set_pixel_rounding`.
+    pub fn draw_image(&mut self, image: DrawImage) {
+        self.low_res_pass.draw_image(image)
+    }
+
+    /// Draw a filled or outlined rectangle in a solid color.
+    pub fn draw_rectangle(
         &mut self,
-        sprite_index: SpriteIndex,
-        sprite_z: f32,
-        location: glam::Vec2,
+        top_left: glam::Vec2,
         size: glam::Vec2,
+        color: glam::Vec4,
+        style: RectangleStyle,
     ) {
         self.low_res_pass
-            .draw_image(sprite_index, sprite_z, location, size)
+            .draw_rectangle(top_left, size, color, style)
     }
 
-    pub fn draw_rectangle(&mut self, location: glam::Vec2, width_height: glam::Vec2) {
-        self.low_res_pass.draw_rectangle(location, width_height)
+    /// Draw a line in a solid color, such as a velocity vector for debugging.
+    /// Like rectangles, lines are drawn over the sprites.
+    pub fn draw_line(&mut self, a: glam::Vec2, b: glam::Vec2, thickness: f32, color: glam::Vec4) {
+        self.low_res_pass.draw_line(a, b, thickness, color)
+    }
+
+    /// Draw lines connecting each point to the next, such as a path.
+    pub fn draw_polyline(&mut self, points: &[glam::Vec2], thickness: f32, color: glam::Vec4) {
+        for segment in points.windows(2) {
+            self.low_res_pass
+                .draw_line(segment[0], segment[1], thickness, color);
+        }
     }
 
     pub fn draw(&mut self) {
@@ -766,9 +2338,1034 @@ impl Renderer {
                 .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                     label: Some("command encoder"),
                 });
-        self.low_res_pass.draw(&self.queue, &mut command_encoder);
-        self.surface_pass.draw(&mut command_encoder, &surface_view);
+        if self.can_render_direct() {
+            self.low_res_pass.draw(
+                &self.device,
+                &self.queue,
+                &mut command_encoder,
+                Some(&surface_view),
+            );
+        } else {
+            self.low_res_pass
+                .draw(&self.device, &self.queue, &mut command_encoder, None);
+            self.surface_pass.draw(&mut command_encoder, &surface_view);
+        }
         self.queue.submit([command_encoder.finish()]);
         surface_texture.present();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt as _;
+
+    use super::{
+        canvas_scales, check_shader, choose_surface_format, color_square, draw_calls,
+        grown_buffer_size, grown_sprites_size, line_quad, load_shader, next_atlas_id,
+        padded_bytes_per_row, readback_image, square, square_outline, window_to_canvas, AlphaMode,
+        Camera, CameraUniform, DrawBatch, DrawCall, DrawImage, DrawSpace, LowResPass,
+        PixelRounding, Rectangle, RendererError, Rotation, ShaderWatcher, SplitScreen, Sprite,
+        SpriteColorSpace, SpriteIndex, SpriteOrientation, TextureVertex, Viewport,
+        DEFAULT_CLEAR_COLOR, EMBEDDED_LOW_RES_SHADER, EMBEDDED_SURFACE_SHADER,
+        INITIAL_VERTEX_BUFFER_SIZE, LOW_RES_SHADER_FILE, SQUARE_VERTS, SURFACE_SHADER_FILE,
+        TEXTURE_VERTEX_ATTRIBUTES,
+    };
+
+    #[test]
+    fn test_sprite_orientation_sample_uv() {
+        let top_left = glam::Vec2::new(0.0, 0.0);
+        let top_right = glam::Vec2::new(1.0, 0.0);
+        let bottom_left = glam::Vec2::new(0.0, 1.0);
+        let bottom_right = glam::Vec2::new(1.0, 1.0);
+        let orientation = |flip_x, flip_y, rotation| SpriteOrientation {
+            flip_x,
+            flip_y,
+            rotation,
+        };
+        assert_eq!(SpriteOrientation::default().sample_uv(top_left), top_left);
+        assert_eq!(
+            orientation(true, false, Rotation::None).sample_uv(top_left),
+            top_right
+        );
+        assert_eq!(
+            orientation(false, true, Rotation::None).sample_uv(top_left),
+            bottom_left
+        );
+        // Rotating clockwise moves the bottom left of the sprite to the top left.
+        assert_eq!(
+            orientation(false, false, Rotation::Clockwise90).sample_uv(top_left),
+            bottom_left
+        );
+        assert_eq!(
+            orientation(false, false, Rotation::Clockwise180).sample_uv(top_left),
+            bottom_right
+        );
+        assert_eq!(
+            orientation(false, false, Rotation::Clockwise270).sample_uv(top_left),
+            top_right
+        );
+    }
+
+    #[test]
+    fn test_square_outline_stays_inside_rectangle() {
+        let position = glam::Vec2::new(10.0, 20.0);
+        let width_height = glam::Vec2::new(30.0, 8.0);
+        for thickness in [1.0, 3.0, 100.0] {
+            for vertex in square_outline(position, width_height, thickness, glam::Vec4::ONE) {
+                assert!(vertex.position.x >= position.x);
+                assert!(vertex.position.y >= position.y);
+                assert!(vertex.position.x <= position.x + width_height.x);
+                assert!(vertex.position.y <= position.y + width_height.y);
+            }
+        }
+        let top_edge = &square_outline(position, width_height, 3.0, glam::Vec4::ONE)[0..6];
+        let top_edge_height = top_edge
+            .iter()
+            .map(|vertex| vertex.position.y - position.y)
+            .fold(0.0, f32::max);
+        assert_eq!(top_edge_height, 3.0);
+    }
+
+    #[test]
+    fn test_line_quad() {
+        let vertices = line_quad(
+            glam::Vec2::new(10.0, 10.0),
+            glam::Vec2::new(20.0, 10.0),
+            4.0,
+            glam::Vec4::ONE,
+        );
+        assert_eq!(vertices.len(), 6);
+        let positions: Vec<glam::Vec2> = vertices
+            .iter()
+            .map(|vertex| vertex.position.truncate())
+            .collect();
+        // A horizontal line is offset vertically by half its thickness on each side.
+        for position in &positions {
+            assert!(position.y == 8.0 || position.y == 12.0);
+            assert!(position.x == 10.0 || position.x == 20.0);
+        }
+        for corner in [
+            glam::Vec2::new(10.0, 8.0),
+            glam::Vec2::new(10.0, 12.0),
+            glam::Vec2::new(20.0, 8.0),
+            glam::Vec2::new(20.0, 12.0),
+        ] {
+            assert!(positions.contains(&corner));
+        }
+        // A diagonal line is offset perpendicular to itself.
+        let vertices = line_quad(glam::Vec2::ZERO, glam::Vec2::ONE, 2.0, glam::Vec4::ONE);
+        let offset = vertices[0].position.truncate();
+        assert!((offset.length() - 1.0).abs() < 1e-6);
+        assert!(offset.dot(glam::Vec2::ONE).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_color_square_color() {
+        let red = glam::Vec4::new(1.0, 0.0, 0.0, 1.0);
+        for vertex in color_square(glam::Vec2::ZERO, glam::Vec2::ONE, red) {
+            assert_eq!(vertex.color, [1.0, 0.0, 0.0, 1.0]);
+        }
+    }
+
+    #[test]
+    fn test_sprite_color_space_texture_format() {
+        assert_eq!(
+            SpriteColorSpace::default().texture_format(),
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        );
+        assert_eq!(
+            SpriteColorSpace::Linear.texture_format(),
+            wgpu::TextureFormat::Rgba8Unorm
+        );
+        assert!(!SpriteColorSpace::Linear.texture_format().is_srgb());
+    }
+
+    /// A fresh, empty directory for a test to write shaders into.
+    fn temp_shader_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "pikuma_game_engine_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_load_shader_from_dir() {
+        let dir = temp_shader_dir("valid_shader");
+        let source = format!("// An external copy.\n{}", EMBEDDED_SURFACE_SHADER);
+        std::fs::write(dir.join(SURFACE_SHADER_FILE), &source).unwrap();
+        let loaded = load_shader(Some(&dir), SURFACE_SHADER_FILE, EMBEDDED_SURFACE_SHADER).unwrap();
+        assert_eq!(loaded, source);
+        // A missing shader directory falls back to the embedded shader.
+        let missing = dir.join("missing");
+        let loaded =
+            load_shader(Some(&missing), SURFACE_SHADER_FILE, EMBEDDED_SURFACE_SHADER).unwrap();
+        assert_eq!(loaded, EMBEDDED_SURFACE_SHADER);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn test_set_clear_color() {
+        let (device, _queue) = test_device();
+        let mut low_res_pass = LowResPass::new(
+            &device,
+            64,
+            48,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            SpriteColorSpace::default(),
+            EMBEDDED_LOW_RES_SHADER,
+        );
+        // Games that don't set a clear color keep the original dark green background.
+        assert_eq!(low_res_pass.clear_color(), DEFAULT_CLEAR_COLOR);
+        low_res_pass.set_clear_color(wgpu::Color::BLACK);
+        assert_eq!(low_res_pass.clear_color(), wgpu::Color::BLACK);
+    }
+
+    /// Notifications arrive on another thread, so wait for them to settle.
+    fn settled_changed_files(watcher: &mut ShaderWatcher) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        let start = std::time::Instant::now();
+        while changed.is_empty() && start.elapsed() < std::time::Duration::from_secs(5) {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            changed = watcher.changed_files();
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        for file_name in watcher.changed_files() {
+            if !changed.contains(&file_name) {
+                changed.push(file_name);
+            }
+        }
+        changed
+    }
+
+    #[test]
+    fn test_shader_watcher() {
+        let dir = temp_shader_dir("watched_shader");
+        let path = dir.join(SURFACE_SHADER_FILE);
+        std::fs::write(&path, EMBEDDED_SURFACE_SHADER).unwrap();
+        let mut watcher =
+            ShaderWatcher::new(&dir, &[LOW_RES_SHADER_FILE, SURFACE_SHADER_FILE]).unwrap();
+        assert!(watcher.changed_files().is_empty());
+        std::fs::write(&path, EMBEDDED_SURFACE_SHADER).unwrap();
+        assert_eq!(
+            settled_changed_files(&mut watcher),
+            vec![SURFACE_SHADER_FILE]
+        );
+        assert!(watcher.changed_files().is_empty());
+        // A new file counts as a change, but other files in the directory don't.
+        std::fs::write(dir.join("notes.txt"), "").unwrap();
+        std::fs::write(dir.join(LOW_RES_SHADER_FILE), EMBEDDED_LOW_RES_SHADER).unwrap();
+        assert_eq!(
+            settled_changed_files(&mut watcher),
+            vec![LOW_RES_SHADER_FILE]
+        );
+        // A deleted file isn't a change.
+        std::fs::remove_file(&path).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        assert!(watcher.changed_files().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn test_device() -> (wgpu::Device, wgpu::Queue) {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions::default())
+            .block_on()
+            .expect("no GPU adapter");
+        adapter
+            .request_device(&wgpu::DeviceDescriptor::default(), None)
+            .block_on()
+            .unwrap()
+    }
+
+    /// Draws 8 x 8 test sprites over each other in the middle of a 16 x 16 canvas, in the given
+    /// order, and returns the color of the middle pixel.
+    fn draw_overlapping(sprites: &[(&str, f32, AlphaMode)]) -> image::Rgba<u8> {
+        let (device, queue) = test_device();
+        let mut low_res_pass = LowResPass::new(
+            &device,
+            16,
+            16,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            SpriteColorSpace::default(),
+            EMBEDDED_LOW_RES_SHADER,
+        );
+        low_res_pass.set_clear_color(wgpu::Color::BLACK);
+        for (file, z, alpha_mode) in sprites {
+            let sprite_index = low_res_pass.load_sprite(
+                &device,
+                &queue,
+                Sprite::new(
+                    std::path::Path::new("assets/images/test").join(file),
+                    glam::UVec2::ZERO,
+                    glam::UVec2::new(8, 8),
+                ),
+            );
+            low_res_pass.draw_image(DrawImage {
+                z: *z,
+                alpha_mode: *alpha_mode,
+                ..DrawImage::new(
+                    sprite_index,
+                    glam::Vec2::new(4.0, 4.0),
+                    glam::Vec2::new(8.0, 8.0),
+                )
+            });
+        }
+        let mut command_encoder = device.create_command_encoder(&Default::default());
+        low_res_pass.draw(&device, &queue, &mut command_encoder, None);
+        queue.submit([command_encoder.finish()]);
+        *low_res_pass
+            .capture(&device, &queue)
+            .unwrap()
+            .get_pixel(8, 8)
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn test_depth_ignores_draw_order() {
+        // Sprites aren't sorted before drawing; the depth buffer keeps the higher z on top.
+        let red = image::Rgba([255, 0, 0, 255]);
+        assert_eq!(
+            draw_overlapping(&[
+                ("red.png", 1.0, AlphaMode::Cutout),
+                ("green.png", 0.5, AlphaMode::Cutout),
+            ]),
+            red
+        );
+        assert_eq!(
+            draw_overlapping(&[
+                ("green.png", 0.5, AlphaMode::Cutout),
+                ("red.png", 1.0, AlphaMode::Cutout),
+            ]),
+            red
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn test_blend_over_cutout() {
+        let image::Rgba([r, g, b, _]) = draw_overlapping(&[
+            ("green.png", 0.5, AlphaMode::Cutout),
+            ("translucent-blue.png", 1.0, AlphaMode::Blend),
+        ]);
+        assert_eq!(r, 0);
+        assert!(g > 0 && g < 255, "green {g} should show through");
+        assert!(b > 0 && b < 255, "blue {b} should be blended");
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn test_reload_shader_replaces_pipeline() {
+        let (device, _queue) = test_device();
+        let mut low_res_pass = LowResPass::new(
+            &device,
+            64,
+            48,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            SpriteColorSpace::default(),
+            EMBEDDED_LOW_RES_SHADER,
+        );
+        let pipeline_id = low_res_pass.pipeline.global_id();
+        low_res_pass
+            .reload_shader(&device, EMBEDDED_LOW_RES_SHADER)
+            .unwrap();
+        let reloaded_pipeline_id = low_res_pass.pipeline.global_id();
+        assert_ne!(reloaded_pipeline_id, pipeline_id);
+        // A shader that doesn't build keeps the pipeline that was working.
+        let result = low_res_pass.reload_shader(
+            &device,
+            "@vertex fn vertex_main() -> @builtin(position) vec4f { return vec4f(0.0); }",
+        );
+        assert!(matches!(result, Err(RendererError::ShaderCompile { .. })));
+        assert_eq!(low_res_pass.pipeline.global_id(), reloaded_pipeline_id);
+    }
+
+    #[test]
+    fn test_load_shader_compile_error() {
+        let dir = temp_shader_dir("invalid_shader");
+        std::fs::write(
+            dir.join(LOW_RES_SHADER_FILE),
+            "@vertex fn vertex_main() -> @builtin(position) vec4f { return 1.0; }",
+        )
+        .unwrap();
+        let result = load_shader(Some(&dir), LOW_RES_SHADER_FILE, EMBEDDED_LOW_RES_SHADER);
+        assert!(matches!(
+            result,
+            Err(RendererError::ShaderCompile { ref file, .. }) if file == LOW_RES_SHADER_FILE
+        ));
+        // A shader directory missing one of the files is an error, not a silent fallback.
+        let result = load_shader(Some(&dir), SURFACE_SHADER_FILE, EMBEDDED_SURFACE_SHADER);
+        assert!(matches!(result, Err(RendererError::ShaderIo { .. })));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_embedded_shaders_are_valid() {
+        assert!(check_shader(LOW_RES_SHADER_FILE, EMBEDDED_LOW_RES_SHADER).is_ok());
+        assert!(check_shader(SURFACE_SHADER_FILE, EMBEDDED_SURFACE_SHADER).is_ok());
+    }
+
+    #[test]
+    fn test_choose_surface_format() {
+        assert_eq!(
+            choose_surface_format(&[
+                wgpu::TextureFormat::Bgra8Unorm,
+                wgpu::TextureFormat::Bgra8UnormSrgb,
+            ])
+            .unwrap(),
+            wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+        assert_eq!(
+            choose_surface_format(&[wgpu::TextureFormat::Rgba16Float]).unwrap(),
+            wgpu::TextureFormat::Rgba16Float
+        );
+        assert!(matches!(
+            choose_surface_format(&[]),
+            Err(RendererError::NoSupportedFormat)
+        ));
+    }
+
+    /// Runs `world_to_ndc` from low_res.wgsl on the GPU, in a compute shader added to the shader.
+    fn world_to_ndc(camera: &Camera, position: glam::Vec3) -> glam::Vec2 {
+        use wgpu::util::DeviceExt as _;
+
+        let (device, queue) = test_device();
+        let shader_source = format!(
+            "{EMBEDDED_LOW_RES_SHADER}
+            @group(0) @binding(3) var<storage, read> world_position: vec4f;
+            @group(0) @binding(4) var<storage, read_write> ndc_position: vec4f;
+
+            @compute @workgroup_size(1)
+            fn world_to_ndc_main() {{
+                ndc_position = world_to_ndc(world_position.xyz);
+            }}"
+        );
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(LOW_RES_SHADER_FILE),
+            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: None,
+            module: &shader,
+            entry_point: "world_to_ndc_main",
+        });
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::bytes_of(&CameraUniform::from(*camera)),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let world_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::bytes_of(&position.extend(1.0)),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let ndc_size = std::mem::size_of::<glam::Vec4>() as u64;
+        let ndc_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: ndc_size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: ndc_size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: camera_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: world_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: ndc_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        let mut command_encoder = device.create_command_encoder(&Default::default());
+        {
+            let mut compute_pass = command_encoder.begin_compute_pass(&Default::default());
+            compute_pass.set_pipeline(&pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(1, 1, 1);
+        }
+        command_encoder.copy_buffer_to_buffer(&ndc_buffer, 0, &readback_buffer, 0, ndc_size);
+        queue.submit([command_encoder.finish()]);
+        readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, |result| result.unwrap());
+        device.poll(wgpu::Maintain::Wait);
+        let ndc: glam::Vec4 = *bytemuck::from_bytes(&readback_buffer.slice(..).get_mapped_range());
+        ndc.truncate().truncate()
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn test_rectangle_at_camera_origin() {
+        let camera = Camera {
+            top_left: glam::Vec2::ZERO,
+            width_height: glam::Vec2::new(800.0, 600.0),
+            zoom: 1.0,
+        };
+        let vertices = color_square(
+            glam::Vec2::ZERO,
+            glam::Vec2::new(400.0, 300.0),
+            glam::Vec4::new(1.0, 0.0, 0.0, 1.0),
+        );
+        assert_eq!(
+            world_to_ndc(&camera, vertices[0].position),
+            glam::Vec2::new(-1.0, -1.0)
+        );
+        assert_eq!(
+            world_to_ndc(&camera, vertices[2].position),
+            glam::Vec2::new(0.0, 0.0)
+        );
+        assert!(vertices
+            .iter()
+            .all(|vertex| vertex.color == [1.0, 0.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn test_rectangle_offset_by_camera() {
+        let camera = Camera {
+            top_left: glam::Vec2::new(100.0, 50.0),
+            width_height: glam::Vec2::new(800.0, 600.0),
+            zoom: 1.0,
+        };
+        // A rectangle at the camera's top left is drawn where one at the origin would be
+        // with an unmoved camera.
+        let vertices = color_square(
+            camera.top_left,
+            glam::Vec2::new(400.0, 300.0),
+            glam::Vec4::ONE,
+        );
+        assert_eq!(
+            world_to_ndc(&camera, vertices[0].position),
+            glam::Vec2::new(-1.0, -1.0)
+        );
+        assert_eq!(
+            world_to_ndc(&camera, vertices[2].position),
+            glam::Vec2::new(0.0, 0.0)
+        );
+        // And a rectangle at the origin is off the top left of the screen.
+        let vertices = color_square(
+            glam::Vec2::ZERO,
+            glam::Vec2::new(100.0, 50.0),
+            glam::Vec4::ONE,
+        );
+        let bottom_right = world_to_ndc(&camera, vertices[2].position);
+        assert_eq!(bottom_right, glam::Vec2::new(-1.0, -1.0));
+    }
+
+    #[test]
+    fn test_camera_bounds() {
+        let camera = Camera {
+            top_left: glam::Vec2::new(100.0, 50.0),
+            width_height: glam::Vec2::new(800.0, 600.0),
+            zoom: 1.0,
+        };
+        let bounds = camera.bounds();
+        assert_eq!(bounds.top_left, glam::Vec2::new(100.0, 50.0));
+        assert_eq!(bounds.bottom_right, glam::Vec2::new(900.0, 650.0));
+        assert!(bounds.contains_point(glam::Vec2::new(500.0, 300.0)));
+        assert!(!bounds.contains_point(glam::Vec2::new(50.0, 300.0)));
+    }
+
+    #[test]
+    fn test_camera_zoom_identity() {
+        let camera = Camera {
+            top_left: glam::Vec2::new(100.0, 50.0),
+            width_height: glam::Vec2::new(800.0, 600.0),
+            zoom: 1.0,
+        };
+        assert_eq!(
+            camera.bounds(),
+            Rectangle::from_top_left(camera.top_left, camera.width_height)
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn test_world_to_ndc_zoom_identity() {
+        let camera = Camera {
+            top_left: glam::Vec2::new(100.0, 50.0),
+            width_height: glam::Vec2::new(800.0, 600.0),
+            zoom: 1.0,
+        };
+        let position = glam::Vec3::new(300.0, 200.0, 0.0);
+        assert_eq!(
+            world_to_ndc(&camera, position),
+            (position.truncate() - camera.top_left) / camera.width_height * 2.0 - 1.0
+        );
+    }
+
+    #[test]
+    fn test_camera_zoom_in() {
+        let camera = Camera {
+            top_left: glam::Vec2::new(100.0, 50.0),
+            width_height: glam::Vec2::new(800.0, 600.0),
+            zoom: 2.0,
+        };
+        // Half as much of the world is visible, around the same center.
+        let bounds = camera.bounds();
+        assert_eq!(bounds.width_height(), glam::Vec2::new(400.0, 300.0));
+        assert_eq!(bounds.center(), glam::Vec2::new(500.0, 350.0));
+        // Pixels are rounded to the zoomed canvas, so there are 2 canvas pixels per world unit.
+        let canvas_size = glam::Vec2::new(800.0, 600.0);
+        assert_eq!(
+            PixelRounding::Nearest.round(glam::Vec2::new(320.3, 210.8), &camera, canvas_size),
+            glam::Vec2::new(320.5, 211.0)
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn test_world_to_ndc_zoom_in() {
+        let camera = Camera {
+            top_left: glam::Vec2::new(100.0, 50.0),
+            width_height: glam::Vec2::new(800.0, 600.0),
+            zoom: 2.0,
+        };
+        // The visible bounds fill the screen.
+        let bounds = camera.bounds();
+        assert_eq!(
+            world_to_ndc(&camera, bounds.top_left.extend(0.0)),
+            glam::Vec2::new(-1.0, -1.0)
+        );
+        assert_eq!(
+            world_to_ndc(&camera, bounds.bottom_right.extend(0.0)),
+            glam::Vec2::new(1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn test_readback_image() {
+        assert_eq!(padded_bytes_per_row(64), 256);
+        assert_eq!(padded_bytes_per_row(65), 512);
+        // Two rows of two pixels, each row padded to the copy alignment.
+        let size = glam::UVec2::new(2, 2);
+        let padded_bytes_per_row = padded_bytes_per_row(size.x);
+        let mut data = vec![0; (padded_bytes_per_row * size.y) as usize];
+        data[0..8].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        let second_row = padded_bytes_per_row as usize;
+        data[second_row..second_row + 8].copy_from_slice(&[9, 10, 11, 12, 13, 14, 15, 16]);
+        let image = readback_image(
+            &data,
+            size,
+            padded_bytes_per_row,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+        );
+        assert_eq!(image.get_pixel(1, 0), &image::Rgba([5, 6, 7, 8]));
+        assert_eq!(image.get_pixel(0, 1), &image::Rgba([9, 10, 11, 12]));
+        // BGRA is reordered.
+        let image = readback_image(
+            &data,
+            size,
+            padded_bytes_per_row,
+            wgpu::TextureFormat::Bgra8Unorm,
+        );
+        assert_eq!(image.get_pixel(1, 1), &image::Rgba([15, 14, 13, 16]));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn test_capture_clear_color() {
+        let (device, queue) = test_device();
+        // Wider than the row alignment, and not a multiple of it, so rows are padded.
+        let mut low_res_pass = LowResPass::new(
+            &device,
+            70,
+            30,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            SpriteColorSpace::default(),
+            EMBEDDED_LOW_RES_SHADER,
+        );
+        low_res_pass.set_clear_color(wgpu::Color {
+            r: 1.0,
+            g: 0.0,
+            b: 1.0,
+            a: 1.0,
+        });
+        let mut command_encoder = device.create_command_encoder(&Default::default());
+        low_res_pass.draw(&device, &queue, &mut command_encoder, None);
+        queue.submit([command_encoder.finish()]);
+        let image = low_res_pass.capture(&device, &queue).unwrap();
+        assert_eq!(image.dimensions(), (70, 30));
+        assert!(image
+            .pixels()
+            .all(|pixel| *pixel == image::Rgba([255, 0, 255, 255])));
+    }
+
+    #[test]
+    fn test_window_to_canvas() {
+        let canvas_size = glam::Vec2::new(800.0, 600.0);
+        // The same aspect ratio, scaled up.
+        let window_size = glam::Vec2::new(1600.0, 1200.0);
+        assert_eq!(canvas_scales(canvas_size, window_size), glam::Vec2::ONE);
+        assert_eq!(
+            window_to_canvas(glam::Vec2::new(200.0, 1000.0), canvas_size, window_size),
+            glam::Vec2::new(100.0, 500.0)
+        );
+        // A wide window has bars on the left and right.
+        let window_size = glam::Vec2::new(1600.0, 600.0);
+        assert_eq!(
+            canvas_scales(canvas_size, window_size),
+            glam::Vec2::new(0.5, 1.0)
+        );
+        assert_eq!(
+            window_to_canvas(glam::Vec2::new(400.0, 0.0), canvas_size, window_size),
+            glam::Vec2::ZERO
+        );
+        assert_eq!(
+            window_to_canvas(glam::Vec2::new(1200.0, 600.0), canvas_size, window_size),
+            canvas_size
+        );
+        assert!(window_to_canvas(glam::Vec2::new(100.0, 300.0), canvas_size, window_size).x < 0.0);
+        // A tall window has bars on the top and bottom.
+        let window_size = glam::Vec2::new(400.0, 600.0);
+        assert_eq!(
+            window_to_canvas(glam::Vec2::new(200.0, 300.0), canvas_size, window_size),
+            glam::Vec2::new(400.0, 300.0)
+        );
+        assert_eq!(
+            window_to_canvas(glam::Vec2::new(0.0, 150.0), canvas_size, window_size),
+            glam::Vec2::ZERO
+        );
+    }
+
+    #[test]
+    fn test_split_screen_viewports() {
+        let canvas_size = glam::UVec2::new(801, 600);
+        assert_eq!(
+            SplitScreen::SideBySide.viewports(canvas_size, 1),
+            vec![Viewport {
+                top_left: glam::UVec2::ZERO,
+                size: canvas_size,
+            }]
+        );
+        // The odd pixel goes to one of the viewports, rather than being left unused.
+        assert_eq!(
+            SplitScreen::SideBySide.viewports(canvas_size, 2),
+            vec![
+                Viewport {
+                    top_left: glam::UVec2::new(0, 0),
+                    size: glam::UVec2::new(400, 600),
+                },
+                Viewport {
+                    top_left: glam::UVec2::new(400, 0),
+                    size: glam::UVec2::new(401, 600),
+                },
+            ]
+        );
+        assert_eq!(
+            SplitScreen::Stacked.viewports(canvas_size, 2),
+            vec![
+                Viewport {
+                    top_left: glam::UVec2::new(0, 0),
+                    size: glam::UVec2::new(801, 300),
+                },
+                Viewport {
+                    top_left: glam::UVec2::new(0, 300),
+                    size: glam::UVec2::new(801, 300),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_draw_calls_per_camera() {
+        let canvas_size = glam::UVec2::new(800, 600);
+        let viewports = SplitScreen::SideBySide.viewports(canvas_size, 2);
+        // World sprites and shapes, then UI, then more world.
+        let batches = [
+            DrawBatch {
+                space: DrawSpace::World,
+                sprite_start: 0,
+                shape_vertex_start: 0,
+            },
+            DrawBatch {
+                space: DrawSpace::Ui,
+                sprite_start: 3,
+                shape_vertex_start: 6,
+            },
+            DrawBatch {
+                space: DrawSpace::World,
+                sprite_start: 4,
+                shape_vertex_start: 6,
+            },
+        ];
+        let draw_calls = draw_calls(&batches, 5, 12, &viewports, canvas_size);
+        let world = |camera: usize, sprites, shape_vertices| DrawCall {
+            viewport: viewports[camera],
+            camera: Some(camera),
+            sprites,
+            shape_vertices,
+        };
+        assert_eq!(
+            draw_calls,
+            vec![
+                world(0, 0..3, 0..6),
+                world(1, 0..3, 0..6),
+                DrawCall {
+                    viewport: Viewport {
+                        top_left: glam::UVec2::ZERO,
+                        size: canvas_size,
+                    },
+                    camera: None,
+                    sprites: 3..4,
+                    shape_vertices: 6..6,
+                },
+                world(0, 4..5, 6..12),
+                world(1, 4..5, 6..12),
+            ]
+        );
+        assert_eq!(draw_calls[1].viewport.top_left, glam::UVec2::new(400, 0));
+        assert_eq!(draw_calls[1].viewport.size, glam::UVec2::new(400, 600));
+    }
+
+    #[test]
+    fn test_pixel_rounding() {
+        let camera = Camera {
+            top_left: glam::Vec2::new(10.25, 0.0),
+            width_height: glam::Vec2::new(400.0, 300.0),
+            zoom: 1.0,
+        };
+        // The canvas is twice the size of the camera, so there are 2 canvas pixels per world unit.
+        let canvas_size = glam::Vec2::new(800.0, 600.0);
+        let position = glam::Vec2::new(20.0, 5.3);
+        assert_eq!(
+            PixelRounding::None.round(position, &camera, canvas_size),
+            position
+        );
+        assert_eq!(
+            PixelRounding::Nearest.round(position, &camera, canvas_size),
+            glam::Vec2::new(20.25, 5.5)
+        );
+        assert_eq!(
+            PixelRounding::Floor.round(position, &camera, canvas_size),
+            glam::Vec2::new(19.75, 5.0)
+        );
+    }
+
+    #[test]
+    fn test_grown_buffer_size() {
+        assert_eq!(grown_buffer_size(100_000, 0), None);
+        assert_eq!(grown_buffer_size(100_000, 100_000), None);
+        assert_eq!(grown_buffer_size(100_000, 100_001), Some(131_072));
+        assert_eq!(grown_buffer_size(131_072, 300_000), Some(524_288));
+        // Enough sprites to overflow the initial buffer.
+        let sprite_bytes = std::mem::size_of::<TextureVertex>() * SQUARE_VERTS as usize;
+        let required_size = (sprite_bytes * 10_000) as u64;
+        let new_size = grown_buffer_size(INITIAL_VERTEX_BUFFER_SIZE, required_size).unwrap();
+        assert!(new_size >= required_size);
+        assert!(new_size.is_power_of_two());
+    }
+
+    #[test]
+    fn test_grown_sprites_size() {
+        let size = glam::UVec2::new;
+        assert_eq!(grown_sprites_size(size(32, 32), size(16, 32)), None);
+        assert_eq!(grown_sprites_size(size(32, 32), size(32, 32)), None);
+        assert_eq!(
+            grown_sprites_size(size(32, 32), size(64, 64)),
+            Some(size(64, 64))
+        );
+        // Layers only ever grow, so they still fit every sprite loaded before.
+        assert_eq!(
+            grown_sprites_size(size(64, 32), size(16, 48)),
+            Some(size(64, 48))
+        );
+    }
+
+    /// A 32 x 32 sprite drawn at its own size.
+    fn image(location: glam::Vec2) -> DrawImage {
+        DrawImage::new(
+            SpriteIndex::for_tests(0),
+            location,
+            glam::Vec2::new(32.0, 32.0),
+        )
+    }
+
+    #[test]
+    fn test_square_tint() {
+        let red = glam::Vec4::new(1.0, 0.0, 0.0, 1.0);
+        let vertices = square(
+            DrawImage {
+                tint: red,
+                ..image(glam::Vec2::ZERO)
+            },
+            glam::UVec2::new(32, 32),
+        );
+        let tint_offset = TEXTURE_VERTEX_ATTRIBUTES[4].offset as usize;
+        let red_bytes: &[u8] = bytemuck::bytes_of(&red);
+        for vertex in vertices {
+            let vertex_bytes: &[u8] = bytemuck::bytes_of(&vertex);
+            assert_eq!(&vertex_bytes[tint_offset..tint_offset + 16], red_bytes);
+        }
+    }
+
+    #[test]
+    fn test_square_flip_x() {
+        let uvs = |flip_x| {
+            square(
+                DrawImage {
+                    orientation: SpriteOrientation {
+                        flip_x,
+                        flip_y: false,
+                        rotation: Rotation::None,
+                    },
+                    ..image(glam::Vec2::ZERO)
+                },
+                glam::UVec2::new(32, 32),
+            )
+            .map(|vertex| vertex.uv)
+        };
+        let flipped = uvs(true);
+        for (unflipped_uv, flipped_uv) in uvs(false).iter().zip(flipped) {
+            assert_eq!(
+                flipped_uv,
+                glam::Vec2::new(1.0 - unflipped_uv.x, unflipped_uv.y)
+            );
+        }
+        // The top left corner of the quad samples the top right of the sprite.
+        assert_eq!(flipped[0], glam::Vec2::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn test_square_fractional_position() {
+        // `square` keeps fractional positions; `LowResPass::draw_image` rounds them first.
+        let vertices = square(
+            image(glam::Vec2::new(10.5, 20.25)),
+            glam::UVec2::new(32, 32),
+        );
+        assert_eq!(
+            vertices[0].position.truncate(),
+            glam::Vec2::new(10.5, 20.25)
+        );
+        assert_eq!(
+            vertices[2].position.truncate(),
+            glam::Vec2::new(42.5, 52.25)
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn test_draw_image_pixel_rounding() {
+        let (device, queue) = test_device();
+        let mut low_res_pass = LowResPass::new(
+            &device,
+            64,
+            48,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            SpriteColorSpace::default(),
+            EMBEDDED_LOW_RES_SHADER,
+        );
+        let sprite_index = low_res_pass.load_sprite(
+            &device,
+            &queue,
+            Sprite::new(
+                "assets/images/test/red.png".into(),
+                glam::UVec2::ZERO,
+                glam::UVec2::new(8, 8),
+            ),
+        );
+        let mut top_left = |pixel_rounding| {
+            low_res_pass.set_pixel_rounding(pixel_rounding);
+            low_res_pass.vertex_buffer_cpu.clear();
+            low_res_pass.draw_image(DrawImage::new(
+                sprite_index,
+                glam::Vec2::new(10.5, 20.25),
+                glam::Vec2::new(8.0, 8.0),
+            ));
+            let vertices: &[TextureVertex] = bytemuck::cast_slice(&low_res_pass.vertex_buffer_cpu);
+            vertices[0].position.truncate()
+        };
+        // Fractional positions are snapped to the nearest canvas pixel by default.
+        assert_eq!(
+            top_left(PixelRounding::default()),
+            glam::Vec2::new(11.0, 20.0)
+        );
+        assert_eq!(top_left(PixelRounding::None), glam::Vec2::new(10.5, 20.25));
+    }
+
+    #[test]
+    fn test_square_depth() {
+        let depth = |z| {
+            square(
+                DrawImage {
+                    z,
+                    ..image(glam::Vec2::ZERO)
+                },
+                glam::UVec2::new(32, 32),
+            )[0]
+            .position
+            .z
+        };
+        // Higher sprites get a lower depth, so they pass the depth test over lower sprites.
+        assert!(depth(1.0) < depth(0.5));
+        assert!(depth(0.5) < depth(0.0));
+        // The depth buffer is cleared to 1.0, and even the lowest sprites must pass over that.
+        assert_eq!(depth(0.0), 1.0);
+        assert_eq!(depth(-1.0), 1.0);
+        assert_eq!(depth(2.0), 0.0);
+    }
+
+    #[test]
+    fn test_square_alpha_cutoff() {
+        let vertices = |alpha_mode| {
+            square(
+                DrawImage {
+                    alpha_mode,
+                    ..image(glam::Vec2::ZERO)
+                },
+                glam::UVec2::new(32, 32),
+            )
+        };
+        assert!(vertices(AlphaMode::Blend)
+            .iter()
+            .all(|vertex| vertex.alpha_cutoff == 0.0));
+        assert!(vertices(AlphaMode::Cutout)
+            .iter()
+            .all(|vertex| vertex.alpha_cutoff > 0.0));
+    }
+
+    #[test]
+    fn test_palette_swap() {
+        let red = image::Rgba([255, 0, 0, 255]);
+        let blue = image::Rgba([0, 0, 255, 255]);
+        let green = image::Rgba([0, 255, 0, 255]);
+        let sprite = Sprite::new(
+            "assets/images/tank-panther-right.png".into(),
+            glam::UVec2::ZERO,
+            glam::UVec2::new(2, 1),
+        );
+        let swapped_sprite = sprite.clone().with_palette_swap(vec![(red, blue)]);
+        assert_ne!(sprite, swapped_sprite);
+        let mut image = image::RgbaImage::from_pixel(2, 1, red);
+        image.put_pixel(1, 0, green);
+        swapped_sprite.apply_palette_swap(&mut image);
+        assert_eq!(*image.get_pixel(0, 0), blue);
+        assert_eq!(*image.get_pixel(1, 0), green);
+    }
+
+    #[test]
+    fn test_next_atlas_id_is_unique() {
+        let a = next_atlas_id();
+        let b = next_atlas_id();
+        assert_ne!(a, b);
+    }
+}
@@ -2,35 +2,62 @@
 struct Camera {
     @location(0) top_left: vec2f,
     @location(1) width_height: vec2f,
+    @location(2) zoom: f32,
 };
 
 struct TextureVertex {
     @location(0) position: vec3f,
     @location(1) uv: vec2f,
     @location(2) lower_right: vec3u,
+    @location(3) alpha_cutoff: f32,
+    @location(4) tint: vec4f,
 };
 
 struct TextureFragment {
     @builtin(position) position: vec4f,
     @location(1) uv: vec2f,
     @location(2) @interpolate(flat) lower_right: vec3u,
+    @location(3) @interpolate(flat) alpha_cutoff: f32,
+    @location(4) @interpolate(flat) tint: vec4f,
+};
+
+struct ColorVertex {
+    @location(0) position: vec3f,
+    @location(1) color: vec4f,
+};
+
+struct ColorFragment {
+    @builtin(position) position: vec4f,
+    @location(0) color: vec4f,
 };
 
 @group(0) @binding(0) var<uniform> camera: Camera;
 @group(0) @binding(1) var textures_sampler: sampler;
 @group(0) @binding(2) var textures: texture_2d_array<f32>;
 
+// Adjust coordinates in our world space (e.g., somewhere in the 800 x 600 grid)
+// to normalized device coordinates (NDC, e.g., somewhere in the -1 to 1 range).
+// Zoom scales positions around the center of the camera first.
+fn world_to_ndc(position: vec3f) -> vec4f {
+    let center = camera.top_left + camera.width_height / 2.0;
+    let zoomed = (position.xy - center) * camera.zoom + center;
+    return vec4f(
+        (zoomed.x - camera.top_left.x) / f32(camera.width_height.x) * 2.0 - 1.0,
+        (zoomed.y - camera.top_left.y) / f32(camera.width_height.y) * 2.0 - 1.0,
+        position.z,
+        1.0,
+    );
+}
+
 @vertex
 fn vertex_main(vertex: TextureVertex) -> TextureFragment {
-    // Adjust coordinates in our world space (e.g., somewhere in the 800 x 600 grid)
-    // to normalized device coordinates (NDC, e.g., somewhere in the -1 to 1 range).
-    let ndc = vec4f(
-        (vertex.position.x - camera.top_left.x) / f32(camera.width_height.x) * 2.0 - 1.0,
-        (vertex.position.y - camera.top_left.y) / f32(camera.width_height.y) * 2.0 - 1.0,
-        vertex.position.z,
-        1.0,
+    return TextureFragment(
+        world_to_ndc(vertex.position),
+        vertex.uv,
+        vertex.lower_right,
+        vertex.alpha_cutoff,
+        vertex.tint,
     );
-    return TextureFragment(ndc, vertex.uv, vertex.lower_right);
 }
 
 @fragment
@@ -40,10 +67,28 @@ fn fragment_main(fragment: TextureFragment) -> @location(0) vec4f {
         fragment.uv.x * (f32(fragment.lower_right.x) / f32(full_dims.x)),
         fragment.uv.y * (f32(fragment.lower_right.y) / f32(full_dims.y)),
     );
-    return textureSample(textures, textures_sampler, adjusted_uv, fragment.lower_right.z);
+    let color = textureSample(textures, textures_sampler, adjusted_uv, fragment.lower_right.z)
+        * fragment.tint;
+    // A cutoff of zero means the sprite is alpha blended.
+    if fragment.alpha_cutoff > 0.0 {
+        if color.a < fragment.alpha_cutoff {
+            discard;
+        }
+        return vec4f(color.rgb, 1.0);
+    }
+    // Don't let fully transparent pixels write to the depth buffer and hide what's behind them.
+    if color.a == 0.0 {
+        discard;
+    }
+    return color;
+}
+
+@vertex
+fn vertex_color(vertex: ColorVertex) -> ColorFragment {
+    return ColorFragment(world_to_ndc(vertex.position), vertex.color);
 }
 
 @fragment
-fn fragment_line(fragment: TextureFragment) -> @location(0) vec4f {
-    return vec4f(1.0, 1.0, 0.0, 1.0);
+fn fragment_color(fragment: ColorFragment) -> @location(0) vec4f {
+    return fragment.color;
 }
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+
+use rand::seq::SliceRandom as _;
+
+use crate::renderer::{Rotation, Sprite, SpriteOrientation};
+
+// Map files use the same flag bits as Tiled to flip and rotate tiles.
+// See: https://doc.mapeditor.org/en/stable/reference/global-tile-ids/
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+const TILE_INDEX_MASK: u32 = !(FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    /// Index of the tile in the tile set.
+    pub index: u32,
+    pub orientation: SpriteOrientation,
+}
+
+impl Tile {
+    /// Decode a tile from a map file value, which may have flip flags in its high bits.
+    pub fn decode(value: u32) -> Self {
+        let horizontal = value & FLIPPED_HORIZONTALLY != 0;
+        let vertical = value & FLIPPED_VERTICALLY != 0;
+        let diagonal = value & FLIPPED_DIAGONALLY != 0;
+        // Tiled flips diagonally first, then horizontally, then vertically.
+        // A diagonal flip combined with the other flips is equivalent to a
+        // quarter turn followed by (at most) a horizontal flip.
+        let orientation = if diagonal {
+            SpriteOrientation {
+                flip_x: horizontal == vertical,
+                flip_y: false,
+                rotation: if vertical {
+                    Rotation::Clockwise270
+                } else {
+                    Rotation::Clockwise90
+                },
+            }
+        } else {
+            SpriteOrientation {
+                flip_x: horizontal,
+                flip_y: vertical,
+                rotation: Rotation::None,
+            }
+        };
+        Self {
+            index: value & TILE_INDEX_MASK,
+            orientation,
+        }
+    }
+}
+
+/// Weighted sprite variants for tile indices.
+/// When a map is loaded, a tile with variants is drawn with one of them, chosen at random.
+#[derive(Default)]
+pub struct TileVariants {
+    variants: HashMap<u32, Vec<(Sprite, f32)>>,
+}
+
+impl TileVariants {
+    pub fn new() -> Self {
+        Self {
+            variants: HashMap::new(),
+        }
+    }
+
+    /// Add a sprite that may be used for the given tile index.
+    /// Variants with a larger weight are chosen more often.
+    /// A variant with zero weight is never chosen.
+    pub fn add_variant(&mut self, tile_index: u32, sprite: Sprite, weight: f32) {
+        assert!(
+            weight.is_finite() && weight >= 0.0,
+            "tile variant weight must be finite and non-negative, got {weight}"
+        );
+        self.variants
+            .entry(tile_index)
+            .or_default()
+            .push((sprite, weight));
+    }
+
+    /// Choose a sprite for the tile index, or None if the tile index has no variants.
+    /// Panics if every variant of the tile index has zero weight.
+    pub fn choose<R: rand::Rng>(&self, tile_index: u32, rng: &mut R) -> Option<&Sprite> {
+        let (sprite, _weight) = self
+            .variants
+            .get(&tile_index)?
+            .choose_weighted(rng, |(_sprite, weight)| *weight)
+            .unwrap_or_else(|error| {
+                panic!("can't choose a variant for tile {tile_index}: {error}")
+            });
+        Some(sprite)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Tile, TileVariants, FLIPPED_DIAGONALLY, FLIPPED_HORIZONTALLY, FLIPPED_VERTICALLY};
+    use crate::renderer::{Rotation, Sprite, SpriteOrientation};
+    use rand::SeedableRng as _;
+
+    #[test]
+    fn test_decode_plain_tile() {
+        assert_eq!(
+            Tile::decode(21),
+            Tile {
+                index: 21,
+                orientation: SpriteOrientation::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_decode_flipped_tiles() {
+        let decode = |flags: u32| Tile::decode(7 | flags);
+        let orientation = |flip_x, flip_y, rotation| SpriteOrientation {
+            flip_x,
+            flip_y,
+            rotation,
+        };
+        assert_eq!(decode(FLIPPED_HORIZONTALLY).index, 7);
+        assert_eq!(
+            decode(FLIPPED_HORIZONTALLY).orientation,
+            orientation(true, false, Rotation::None)
+        );
+        assert_eq!(
+            decode(FLIPPED_VERTICALLY).orientation,
+            orientation(false, true, Rotation::None)
+        );
+        assert_eq!(
+            decode(FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY).orientation,
+            orientation(true, true, Rotation::None)
+        );
+        assert_eq!(
+            decode(FLIPPED_DIAGONALLY | FLIPPED_HORIZONTALLY).orientation,
+            orientation(false, false, Rotation::Clockwise90)
+        );
+        assert_eq!(
+            decode(FLIPPED_DIAGONALLY | FLIPPED_VERTICALLY).orientation,
+            orientation(false, false, Rotation::Clockwise270)
+        );
+        assert_eq!(
+            decode(FLIPPED_DIAGONALLY).orientation,
+            orientation(true, false, Rotation::Clockwise90)
+        );
+        assert_eq!(
+            decode(FLIPPED_DIAGONALLY | FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY).orientation,
+            orientation(true, false, Rotation::Clockwise270)
+        );
+    }
+
+    fn grass(column: u32) -> Sprite {
+        Sprite::new(
+            "assets/tilemaps/jungle.png".into(),
+            glam::UVec2::new(32 * column, 0),
+            glam::UVec2::new(32, 32),
+        )
+    }
+
+    #[test]
+    fn test_tile_variants_choose() {
+        let mut tile_variants = TileVariants::new();
+        tile_variants.add_variant(21, grass(1), 3.0);
+        tile_variants.add_variant(21, grass(2), 1.0);
+        tile_variants.add_variant(21, grass(3), 0.0);
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        assert_eq!(tile_variants.choose(20, &mut rng), None);
+        let chosen: Vec<Sprite> = (0..1000)
+            .map(|_| tile_variants.choose(21, &mut rng).unwrap().clone())
+            .collect();
+        let count = |sprite: Sprite| chosen.iter().filter(|s| **s == sprite).count();
+        assert_eq!(count(grass(3)), 0);
+        assert!(count(grass(1)) > count(grass(2)));
+        assert!(count(grass(2)) > 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "can't choose a variant for tile 21")]
+    fn test_tile_variants_all_zero_weight() {
+        let mut tile_variants = TileVariants::new();
+        tile_variants.add_variant(21, grass(1), 0.0);
+        tile_variants.add_variant(21, grass(2), 0.0);
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        tile_variants.choose(21, &mut rng);
+    }
+
+    #[test]
+    #[should_panic(expected = "must be finite and non-negative")]
+    fn test_tile_variants_negative_weight() {
+        TileVariants::new().add_variant(21, grass(1), -1.0);
+    }
+
+    #[test]
+    fn test_tile_variants_are_reproducible() {
+        let mut tile_variants = TileVariants::new();
+        tile_variants.add_variant(21, grass(1), 1.0);
+        tile_variants.add_variant(21, grass(2), 1.0);
+        let choose_all = |seed: u64| -> Vec<Sprite> {
+            let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+            (0..100)
+                .map(|_| tile_variants.choose(21, &mut rng).unwrap().clone())
+                .collect()
+        };
+        assert_eq!(choose_all(7), choose_all(7));
+        assert_ne!(choose_all(7), choose_all(8));
+    }
+}
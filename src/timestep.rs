@@ -0,0 +1,88 @@
+/// The simulation's step size, in seconds.
+pub const FIXED_DELTA_SECONDS: f32 = 1.0 / 60.0;
+
+/// The longest frame time fed to systems, in seconds.
+pub const MAX_DELTA_SECONDS: f32 = 0.1;
+
+/// Limit a frame's elapsed time to at most `max_delta`.
+/// After a stall, such as dragging the window, the real frame time can be huge.
+/// Moving entities that far in one step would tunnel them through colliders, and running
+/// enough fixed steps to catch up could make the next frame slow too, spiralling downward.
+/// Clamping trades a brief slowdown, where the game runs slower than real time, for stability.
+pub fn clamp_delta(delta: f32, max_delta: f32) -> f32 {
+    delta.clamp(0.0, max_delta)
+}
+
+/// Turns varying frame times into a whole number of fixed size simulation steps.
+/// Time left over from one frame carries into the next, so over many frames
+/// the simulation keeps pace with real time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixedTimestep {
+    step: f32,
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    pub fn new(step: f32) -> Self {
+        assert!(step > 0.0, "fixed timestep must be positive");
+        Self {
+            step,
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Add a frame's elapsed time, and return how many steps to run.
+    pub fn advance(&mut self, elapsed: f32) -> u32 {
+        self.accumulator += elapsed;
+        let steps = (self.accumulator / self.step).floor();
+        self.accumulator -= steps * self.step;
+        steps as u32
+    }
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(FIXED_DELTA_SECONDS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clamp_delta, FixedTimestep, MAX_DELTA_SECONDS};
+
+    #[test]
+    fn test_clamp_delta() {
+        assert_eq!(clamp_delta(1.0 / 60.0, MAX_DELTA_SECONDS), 1.0 / 60.0);
+        assert_eq!(
+            clamp_delta(MAX_DELTA_SECONDS, MAX_DELTA_SECONDS),
+            MAX_DELTA_SECONDS
+        );
+        assert_eq!(clamp_delta(3.5, MAX_DELTA_SECONDS), MAX_DELTA_SECONDS);
+        assert_eq!(clamp_delta(0.5, 0.25), 0.25);
+    }
+
+    #[test]
+    fn test_fixed_timestep() {
+        let mut timestep = FixedTimestep::new(0.25);
+        assert_eq!(timestep.advance(0.1), 0);
+        assert_eq!(timestep.advance(0.1), 0);
+        // The leftover time from earlier frames adds up to a step.
+        assert_eq!(timestep.advance(0.1), 1);
+        // A slow frame runs several steps.
+        assert_eq!(timestep.advance(1.0), 4);
+        // 0.05 seconds are still left over.
+        assert_eq!(timestep.advance(0.19), 0);
+        assert_eq!(timestep.advance(0.02), 1);
+    }
+
+    #[test]
+    fn test_fixed_timestep_keeps_pace() {
+        let mut timestep = FixedTimestep::new(1.0 / 60.0);
+        let steps: u32 = (0..144).map(|_| timestep.advance(1.0 / 144.0)).sum();
+        assert!((59..=60).contains(&steps), "{steps}");
+    }
+}
@@ -0,0 +1,151 @@
+use pikuma_game_engine::components_systems::{
+    spawn_one_shot_animation, AnimationComponent, AnimationFinishedEvent, AnimationSystem, Layer,
+    RigidBodyComponent, SpriteComponent,
+};
+use pikuma_game_engine::ecs::{Entity, EntityComponentWrapper, Registry};
+use pikuma_game_engine::event_bus::Handler;
+use pikuma_game_engine::impl_handler_base;
+use pikuma_game_engine::renderer::SpriteIndex;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[derive(Default)]
+struct FinishedRecorder {
+    finished: Vec<Entity>,
+}
+
+impl Handler<AnimationFinishedEvent> for FinishedRecorder {
+    fn handle(&mut self, _ec_manager: &mut EntityComponentWrapper, event: &AnimationFinishedEvent) {
+        self.finished.push(event.entity);
+    }
+}
+
+impl_handler_base!(FinishedRecorder: AnimationFinishedEvent);
+
+fn setup(
+    animation_component: AnimationComponent,
+) -> (Registry, Entity, Rc<RefCell<FinishedRecorder>>) {
+    let mut registry = Registry::new();
+    registry.add_system(Rc::new(RefCell::new(AnimationSystem::new())));
+    let recorder = Rc::new(RefCell::new(FinishedRecorder::default()));
+    registry.add_handler::<AnimationFinishedEvent, _>(Rc::clone(&recorder));
+    let entity = registry
+        .entity_builder()
+        .with(SpriteComponent::new(
+            SpriteIndex::for_tests(0),
+            Layer::Air,
+            glam::Vec2::new(16.0, 16.0),
+        ))
+        .with(animation_component)
+        .build();
+    (registry, entity, recorder)
+}
+
+/// The sprite shown after each of several runs of the animation system.
+fn frames_shown(registry: &mut Registry, entity: Entity, runs: usize) -> Vec<SpriteIndex> {
+    (0..runs)
+        .map(|_| {
+            registry.run_system::<AnimationSystem>(1.1).unwrap();
+            let sprite_component: &SpriteComponent =
+                registry.get_component(entity).unwrap().unwrap();
+            sprite_component.sprite_index
+        })
+        .collect()
+}
+
+#[test]
+fn test_looping_animation() {
+    let frames = vec![
+        SpriteIndex::for_tests(0),
+        SpriteIndex::for_tests(1),
+        SpriteIndex::for_tests(2),
+    ];
+    let (mut registry, entity, recorder) = setup(AnimationComponent::new(1.0, frames));
+    assert_eq!(
+        frames_shown(&mut registry, entity, 5),
+        vec![1, 2, 0, 1, 2]
+            .into_iter()
+            .map(SpriteIndex::for_tests)
+            .collect::<Vec<_>>()
+    );
+    assert!(recorder.borrow().finished.is_empty());
+}
+
+#[test]
+fn test_one_shot_animation() {
+    let frames = vec![
+        SpriteIndex::for_tests(0),
+        SpriteIndex::for_tests(1),
+        SpriteIndex::for_tests(2),
+    ];
+    let (mut registry, entity, recorder) = setup(AnimationComponent::new_one_shot(1.0, frames));
+    assert_eq!(
+        frames_shown(&mut registry, entity, 2),
+        vec![SpriteIndex::for_tests(1), SpriteIndex::for_tests(2)]
+    );
+    assert!(recorder.borrow().finished.is_empty());
+    // It stays on the last frame, and only finishes once.
+    assert_eq!(
+        frames_shown(&mut registry, entity, 3),
+        vec![SpriteIndex::for_tests(2); 3]
+    );
+    assert_eq!(recorder.borrow().finished, vec![entity]);
+    let animation_component: &AnimationComponent = registry.get_component(entity).unwrap().unwrap();
+    assert!(animation_component.finished);
+    assert_eq!(animation_component.current_frame, 2);
+}
+
+#[test]
+fn test_spawn_one_shot_animation() {
+    let mut registry = Registry::new();
+    registry.add_system(Rc::new(RefCell::new(AnimationSystem::new())));
+    let frames = vec![
+        SpriteIndex::for_tests(0),
+        SpriteIndex::for_tests(1),
+        SpriteIndex::for_tests(2),
+    ];
+    let entity = spawn_one_shot_animation(
+        &mut registry,
+        glam::Vec2::new(50.0, 50.0),
+        glam::Vec2::new(16.0, 16.0),
+        frames,
+        0.1,
+    );
+    let rigid_body_component: &RigidBodyComponent =
+        registry.get_component(entity).unwrap().unwrap();
+    assert_eq!(rigid_body_component.position, glam::Vec2::new(42.0, 42.0));
+    // Three frames of 0.1 seconds, run at 60 FPS, take about 18 runs.
+    for _ in 0..17 {
+        registry.run_system::<AnimationSystem>(1.0 / 60.0).unwrap();
+    }
+    assert!(registry.is_alive(entity));
+    for _ in 0..4 {
+        registry.run_system::<AnimationSystem>(1.0 / 60.0).unwrap();
+    }
+    assert!(registry.is_dead(entity));
+}
+
+#[test]
+#[should_panic(expected = "at least one frame")]
+fn test_spawn_one_shot_animation_without_frames() {
+    let mut registry = Registry::new();
+    spawn_one_shot_animation(
+        &mut registry,
+        glam::Vec2::ZERO,
+        glam::Vec2::new(16.0, 16.0),
+        vec![],
+        0.1,
+    );
+}
+
+#[test]
+fn test_load_animation_without_looping() {
+    // Saved before animations could stop.
+    let animation_component: AnimationComponent = serde_json::from_str(
+        r#"{"frames": [], "frame_time": 0.1, "current_frame": 0, "current_frame_time": 0.0}"#,
+    )
+    .unwrap();
+    assert!(animation_component.looping);
+    assert!(!animation_component.finished);
+    assert!(!animation_component.despawn_when_finished);
+}
@@ -0,0 +1,76 @@
+use pikuma_game_engine::components_systems::CameraFocusComponent;
+
+const VIEWPORT_SIZE: glam::Vec2 = glam::Vec2::new(200.0, 100.0);
+
+fn camera_focus(zoom: f32) -> CameraFocusComponent {
+    CameraFocusComponent {
+        focus_offset: glam::Vec2::ZERO,
+        map_top_left: glam::Vec2::ZERO,
+        map_bottom_right: glam::Vec2::new(1000.0, 500.0),
+        zoom,
+        slot: 0,
+    }
+}
+
+fn center(focus: glam::Vec2, zoom: f32) -> glam::Vec2 {
+    let camera = camera_focus(zoom).camera(focus, VIEWPORT_SIZE);
+    assert_eq!(camera.width_height, VIEWPORT_SIZE);
+    camera.bounds().center()
+}
+
+#[test]
+fn test_camera_centers_on_focus_in_interior() {
+    let focus = glam::Vec2::new(500.0, 250.0);
+    assert_eq!(center(focus, 1.0), focus);
+    let camera = camera_focus(1.0).camera(focus, VIEWPORT_SIZE);
+    assert_eq!(camera.top_left, glam::Vec2::new(400.0, 200.0));
+}
+
+#[test]
+fn test_camera_stops_at_each_edge() {
+    // Left
+    assert_eq!(
+        center(glam::Vec2::new(30.0, 250.0), 1.0),
+        glam::Vec2::new(100.0, 250.0)
+    );
+    // Right
+    assert_eq!(
+        center(glam::Vec2::new(990.0, 250.0), 1.0),
+        glam::Vec2::new(900.0, 250.0)
+    );
+    // Top
+    assert_eq!(
+        center(glam::Vec2::new(500.0, -20.0), 1.0),
+        glam::Vec2::new(500.0, 50.0)
+    );
+    // Bottom
+    assert_eq!(
+        center(glam::Vec2::new(500.0, 480.0), 1.0),
+        glam::Vec2::new(500.0, 450.0)
+    );
+}
+
+#[test]
+fn test_camera_stops_at_corner() {
+    let camera = camera_focus(1.0).camera(glam::Vec2::new(1200.0, 600.0), VIEWPORT_SIZE);
+    assert_eq!(camera.bounds().bottom_right, glam::Vec2::new(1000.0, 500.0));
+    // Zoomed in, less is visible, so the camera can get closer to the corner.
+    let camera = camera_focus(2.0).camera(glam::Vec2::new(1200.0, 600.0), VIEWPORT_SIZE);
+    assert_eq!(camera.bounds().bottom_right, glam::Vec2::new(1000.0, 500.0));
+    assert_eq!(camera.bounds().center(), glam::Vec2::new(950.0, 475.0));
+}
+
+#[test]
+fn test_camera_centers_small_map() {
+    let camera_focus = CameraFocusComponent {
+        focus_offset: glam::Vec2::ZERO,
+        map_top_left: glam::Vec2::ZERO,
+        map_bottom_right: glam::Vec2::new(150.0, 500.0),
+        zoom: 1.0,
+        slot: 0,
+    };
+    // The map is narrower than the viewport, so it's centered horizontally,
+    // but the camera still follows the focus vertically.
+    let camera = camera_focus.camera(glam::Vec2::new(10.0, 250.0), VIEWPORT_SIZE);
+    assert_eq!(camera.bounds().center(), glam::Vec2::new(75.0, 250.0));
+}
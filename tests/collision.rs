@@ -0,0 +1,112 @@
+use pikuma_game_engine::components_systems::{
+    BroadPhase, ColliderShape, CollisionComponent, CollisionEvent, CollisionSystem,
+    RigidBodyComponent,
+};
+use pikuma_game_engine::ecs::{Entity, EntityComponentWrapper, Registry};
+use pikuma_game_engine::event_bus::Handler;
+use pikuma_game_engine::impl_handler_base;
+use rand::{Rng, SeedableRng};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[derive(Default)]
+struct CollisionRecorder {
+    events: Vec<(Entity, Entity, glam::Vec2, glam::Vec2)>,
+}
+
+impl Handler<CollisionEvent> for CollisionRecorder {
+    fn handle(&mut self, _ec_manager: &mut EntityComponentWrapper, event: &CollisionEvent) {
+        self.events.push((
+            event.entity_a,
+            event.entity_b,
+            event.overlap,
+            event.contact_point,
+        ));
+    }
+}
+
+impl_handler_base!(CollisionRecorder: CollisionEvent);
+
+#[test]
+fn test_spatial_hash_matches_brute_force() {
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(1536);
+    let mut registry = Registry::new();
+    let collision_system = Rc::new(RefCell::new(CollisionSystem::new()));
+    registry.add_system(Rc::clone(&collision_system));
+    let recorder = Rc::new(RefCell::new(CollisionRecorder::default()));
+    registry.add_handler::<CollisionEvent, _>(Rc::clone(&recorder));
+    for _ in 0..300 {
+        let shape = if rng.gen() {
+            ColliderShape::Rectangle {
+                width_height: glam::Vec2::new(rng.gen_range(1.0..40.0), rng.gen_range(1.0..40.0)),
+            }
+        } else {
+            ColliderShape::Circle {
+                radius: rng.gen_range(1.0..20.0),
+            }
+        };
+        registry
+            .entity_builder()
+            .with(RigidBodyComponent {
+                position: glam::Vec2::new(
+                    rng.gen_range(-200.0..400.0),
+                    rng.gen_range(-200.0..400.0),
+                ),
+                velocity: glam::Vec2::ZERO,
+                acceleration: glam::Vec2::ZERO,
+                affected_by_gravity: false,
+                max_speed: None,
+            })
+            .with(CollisionComponent {
+                offset: glam::Vec2::ZERO,
+                shape,
+            })
+            .build();
+    }
+
+    collision_system
+        .borrow_mut()
+        .set_broad_phase(BroadPhase::BruteForce);
+    registry.run_system::<CollisionSystem>(()).unwrap();
+    let brute_force = std::mem::take(&mut recorder.borrow_mut().events);
+    assert!(!brute_force.is_empty());
+
+    for cell_size in [8.0, 32.0, 100.0] {
+        let mut system = collision_system.borrow_mut();
+        system.set_broad_phase(BroadPhase::SpatialHash);
+        system.set_spatial_hash_cell_size(cell_size);
+        drop(system);
+        registry.run_system::<CollisionSystem>(()).unwrap();
+        let spatial_hash = std::mem::take(&mut recorder.borrow_mut().events);
+        assert_eq!(spatial_hash, brute_force, "cell size {cell_size}");
+    }
+}
+
+#[test]
+fn test_spatial_hash_oversized_collider() {
+    let mut registry = Registry::new();
+    let collision_system = Rc::new(RefCell::new(CollisionSystem::new()));
+    // Far more cells than the collider could be put in, one at a time.
+    collision_system
+        .borrow_mut()
+        .set_spatial_hash_cell_size(0.01);
+    registry.add_system(Rc::clone(&collision_system));
+    let recorder = Rc::new(RefCell::new(CollisionRecorder::default()));
+    registry.add_handler::<CollisionEvent, _>(Rc::clone(&recorder));
+    let mut spawn = |position: glam::Vec2, width_height: glam::Vec2| {
+        registry
+            .entity_builder()
+            .with(RigidBodyComponent::at(position))
+            .with(CollisionComponent {
+                offset: glam::Vec2::ZERO,
+                shape: ColliderShape::Rectangle { width_height },
+            })
+            .build()
+    };
+    let level = spawn(glam::Vec2::ZERO, glam::Vec2::new(10_000.0, 10_000.0));
+    let player = spawn(glam::Vec2::new(500.0, 500.0), glam::Vec2::ONE);
+    registry.run_system::<CollisionSystem>(()).unwrap();
+    assert!(collision_system.borrow().is_colliding(level));
+    assert!(collision_system.borrow().is_colliding(player));
+    assert_eq!(recorder.borrow().events.len(), 1);
+}
@@ -0,0 +1,13 @@
+use pikuma_game_engine::components_systems::RigidBodyComponent;
+use pikuma_game_engine::ecs::{Entity, Registry};
+
+/// Spawns an entity with only a rigid body, at the position and moving with the velocity.
+pub fn spawn_body(registry: &mut Registry, position: glam::Vec2, velocity: glam::Vec2) -> Entity {
+    registry
+        .entity_builder()
+        .with(RigidBodyComponent {
+            velocity,
+            ..RigidBodyComponent::at(position)
+        })
+        .build()
+}
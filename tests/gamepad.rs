@@ -0,0 +1,86 @@
+use pikuma_game_engine::components_systems::{
+    add_update_systems, run_fixed_update_systems, GamepadControlComponent, GamepadControlSystem,
+    RigidBodyComponent,
+};
+use pikuma_game_engine::ecs::Registry;
+use pikuma_game_engine::gamepad::StickState;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+fn close(a: glam::Vec2, b: glam::Vec2) -> bool {
+    (a - b).length() < 1e-4
+}
+
+#[test]
+fn test_deadzone() {
+    // Inside the deadzone is centered.
+    assert_eq!(
+        StickState::from_axes(0.1, 0.05).with_deadzone(0.2),
+        glam::Vec2::ZERO
+    );
+    // The edge of the deadzone is still centered, and full tilt is still full.
+    assert_eq!(
+        StickState::from_axes(0.2, 0.0).with_deadzone(0.2),
+        glam::Vec2::ZERO
+    );
+    assert!(close(
+        StickState::from_axes(1.0, 0.0).with_deadzone(0.2),
+        glam::Vec2::new(1.0, 0.0)
+    ));
+    // Halfway between the deadzone and full tilt is half speed.
+    assert!(close(
+        StickState::from_axes(0.0, 0.6).with_deadzone(0.2),
+        glam::Vec2::new(0.0, -0.5)
+    ));
+    // Diagonals in the corner of the stick's square range aren't faster than straight lines.
+    assert!(close(
+        StickState::from_axes(1.0, 1.0).with_deadzone(0.2),
+        glam::Vec2::new(1.0, -1.0).normalize()
+    ));
+}
+
+#[test]
+fn test_stick_sets_velocity() {
+    let mut registry = Registry::new();
+    let mut gamepad_control_system = GamepadControlSystem::new();
+    gamepad_control_system.set_deadzone(0.2);
+    registry.add_system(Rc::new(RefCell::new(gamepad_control_system)));
+    let player = registry
+        .entity_builder()
+        .with(RigidBodyComponent {
+            velocity: glam::Vec2::new(5.0, 5.0),
+            ..RigidBodyComponent::default()
+        })
+        .with(GamepadControlComponent)
+        .build();
+    let velocity = |registry: &Registry| {
+        let rigid_body: &RigidBodyComponent = registry.get_component(player).unwrap().unwrap();
+        rigid_body.velocity
+    };
+    // Pushing the stick up moves up the screen, toward negative y.
+    registry
+        .run_system::<GamepadControlSystem>(StickState::from_axes(0.0, 1.0))
+        .unwrap();
+    assert!(close(velocity(&registry), glam::Vec2::new(0.0, -80.0)));
+    // Letting go stops.
+    registry
+        .run_system::<GamepadControlSystem>(StickState::from_axes(0.05, -0.1))
+        .unwrap();
+    assert_eq!(velocity(&registry), glam::Vec2::ZERO);
+}
+
+#[test]
+fn test_stick_state_resource() {
+    let mut registry = Registry::new();
+    add_update_systems(&mut registry);
+    let player = registry
+        .entity_builder()
+        .with(RigidBodyComponent::default())
+        .with(GamepadControlComponent)
+        .build();
+    registry.insert_resource(StickState::from_axes(1.0, 0.0));
+    run_fixed_update_systems(&mut registry, &HashSet::new(), 0.0).unwrap();
+    let rigid_body: &RigidBodyComponent = registry.get_component(player).unwrap().unwrap();
+    assert!(close(rigid_body.velocity, glam::Vec2::new(80.0, 0.0)));
+}
@@ -0,0 +1,74 @@
+use pikuma_game_engine::components_systems::{
+    add_update_systems, DamageEvent, DeathEvent, HealthComponent,
+};
+use pikuma_game_engine::ecs::{Entity, EntityComponentWrapper, Registry};
+use pikuma_game_engine::event_bus::Handler;
+use pikuma_game_engine::impl_handler_base;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[derive(Default)]
+struct DeathRecorder {
+    deaths: Vec<Entity>,
+}
+
+impl Handler<DeathEvent> for DeathRecorder {
+    fn handle(&mut self, _ec_manager: &mut EntityComponentWrapper, event: &DeathEvent) {
+        self.deaths.push(event.entity);
+    }
+}
+
+impl_handler_base!(DeathRecorder: DeathEvent);
+
+fn setup() -> (Registry, Entity, Rc<RefCell<DeathRecorder>>) {
+    let mut registry = Registry::new();
+    add_update_systems(&mut registry);
+    let recorder = Rc::new(RefCell::new(DeathRecorder::default()));
+    registry.add_handler::<DeathEvent, _>(Rc::clone(&recorder));
+    let tank = registry
+        .entity_builder()
+        .with(HealthComponent {
+            current: 80.0,
+            max: 100.0,
+        })
+        .build();
+    (registry, tank, recorder)
+}
+
+fn damage(registry: &mut Registry, target: Entity, amount: f32) {
+    registry.run_once(|ec_manager| ec_manager.dispatch_event(DamageEvent { target, amount }));
+}
+
+fn health(registry: &Registry, entity: Entity) -> f32 {
+    let health: &HealthComponent = registry.get_component(entity).unwrap().unwrap();
+    health.current
+}
+
+#[test]
+fn test_partial_damage() {
+    let (mut registry, tank, recorder) = setup();
+    damage(&mut registry, tank, 30.0);
+    assert!(registry.is_alive(tank));
+    assert_eq!(health(&registry, tank), 50.0);
+    assert!(recorder.borrow().deaths.is_empty());
+}
+
+#[test]
+fn test_lethal_damage() {
+    let (mut registry, tank, recorder) = setup();
+    damage(&mut registry, tank, 50.0);
+    damage(&mut registry, tank, 50.0);
+    assert!(registry.is_dead(tank));
+    assert_eq!(recorder.borrow().deaths, vec![tank]);
+    // Damaging a dead entity does nothing.
+    damage(&mut registry, tank, 50.0);
+    assert_eq!(recorder.borrow().deaths, vec![tank]);
+}
+
+#[test]
+fn test_over_heal_clamps_to_max() {
+    let (mut registry, tank, recorder) = setup();
+    damage(&mut registry, tank, -50.0);
+    assert_eq!(health(&registry, tank), 100.0);
+    assert!(recorder.borrow().deaths.is_empty());
+}
@@ -0,0 +1,99 @@
+use pikuma_game_engine::components_systems::{
+    add_update_systems, remove_entity_recursive, run_update_systems, ParentComponent,
+    RigidBodyComponent,
+};
+use pikuma_game_engine::ecs::{Entity, Registry};
+use std::collections::HashSet;
+
+mod common;
+use common::spawn_body;
+
+fn attach(registry: &mut Registry, child: Entity, parent: Entity, local_offset: glam::Vec2) {
+    registry
+        .add_component(
+            child,
+            ParentComponent {
+                parent,
+                local_offset,
+            },
+        )
+        .unwrap();
+}
+
+fn position(registry: &Registry, entity: Entity) -> glam::Vec2 {
+    let rigid_body: &RigidBodyComponent = registry.get_component(entity).unwrap().unwrap();
+    rigid_body.position
+}
+
+#[test]
+fn test_two_level_chain_follows_parent() {
+    let mut registry = Registry::new();
+    add_update_systems(&mut registry);
+    let tank = spawn_body(
+        &mut registry,
+        glam::Vec2::new(0.0, 0.0),
+        glam::Vec2::new(10.0, 0.0),
+    );
+    // Attach the gun to the turret before the turret to the tank,
+    // so the gun's parent isn't always positioned first by accident.
+    let gun = spawn_body(&mut registry, glam::Vec2::ZERO, glam::Vec2::ZERO);
+    let turret = spawn_body(&mut registry, glam::Vec2::ZERO, glam::Vec2::ZERO);
+    attach(&mut registry, gun, turret, glam::Vec2::new(8.0, 0.0));
+    attach(&mut registry, turret, tank, glam::Vec2::new(4.0, 4.0));
+    run_update_systems(&mut registry, &HashSet::new(), 1.0, None).unwrap();
+    assert_eq!(position(&registry, tank), glam::Vec2::new(10.0, 0.0));
+    assert_eq!(position(&registry, turret), glam::Vec2::new(14.0, 4.0));
+    assert_eq!(position(&registry, gun), glam::Vec2::new(22.0, 4.0));
+}
+
+#[test]
+fn test_child_detached_when_parent_removed() {
+    let mut registry = Registry::new();
+    add_update_systems(&mut registry);
+    let tank = spawn_body(
+        &mut registry,
+        glam::Vec2::new(0.0, 0.0),
+        glam::Vec2::new(10.0, 0.0),
+    );
+    let turret = spawn_body(&mut registry, glam::Vec2::ZERO, glam::Vec2::ZERO);
+    attach(&mut registry, turret, tank, glam::Vec2::new(4.0, 4.0));
+    run_update_systems(&mut registry, &HashSet::new(), 1.0, None).unwrap();
+    registry.remove_entity(tank).unwrap();
+    run_update_systems(&mut registry, &HashSet::new(), 1.0, None).unwrap();
+    assert!(registry.is_alive(turret));
+    assert_eq!(position(&registry, turret), glam::Vec2::new(14.0, 4.0));
+    assert!(matches!(
+        registry.get_component::<ParentComponent>(turret),
+        Ok(None)
+    ));
+}
+
+#[test]
+fn test_remove_entity_recursive() {
+    let mut registry = Registry::new();
+    let attached = |registry: &mut Registry, parent: Entity| {
+        let child = registry.create_entity();
+        attach(registry, child, parent, glam::Vec2::ZERO);
+        child
+    };
+    let tank = registry.create_entity();
+    let turret = attached(&mut registry, tank);
+    let gun = attached(&mut registry, turret);
+    let other_tank = registry.create_entity();
+    let other_turret = attached(&mut registry, other_tank);
+    remove_entity_recursive(&mut registry, tank).unwrap();
+    assert!(registry.is_dead(tank));
+    assert!(registry.is_dead(turret));
+    assert!(registry.is_dead(gun));
+    assert!(registry.is_alive(other_tank));
+    assert!(registry.is_alive(other_turret));
+    assert!(remove_entity_recursive(&mut registry, tank).is_err());
+
+    // A cycle, which shouldn't happen, is still removed.
+    let a = registry.create_entity();
+    let b = attached(&mut registry, a);
+    attach(&mut registry, a, b, glam::Vec2::ZERO);
+    remove_entity_recursive(&mut registry, a).unwrap();
+    assert!(registry.is_dead(a));
+    assert!(registry.is_dead(b));
+}
@@ -0,0 +1,128 @@
+use pikuma_game_engine::components_systems::{
+    Gravity, Integrator, MovementSystem, RigidBodyComponent,
+};
+use pikuma_game_engine::ecs::{Entity, Registry};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+mod common;
+use common::spawn_body;
+
+/// Simulate a unit mass on a unit spring for 10 seconds, and return the final energy.
+/// The starting energy is 0.5.
+fn spring_energy(integrator: Integrator) -> f32 {
+    let mut registry = Registry::new();
+    let mut movement_system = MovementSystem::new();
+    movement_system.set_integrator(integrator);
+    registry.add_system(Rc::new(RefCell::new(movement_system)));
+    let mass = spawn_body(&mut registry, glam::Vec2::new(1.0, 0.0), glam::Vec2::ZERO);
+    for _ in 0..1000 {
+        let rigid_body: &mut RigidBodyComponent =
+            registry.get_component_mut(mass).unwrap().unwrap();
+        rigid_body.acceleration = -rigid_body.position;
+        registry.run_system::<MovementSystem>(0.01).unwrap();
+    }
+    let rigid_body: &RigidBodyComponent = registry.get_component(mass).unwrap().unwrap();
+    0.5 * rigid_body.velocity.length_squared() + 0.5 * rigid_body.position.length_squared()
+}
+
+#[test]
+fn test_semi_implicit_euler_conserves_energy() {
+    let explicit_drift = (spring_energy(Integrator::ExplicitEuler) - 0.5).abs();
+    let semi_implicit_drift = (spring_energy(Integrator::SemiImplicitEuler) - 0.5).abs();
+    // Explicit Euler gains energy every step, semi-implicit Euler only oscillates around the true energy.
+    assert!(explicit_drift > 0.01);
+    assert!(semi_implicit_drift < 0.01);
+}
+
+fn spawn_at_rest(
+    registry: &mut Registry,
+    acceleration: glam::Vec2,
+    affected_by_gravity: bool,
+) -> Entity {
+    registry
+        .entity_builder()
+        .with(RigidBodyComponent {
+            acceleration,
+            affected_by_gravity,
+            ..RigidBodyComponent::default()
+        })
+        .build()
+}
+
+#[test]
+fn test_constant_acceleration() {
+    let mut registry = Registry::new();
+    let mut movement_system = MovementSystem::new();
+    movement_system.set_integrator(Integrator::SemiImplicitEuler);
+    registry.add_system(Rc::new(RefCell::new(movement_system)));
+    registry.insert_resource(Gravity(glam::Vec2::new(0.0, 10.0)));
+    let pushed = spawn_at_rest(&mut registry, glam::Vec2::new(2.0, 0.0), false);
+    let falling = spawn_at_rest(&mut registry, glam::Vec2::ZERO, true);
+    let pushed_and_falling = spawn_at_rest(&mut registry, glam::Vec2::new(2.0, 0.0), true);
+    let at_rest = spawn_at_rest(&mut registry, glam::Vec2::ZERO, false);
+    let steps = 10;
+    let delta_time = 0.1;
+    for _ in 0..steps {
+        registry.run_system::<MovementSystem>(delta_time).unwrap();
+    }
+    // Semi-implicit Euler moves by the new velocity each step,
+    // so after n steps the position is a * dt^2 * n * (n + 1) / 2.
+    let distance_per_acceleration = delta_time * delta_time * (steps * (steps + 1)) as f32 / 2.0;
+    let position = |entity: Entity| {
+        let rigid_body: &RigidBodyComponent = registry.get_component(entity).unwrap().unwrap();
+        rigid_body.position
+    };
+    let close = |a: glam::Vec2, b: glam::Vec2| (a - b).length() < 1e-4;
+    assert!(close(
+        position(pushed),
+        glam::Vec2::new(2.0 * distance_per_acceleration, 0.0)
+    ));
+    assert!(close(
+        position(falling),
+        glam::Vec2::new(0.0, 10.0 * distance_per_acceleration)
+    ));
+    assert!(close(
+        position(pushed_and_falling),
+        glam::Vec2::new(2.0, 10.0) * distance_per_acceleration
+    ));
+    assert_eq!(position(at_rest), glam::Vec2::ZERO);
+}
+
+#[test]
+fn test_max_speed() {
+    let mut registry = Registry::new();
+    registry.add_system(Rc::new(RefCell::new(MovementSystem::new())));
+    let body = registry
+        .entity_builder()
+        .with(RigidBodyComponent {
+            velocity: glam::Vec2::new(3.0, 0.0),
+            acceleration: glam::Vec2::new(50.0, 50.0),
+            max_speed: Some(20.0),
+            ..RigidBodyComponent::default()
+        })
+        .build();
+    let mut previous_position = glam::Vec2::ZERO;
+    for _ in 0..100 {
+        registry.run_system::<MovementSystem>(0.1).unwrap();
+        let rigid_body: &RigidBodyComponent = registry.get_component(body).unwrap().unwrap();
+        assert!(rigid_body.velocity.length() <= 20.0 + 1e-4);
+        assert!((rigid_body.position - previous_position).length() <= 2.0 + 1e-4);
+        previous_position = rigid_body.position;
+    }
+    // It reached the max speed, rather than stopping short of it.
+    let rigid_body: &RigidBodyComponent = registry.get_component(body).unwrap().unwrap();
+    assert!((rigid_body.velocity.length() - 20.0).abs() < 1e-4);
+}
+
+#[test]
+fn test_load_rigid_body_without_acceleration() {
+    // Saved before bodies could accelerate.
+    let rigid_body: RigidBodyComponent =
+        serde_json::from_str(r#"{"position": [1.0, 2.0], "velocity": [3.0, 4.0]}"#).unwrap();
+    assert_eq!(rigid_body.position, glam::Vec2::new(1.0, 2.0));
+    assert_eq!(rigid_body.velocity, glam::Vec2::new(3.0, 4.0));
+    assert_eq!(rigid_body.acceleration, glam::Vec2::ZERO);
+    assert!(!rigid_body.affected_by_gravity);
+    assert_eq!(rigid_body.max_speed, None);
+}
@@ -0,0 +1,73 @@
+use pikuma_game_engine::components_systems::{
+    KeyBindings, KeyboardControlComponent, KeyboardControlSystem, RigidBodyComponent,
+};
+use pikuma_game_engine::ecs::Registry;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+use winit::keyboard::{KeyCode, PhysicalKey};
+
+/// The velocity of a keyboard controlled entity while the given keys are pressed.
+fn velocity(keys: &[KeyCode]) -> glam::Vec2 {
+    velocity_with_bindings(KeyBindings::default(), keys)
+}
+
+fn velocity_with_bindings(key_bindings: KeyBindings, keys: &[KeyCode]) -> glam::Vec2 {
+    let mut registry = Registry::new();
+    registry.add_system(Rc::new(RefCell::new(KeyboardControlSystem::with_bindings(
+        key_bindings,
+    ))));
+    let player = registry
+        .entity_builder()
+        .with(RigidBodyComponent::default())
+        .with(KeyboardControlComponent)
+        .build();
+    let pressed_keys: HashSet<PhysicalKey> =
+        keys.iter().map(|&key| PhysicalKey::Code(key)).collect();
+    registry
+        .run_system::<KeyboardControlSystem>(&pressed_keys)
+        .unwrap();
+    let rigid_body: &RigidBodyComponent = registry.get_component(player).unwrap().unwrap();
+    rigid_body.velocity
+}
+
+#[test]
+fn test_diagonal_speed() {
+    let velocity = velocity(&[KeyCode::KeyW, KeyCode::KeyD]);
+    assert!((velocity.length() - 80.0).abs() < 1e-4);
+    assert!(velocity.x > 0.0 && velocity.y < 0.0);
+}
+
+#[test]
+fn test_diagonal_speed_matches_cardinal_speed() {
+    let up = velocity(&[KeyCode::KeyW]);
+    let up_right = velocity(&[KeyCode::KeyW, KeyCode::KeyD]);
+    assert_eq!(up, glam::Vec2::new(0.0, -80.0));
+    assert!((up_right.length() - up.length()).abs() < 1e-4);
+    // With no keys, or opposite keys, the entity stops rather than normalizing a zero vector.
+    assert_eq!(velocity(&[]), glam::Vec2::ZERO);
+    assert_eq!(velocity(&[KeyCode::KeyA, KeyCode::KeyD]), glam::Vec2::ZERO);
+}
+
+#[test]
+fn test_remapped_keys() {
+    let arrow_keys = KeyBindings {
+        up: PhysicalKey::Code(KeyCode::ArrowUp),
+        down: PhysicalKey::Code(KeyCode::ArrowDown),
+        left: PhysicalKey::Code(KeyCode::ArrowLeft),
+        right: PhysicalKey::Code(KeyCode::ArrowRight),
+    };
+    assert_eq!(
+        velocity_with_bindings(arrow_keys, &[KeyCode::ArrowLeft]),
+        glam::Vec2::new(-80.0, 0.0)
+    );
+    assert_eq!(
+        velocity_with_bindings(arrow_keys, &[KeyCode::ArrowDown]),
+        glam::Vec2::new(0.0, 80.0)
+    );
+    // The default keys no longer do anything.
+    assert_eq!(
+        velocity_with_bindings(arrow_keys, &[KeyCode::KeyA]),
+        glam::Vec2::ZERO
+    );
+}
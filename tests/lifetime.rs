@@ -0,0 +1,33 @@
+use pikuma_game_engine::components_systems::{LifetimeComponent, LifetimeSystem, NoOpSystem};
+use pikuma_game_engine::ecs::{Registry, SystemBase};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[test]
+fn test_entity_despawns_when_lifetime_elapses() {
+    let mut registry = Registry::new();
+    registry.add_system(Rc::new(RefCell::new(LifetimeSystem::new())));
+    let mut watcher = NoOpSystem::new();
+    watcher.require_component::<LifetimeComponent>();
+    let watcher = Rc::new(RefCell::new(watcher));
+    registry.add_system(Rc::clone(&watcher));
+    let bullet = registry
+        .entity_builder()
+        .with(LifetimeComponent { remaining: 1.0 })
+        .build();
+    let entity_count = registry.entity_count();
+
+    registry.run_system::<LifetimeSystem>(0.4).unwrap();
+    registry.run_system::<LifetimeSystem>(0.4).unwrap();
+    assert!(registry.is_alive(bullet));
+    assert_eq!(watcher.borrow().entities(), vec![bullet]);
+
+    registry.run_system::<LifetimeSystem>(0.4).unwrap();
+    assert!(registry.is_dead(bullet));
+    assert_eq!(registry.entity_count(), entity_count - 1);
+    assert!(watcher.borrow().entities().is_empty());
+
+    // The entity is only removed once, so later runs don't try to remove it again.
+    registry.run_system::<LifetimeSystem>(0.4).unwrap();
+    assert_eq!(registry.entity_count(), entity_count - 1);
+}
@@ -0,0 +1,62 @@
+use pikuma_game_engine::components_systems::{
+    Layer, MotionAnimationComponent, MotionAnimationSystem, RigidBodyComponent, SpriteComponent,
+};
+use pikuma_game_engine::ecs::{Entity, Registry};
+use pikuma_game_engine::renderer::SpriteIndex;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// An entity with only right and down frames.
+fn setup(sprite_flip_x: bool) -> (Registry, Entity) {
+    let mut registry = Registry::new();
+    registry.add_system(Rc::new(RefCell::new(MotionAnimationSystem::new())));
+    let mut sprite_component = SpriteComponent::new(
+        SpriteIndex::for_tests(0),
+        Layer::Ground,
+        glam::Vec2::new(16.0, 16.0),
+    );
+    sprite_component.flip_x = sprite_flip_x;
+    let entity = registry
+        .entity_builder()
+        .with(RigidBodyComponent::default())
+        .with(sprite_component)
+        .with(MotionAnimationComponent::new(
+            0.1,
+            vec![],
+            vec![SpriteIndex::for_tests(3)],
+            vec![SpriteIndex::for_tests(1), SpriteIndex::for_tests(2)],
+            vec![],
+        ))
+        .build();
+    (registry, entity)
+}
+
+/// Move with the velocity for a frame, and return the sprite's flips.
+fn step(registry: &mut Registry, entity: Entity, velocity: glam::Vec2) -> (bool, bool) {
+    registry
+        .get_component_mut::<RigidBodyComponent>(entity)
+        .unwrap()
+        .unwrap()
+        .velocity = velocity;
+    registry.run_system::<MotionAnimationSystem>(0.15).unwrap();
+    let sprite_component: &SpriteComponent = registry.get_component(entity).unwrap().unwrap();
+    (sprite_component.flip_x, sprite_component.flip_y)
+}
+
+#[test]
+fn test_missing_direction_flips_opposite_frames() {
+    let (mut registry, entity) = setup(false);
+    assert_eq!(step(&mut registry, entity, glam::Vec2::X), (false, false));
+    assert_eq!(step(&mut registry, entity, -glam::Vec2::X), (true, false));
+    assert_eq!(step(&mut registry, entity, -glam::Vec2::Y), (false, true));
+    assert_eq!(step(&mut registry, entity, glam::Vec2::Y), (false, false));
+}
+
+#[test]
+fn test_keeps_sprite_flips() {
+    // A sprite drawn facing left, so the frames for moving right are flipped.
+    let (mut registry, entity) = setup(true);
+    assert_eq!(step(&mut registry, entity, glam::Vec2::X), (true, false));
+    assert_eq!(step(&mut registry, entity, -glam::Vec2::X), (false, false));
+    assert_eq!(step(&mut registry, entity, glam::Vec2::Y), (true, false));
+}
@@ -0,0 +1,50 @@
+use pikuma_game_engine::components_systems::{nearest_with, RigidBodyComponent};
+use pikuma_game_engine::ecs::Registry;
+
+#[test]
+fn test_nearest_with() {
+    #[derive(Clone)]
+    struct PlayerComponent;
+
+    let mut registry = Registry::new();
+    let at = |registry: &mut Registry, x: f32| {
+        registry
+            .entity_builder()
+            .with(RigidBodyComponent::at(glam::Vec2::new(x, 0.0)))
+            .build()
+    };
+    assert_eq!(
+        nearest_with::<PlayerComponent>(&registry, glam::Vec2::ZERO, None),
+        None
+    );
+    let near = at(&mut registry, 10.0);
+    let far = at(&mut registry, -20.0);
+    let player_0 = at(&mut registry, 30.0);
+    let player_1 = at(&mut registry, -40.0);
+    registry.add_component(player_0, PlayerComponent).unwrap();
+    registry.add_component(player_1, PlayerComponent).unwrap();
+    assert_eq!(
+        nearest_with::<RigidBodyComponent>(&registry, glam::Vec2::ZERO, None),
+        Some(near)
+    );
+    assert_eq!(
+        nearest_with::<RigidBodyComponent>(&registry, glam::Vec2::new(-25.0, 0.0), None),
+        Some(far)
+    );
+    assert_eq!(
+        nearest_with::<PlayerComponent>(&registry, glam::Vec2::ZERO, None),
+        Some(player_0)
+    );
+    assert_eq!(
+        nearest_with::<PlayerComponent>(&registry, glam::Vec2::new(-15.0, 0.0), None),
+        Some(player_1)
+    );
+    assert_eq!(
+        nearest_with::<PlayerComponent>(&registry, glam::Vec2::ZERO, Some(30.0)),
+        Some(player_0)
+    );
+    assert_eq!(
+        nearest_with::<PlayerComponent>(&registry, glam::Vec2::ZERO, Some(29.0)),
+        None
+    );
+}
@@ -0,0 +1,98 @@
+use pikuma_game_engine::components_systems::{
+    ColliderShape, CollisionComponent, CollisionEvent, CollisionSystem, Layer, NoOpSystem,
+    ProjectileComponent, ProjectileEmitterComponent, ProjectileEmitterSystem, RigidBodyComponent,
+    SpriteComponent,
+};
+use pikuma_game_engine::ecs::{Registry, SystemBase};
+use pikuma_game_engine::renderer::SpriteIndex;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+fn setup() -> (Registry, Rc<RefCell<NoOpSystem>>) {
+    let mut registry = Registry::new();
+    registry.add_system(Rc::new(RefCell::new(ProjectileEmitterSystem::new())));
+    let mut projectiles = NoOpSystem::new();
+    projectiles.require_component::<ProjectileComponent>();
+    let projectiles = Rc::new(RefCell::new(projectiles));
+    registry.add_system(Rc::clone(&projectiles));
+    registry
+        .entity_builder()
+        .with(RigidBodyComponent::at(glam::Vec2::new(10.0, 20.0)))
+        .with(ProjectileEmitterComponent {
+            velocity: glam::Vec2::new(0.0, -50.0),
+            repeat_frequency: 0.5,
+            projectile_duration: 2.0,
+            percent_damage: 10.0,
+            is_friendly: true,
+            projectile_sprite: SpriteComponent::new(
+                SpriteIndex::for_tests(0),
+                Layer::Air,
+                glam::Vec2::new(4.0, 4.0),
+            ),
+            time_since_last_emission: 0.0,
+        })
+        .build();
+    (registry, projectiles)
+}
+
+#[test]
+fn test_emits_at_repeat_frequency() {
+    let (mut registry, projectiles) = setup();
+    registry.run_system::<ProjectileEmitterSystem>(0.4).unwrap();
+    assert_eq!(projectiles.borrow().entities().len(), 0);
+    registry.run_system::<ProjectileEmitterSystem>(0.4).unwrap();
+    assert_eq!(projectiles.borrow().entities().len(), 1);
+    for _ in 0..10 {
+        registry.run_system::<ProjectileEmitterSystem>(0.1).unwrap();
+    }
+    assert_eq!(projectiles.borrow().entities().len(), 3);
+    // A long frame fires every projectile that was due.
+    registry.run_system::<ProjectileEmitterSystem>(1.5).unwrap();
+    assert_eq!(projectiles.borrow().entities().len(), 6);
+}
+
+#[test]
+fn test_projectile_initial_velocity() {
+    let (mut registry, projectiles) = setup();
+    registry.run_system::<ProjectileEmitterSystem>(0.5).unwrap();
+    let projectile = projectiles.borrow().entities()[0];
+    let rigid_body: &RigidBodyComponent = registry.get_component(projectile).unwrap().unwrap();
+    assert_eq!(rigid_body.position, glam::Vec2::new(10.0, 20.0));
+    assert_eq!(rigid_body.velocity, glam::Vec2::new(0.0, -50.0));
+    let projectile_component: &ProjectileComponent =
+        registry.get_component(projectile).unwrap().unwrap();
+    assert!(projectile_component.is_friendly);
+}
+
+#[test]
+fn test_projectile_ignores_owner() {
+    let (mut registry, projectiles) = setup();
+    let collision_system = Rc::new(RefCell::new(CollisionSystem::new()));
+    registry.add_system(Rc::clone(&collision_system));
+    registry.add_handler::<CollisionEvent, _>(Rc::clone(&collision_system));
+    let emitter = registry
+        .system_entities::<ProjectileEmitterSystem>()
+        .unwrap()
+        .next()
+        .unwrap();
+    registry
+        .add_component(
+            emitter,
+            CollisionComponent {
+                offset: glam::Vec2::ZERO,
+                shape: ColliderShape::Rectangle {
+                    width_height: glam::Vec2::new(16.0, 16.0),
+                },
+            },
+        )
+        .unwrap();
+    registry.run_system::<ProjectileEmitterSystem>(0.5).unwrap();
+    let projectile = projectiles.borrow().entities()[0];
+    let projectile_component: &ProjectileComponent =
+        registry.get_component(projectile).unwrap().unwrap();
+    assert_eq!(projectile_component.owner, Some(emitter));
+    registry.run_system::<CollisionSystem>(()).unwrap();
+    assert!(!collision_system.borrow().is_colliding(emitter));
+    assert!(registry.is_alive(emitter));
+    assert!(registry.is_alive(projectile));
+}
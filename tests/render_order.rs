@@ -0,0 +1,39 @@
+use pikuma_game_engine::components_systems::{
+    draw_order, Layer, RigidBodyComponent, SpriteComponent,
+};
+use pikuma_game_engine::renderer::{AlphaMode, SpriteIndex};
+
+/// A sprite whose width identifies it after sorting.
+fn sprite(id: f32, sprite_layer: Layer, alpha_mode: AlphaMode) -> SpriteComponent {
+    let mut sprite_component = SpriteComponent::new(
+        SpriteIndex::for_tests(0),
+        sprite_layer,
+        glam::Vec2::new(id, 1.0),
+    );
+    sprite_component.alpha_mode = alpha_mode;
+    sprite_component
+}
+
+#[test]
+fn test_draw_order() {
+    let rigid_body_component = RigidBodyComponent::default();
+    let sprites = [
+        sprite(1.0, Layer::Air, AlphaMode::Blend),
+        sprite(2.0, Layer::Air, AlphaMode::Cutout),
+        sprite(3.0, Layer::Background, AlphaMode::Blend),
+        sprite(4.0, Layer::Background, AlphaMode::Cutout),
+        sprite(5.0, Layer::Ground, AlphaMode::Blend),
+        sprite(6.0, Layer::Ground, AlphaMode::Cutout),
+    ];
+    let components = sprites
+        .iter()
+        .map(|sprite_component| (&rigid_body_component, sprite_component))
+        .collect();
+    let ids: Vec<f32> = draw_order(components)
+        .into_iter()
+        .map(|(_, sprite_component)| sprite_component.size.x)
+        .collect();
+    // Cutout sprites keep their order, the depth buffer sorts them.
+    // Blended sprites come after them, back to front.
+    assert_eq!(ids, vec![2.0, 4.0, 6.0, 3.0, 5.0, 1.0]);
+}
@@ -0,0 +1,62 @@
+use pikuma_game_engine::components_systems::{
+    add_update_systems, run_update_systems, RigidBodyComponent, SeekComponent,
+};
+use pikuma_game_engine::ecs::Registry;
+use std::collections::HashSet;
+
+mod common;
+use common::spawn_body;
+
+#[test]
+fn test_seeker_reaches_target() {
+    let mut registry = Registry::new();
+    add_update_systems(&mut registry);
+    let player = spawn_body(&mut registry, glam::Vec2::new(30.0, 40.0), glam::Vec2::ZERO);
+    let enemy = spawn_body(&mut registry, glam::Vec2::ZERO, glam::Vec2::ZERO);
+    registry
+        .add_component(enemy, SeekComponent::new(player, 10.0))
+        .unwrap();
+    run_update_systems(&mut registry, &HashSet::new(), 0.1, None).unwrap();
+    let rigid_body: &RigidBodyComponent = registry.get_component(enemy).unwrap().unwrap();
+    assert!((rigid_body.velocity - glam::Vec2::new(6.0, 8.0)).length() < 1e-4);
+    for _ in 0..60 {
+        run_update_systems(&mut registry, &HashSet::new(), 0.1, None).unwrap();
+    }
+    let rigid_body: &RigidBodyComponent = registry.get_component(enemy).unwrap().unwrap();
+    assert!((rigid_body.position - glam::Vec2::new(30.0, 40.0)).length() < 1.0);
+}
+
+#[test]
+fn test_seeker_turns_smoothly() {
+    let mut registry = Registry::new();
+    add_update_systems(&mut registry);
+    let player = spawn_body(&mut registry, glam::Vec2::new(100.0, 0.0), glam::Vec2::ZERO);
+    let enemy = spawn_body(&mut registry, glam::Vec2::ZERO, glam::Vec2::ZERO);
+    let mut seek_component = SeekComponent::new(player, 10.0);
+    seek_component.max_acceleration = Some(5.0);
+    registry.add_component(enemy, seek_component).unwrap();
+    run_update_systems(&mut registry, &HashSet::new(), 0.1, None).unwrap();
+    let rigid_body: &RigidBodyComponent = registry.get_component(enemy).unwrap().unwrap();
+    assert_eq!(rigid_body.acceleration, glam::Vec2::new(5.0, 0.0));
+    assert!(rigid_body.velocity.length() < 10.0);
+}
+
+#[test]
+fn test_seeker_stops_when_target_dies() {
+    let mut registry = Registry::new();
+    add_update_systems(&mut registry);
+    let player = spawn_body(&mut registry, glam::Vec2::new(100.0, 0.0), glam::Vec2::ZERO);
+    let enemy = spawn_body(&mut registry, glam::Vec2::ZERO, glam::Vec2::ZERO);
+    registry
+        .add_component(enemy, SeekComponent::new(player, 10.0))
+        .unwrap();
+    run_update_systems(&mut registry, &HashSet::new(), 0.1, None).unwrap();
+    registry.remove_entity(player).unwrap();
+    run_update_systems(&mut registry, &HashSet::new(), 0.1, None).unwrap();
+    let rigid_body: &RigidBodyComponent = registry.get_component(enemy).unwrap().unwrap();
+    assert_eq!(rigid_body.velocity, glam::Vec2::ZERO);
+    assert!(matches!(
+        registry.get_component::<SeekComponent>(enemy),
+        Ok(None)
+    ));
+}
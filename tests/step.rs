@@ -0,0 +1,197 @@
+use pikuma_game_engine::components_systems::{
+    add_update_systems, run_update_systems, set_simulation_paused, ColliderShape,
+    CollisionComponent, CollisionRenderSystem, CollisionSystem, MovementSystem, RigidBodyComponent,
+    WorldBounds,
+};
+use pikuma_game_engine::ecs::Registry;
+use pikuma_game_engine::geometry::Rectangle;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+mod common;
+use common::spawn_body;
+
+#[test]
+fn test_tank_moves_after_one_second() {
+    let mut registry = Registry::new();
+    add_update_systems(&mut registry);
+    let tank = spawn_body(
+        &mut registry,
+        glam::Vec2::new(0.0, 5.0),
+        glam::Vec2::new(10.0, 0.0),
+    );
+    for _ in 0..10 {
+        run_update_systems(&mut registry, &HashSet::new(), 0.1, None).unwrap();
+    }
+    let rigid_body: &RigidBodyComponent = registry.get_component(tank).unwrap().unwrap();
+    assert!((rigid_body.position - glam::Vec2::new(10.0, 5.0)).length() < 1e-4);
+}
+
+#[test]
+fn test_wraparound() {
+    let mut registry = Registry::new();
+    let mut movement_system = MovementSystem::new();
+    movement_system.set_wraparound(true);
+    registry.add_system(Rc::new(RefCell::new(movement_system)));
+    registry.insert_resource(WorldBounds(Rectangle::new(
+        glam::Vec2::ZERO,
+        glam::Vec2::new(100.0, 100.0),
+    )));
+    let asteroid = spawn_body(
+        &mut registry,
+        glam::Vec2::new(95.0, 50.0),
+        glam::Vec2::new(10.0, 0.0),
+    );
+    registry.run_system::<MovementSystem>(1.0).unwrap();
+    let rigid_body: &RigidBodyComponent = registry.get_component(asteroid).unwrap().unwrap();
+    assert!((rigid_body.position - glam::Vec2::new(5.0, 50.0)).length() < 1e-4);
+}
+
+#[test]
+fn test_colliding_entities_are_removed() {
+    let mut registry = Registry::new();
+    add_update_systems(&mut registry);
+    let mut spawn = |position: glam::Vec2, velocity: glam::Vec2| {
+        registry
+            .entity_builder()
+            .with(RigidBodyComponent {
+                velocity,
+                ..RigidBodyComponent::at(position)
+            })
+            .with(CollisionComponent {
+                offset: glam::Vec2::ZERO,
+                shape: ColliderShape::Rectangle {
+                    width_height: glam::Vec2::new(4.0, 4.0),
+                },
+            })
+            .build()
+    };
+    let tank_1 = spawn(glam::Vec2::new(0.0, 0.0), glam::Vec2::new(10.0, 0.0));
+    let tank_2 = spawn(glam::Vec2::new(20.0, 0.0), glam::Vec2::new(-10.0, 0.0));
+    run_update_systems(&mut registry, &HashSet::new(), 0.1, None).unwrap();
+    assert!(registry.is_alive(tank_1));
+    assert!(registry.is_alive(tank_2));
+    for _ in 0..10 {
+        run_update_systems(&mut registry, &HashSet::new(), 0.1, None).unwrap();
+    }
+    assert!(registry.is_dead(tank_1));
+    assert!(registry.is_dead(tank_2));
+}
+
+#[test]
+fn test_is_colliding() {
+    let mut registry = Registry::new();
+    let collision_system = Rc::new(RefCell::new(CollisionSystem::new()));
+    registry.add_system(Rc::clone(&collision_system));
+    let mut spawn = |position: glam::Vec2| {
+        registry
+            .entity_builder()
+            .with(RigidBodyComponent::at(position))
+            .with(CollisionComponent {
+                offset: glam::Vec2::ZERO,
+                shape: ColliderShape::Rectangle {
+                    width_height: glam::Vec2::new(4.0, 4.0),
+                },
+            })
+            .build()
+    };
+    let player = spawn(glam::Vec2::new(0.0, 0.0));
+    let ground = spawn(glam::Vec2::new(2.0, 2.0));
+    let rock = spawn(glam::Vec2::new(20.0, 0.0));
+    registry.run_system::<CollisionSystem>(()).unwrap();
+    let collision_system = collision_system.borrow();
+    assert!(collision_system.is_colliding(player));
+    assert!(collision_system.is_colliding(ground));
+    assert!(!collision_system.is_colliding(rock));
+    assert_eq!(
+        collision_system.collisions_for(player).collect::<Vec<_>>(),
+        vec![ground]
+    );
+    assert_eq!(collision_system.collisions_for(rock).count(), 0);
+}
+
+#[test]
+fn test_keyed_collision_boxes() {
+    let mut registry = Registry::new();
+    let collision_system = Rc::new(RefCell::new(CollisionSystem::new()));
+    registry.add_system(Rc::clone(&collision_system));
+    let player = spawn_body(&mut registry, glam::Vec2::ZERO, glam::Vec2::ZERO);
+    let rock = spawn_body(&mut registry, glam::Vec2::new(20.0, 0.0), glam::Vec2::ZERO);
+    registry
+        .add_component(
+            rock,
+            CollisionComponent {
+                offset: glam::Vec2::ZERO,
+                shape: ColliderShape::Rectangle {
+                    width_height: glam::Vec2::new(4.0, 4.0),
+                },
+            },
+        )
+        .unwrap();
+    // A body and a long reach, as separate boxes.
+    for (key, width_height) in [
+        (0, glam::Vec2::new(4.0, 4.0)),
+        (1, glam::Vec2::new(30.0, 1.0)),
+    ] {
+        registry
+            .add_component_keyed(
+                player,
+                key,
+                CollisionComponent {
+                    offset: glam::Vec2::ZERO,
+                    shape: ColliderShape::Rectangle { width_height },
+                },
+            )
+            .unwrap();
+    }
+    assert_eq!(
+        registry
+            .get_components::<CollisionComponent>(player)
+            .unwrap()
+            .count(),
+        2
+    );
+    registry.run_system::<CollisionSystem>(()).unwrap();
+    assert!(collision_system.borrow().is_colliding(rock));
+}
+
+#[test]
+fn test_paused_simulation() {
+    let mut registry = Registry::new();
+    add_update_systems(&mut registry);
+    let tank = spawn_body(
+        &mut registry,
+        glam::Vec2::new(0.0, 5.0),
+        glam::Vec2::new(10.0, 0.0),
+    );
+    set_simulation_paused(&mut registry, true).unwrap();
+    assert!(!registry.is_system_enabled::<MovementSystem>());
+    assert!(!registry.is_system_enabled::<CollisionSystem>());
+    // Debug drawing still runs, so the frozen scene looks the same.
+    assert!(registry.is_system_enabled::<CollisionRenderSystem>());
+    run_update_systems(&mut registry, &HashSet::new(), 1.0, None).unwrap();
+    let rigid_body: &RigidBodyComponent = registry.get_component(tank).unwrap().unwrap();
+    assert_eq!(rigid_body.position, glam::Vec2::new(0.0, 5.0));
+
+    set_simulation_paused(&mut registry, false).unwrap();
+    run_update_systems(&mut registry, &HashSet::new(), 1.0, None).unwrap();
+    let rigid_body: &RigidBodyComponent = registry.get_component(tank).unwrap().unwrap();
+    assert!((rigid_body.position - glam::Vec2::new(10.0, 5.0)).length() < 1e-4);
+}
+
+#[test]
+fn test_resume_keeps_disabled_systems() {
+    let mut registry = Registry::new();
+    add_update_systems(&mut registry);
+    // The game turned collisions off itself, before pausing.
+    registry
+        .set_system_enabled::<CollisionSystem>(false)
+        .unwrap();
+    set_simulation_paused(&mut registry, true).unwrap();
+    // Pausing twice doesn't forget which systems were running.
+    set_simulation_paused(&mut registry, true).unwrap();
+    set_simulation_paused(&mut registry, false).unwrap();
+    assert!(registry.is_system_enabled::<MovementSystem>());
+    assert!(!registry.is_system_enabled::<CollisionSystem>());
+}
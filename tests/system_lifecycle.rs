@@ -0,0 +1,56 @@
+use pikuma_game_engine::components_systems::{NoOpSystem, RigidBodyComponent};
+use pikuma_game_engine::ecs::{EcsError, Registry};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[test]
+fn test_system_lifecycle() {
+    let mut registry = Registry::new();
+    assert!(matches!(
+        registry.run_system::<NoOpSystem>(()),
+        Err(EcsError::NoSuchSystem)
+    ));
+
+    let entity_a = registry.create_entity();
+    registry
+        .add_component(entity_a, RigidBodyComponent::default())
+        .unwrap();
+    let entity_b = registry.create_entity();
+
+    let mut system = NoOpSystem::new();
+    system.require_component::<RigidBodyComponent>();
+    let system = Rc::new(RefCell::new(system));
+    registry.add_system(Rc::clone(&system));
+    assert_eq!(
+        registry
+            .system_entities::<NoOpSystem>()
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![entity_a]
+    );
+
+    registry
+        .add_component(entity_b, RigidBodyComponent::default())
+        .unwrap();
+    assert_eq!(registry.system_entities::<NoOpSystem>().unwrap().count(), 2);
+    registry.remove_entity(entity_a).unwrap();
+    assert_eq!(
+        registry
+            .system_entities::<NoOpSystem>()
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![entity_b]
+    );
+
+    registry.run_system::<NoOpSystem>(()).unwrap();
+    registry.run_system::<NoOpSystem>(()).unwrap();
+    assert_eq!(system.borrow().run_count(), 2);
+
+    registry.remove_system::<NoOpSystem>();
+    assert!(registry.system_entities::<NoOpSystem>().is_none());
+    assert!(matches!(
+        registry.run_system::<NoOpSystem>(()),
+        Err(EcsError::NoSuchSystem)
+    ));
+    assert_eq!(system.borrow().run_count(), 2);
+}
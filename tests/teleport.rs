@@ -0,0 +1,43 @@
+use pikuma_game_engine::components_systems::{
+    teleport, MotionAnimationComponent, RigidBodyComponent,
+};
+use pikuma_game_engine::ecs::{EcsError, Registry};
+
+#[test]
+fn test_teleport() {
+    let mut registry = Registry::new();
+    let player = registry
+        .entity_builder()
+        .with(RigidBodyComponent {
+            velocity: glam::Vec2::new(0.0, -10.0),
+            ..RigidBodyComponent::default()
+        })
+        .with(MotionAnimationComponent::new(
+            0.1,
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+        ))
+        .build();
+    {
+        let motion_animation_component: &mut MotionAnimationComponent =
+            registry.get_component_mut(player).unwrap().unwrap();
+        motion_animation_component.last_velocity = glam::Vec2::new(0.0, -10.0);
+        motion_animation_component.current_frame = 2;
+    }
+    teleport(&mut registry, player, glam::Vec2::new(100.0, 50.0)).unwrap();
+    let rigid_body_component: &RigidBodyComponent =
+        registry.get_component(player).unwrap().unwrap();
+    assert_eq!(rigid_body_component.position, glam::Vec2::new(100.0, 50.0));
+    let motion_animation_component: &MotionAnimationComponent =
+        registry.get_component(player).unwrap().unwrap();
+    assert_eq!(motion_animation_component.last_velocity, glam::Vec2::ZERO);
+    assert_eq!(motion_animation_component.current_frame, 0);
+
+    let marker = registry.create_entity();
+    assert!(matches!(
+        teleport(&mut registry, marker, glam::Vec2::ZERO),
+        Err(EcsError::NoSuchComponent)
+    ));
+}